@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Error};
+use bdk_wallet::{
+    bitcoin::{
+        base64::{prelude::BASE64_STANDARD, Engine},
+        bip32::{ChainCode, ChildNumber, Xpriv},
+        hashes::{hmac, sha512, Hash, HashEngine},
+        secp256k1::{Secp256k1, SecretKey},
+        PrivateKey,
+    },
+    keys::bip39::{Language, Mnemonic},
+};
+
+/// Purpose of the BIP85 derivation paths, `m/83696968'`.
+const BIP85_PURPOSE: u32 = 83696968;
+/// Key of the HMAC turning a derived key into entropy.
+const BIP85_HMAC_KEY: &[u8] = b"bip-entropy-from-k";
+
+const APP_BIP39: u32 = 39;
+const APP_WIF: u32 = 2;
+const APP_XPRV: u32 = 32;
+const APP_HEX: u32 = 128169;
+const APP_PWD_BASE64: u32 = 707764;
+
+/// Derive the 64 bytes of BIP85 entropy of the application at `path`, below `m/83696968'`.
+pub fn derive_entropy(root: &Xpriv, path: &[u32]) -> Result<[u8; 64], Error> {
+    let path = std::iter::once(BIP85_PURPOSE)
+        .chain(path.iter().copied())
+        .map(ChildNumber::from_hardened_idx)
+        .collect::<Result<Vec<_>, _>>()?;
+    let derived = root.derive_priv(&Secp256k1::new(), &path)?;
+
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(BIP85_HMAC_KEY);
+    engine.input(&derived.private_key.secret_bytes());
+    Ok(hmac::Hmac::<sha512::Hash>::from_engine(engine).to_byte_array())
+}
+
+/// Derive the child BIP39 mnemonic of `word_count` words (12, 18 or 24) at `index`.
+pub fn derive_mnemonic(root: &Xpriv, language: Language, word_count: u32, index: u32) -> Result<Mnemonic, Error> {
+    if ![12, 18, 24].contains(&word_count) {
+        return Err(anyhow!("Unsupported word count {word_count}, must be 12, 18 or 24"));
+    }
+
+    let entropy = derive_entropy(root, &[APP_BIP39, language_code(language), word_count, index])?;
+    let length = word_count as usize * 4 / 3;
+    Ok(Mnemonic::from_entropy_in(language, &entropy[..length])?)
+}
+
+/// Derive the child private key at `index`, for the network of `root`.
+pub fn derive_wif(root: &Xpriv, index: u32) -> Result<PrivateKey, Error> {
+    let entropy = derive_entropy(root, &[APP_WIF, index])?;
+    let secret_key = SecretKey::from_slice(&entropy[..32])?;
+    Ok(PrivateKey::new(secret_key, root.network))
+}
+
+/// Derive the child extended private key at `index`, for the network of `root`.
+pub fn derive_xpriv(root: &Xpriv, index: u32) -> Result<Xpriv, Error> {
+    let entropy = derive_entropy(root, &[APP_XPRV, index])?;
+    let chain_code: [u8; 32] = entropy[..32].try_into()?;
+
+    Ok(Xpriv {
+        network: root.network,
+        depth: 0,
+        parent_fingerprint: Default::default(),
+        child_number: ChildNumber::from_normal_idx(0)?,
+        private_key: SecretKey::from_slice(&entropy[32..])?,
+        chain_code: ChainCode::from(chain_code),
+    })
+}
+
+/// Derive `num_bytes` (16 to 64) bytes of child entropy at `index`.
+pub fn derive_hex(root: &Xpriv, num_bytes: u32, index: u32) -> Result<Vec<u8>, Error> {
+    if !(16..=64).contains(&num_bytes) {
+        return Err(anyhow!(
+            "Unsupported number of bytes {num_bytes}, must be between 16 and 64"
+        ));
+    }
+
+    let entropy = derive_entropy(root, &[APP_HEX, num_bytes, index])?;
+    Ok(entropy[..num_bytes as usize].to_vec())
+}
+
+/// Derive a base64 password of `length` (20 to 86) characters at `index`.
+pub fn derive_password(root: &Xpriv, length: u32, index: u32) -> Result<String, Error> {
+    if !(20..=86).contains(&length) {
+        return Err(anyhow!(
+            "Unsupported password length {length}, must be between 20 and 86"
+        ));
+    }
+
+    let entropy = derive_entropy(root, &[APP_PWD_BASE64, length, index])?;
+    let mut password = BASE64_STANDARD.encode(entropy);
+    password.truncate(length as usize);
+    Ok(password)
+}
+
+/// The BIP85 code of a BIP39 wordlist.
+fn language_code(language: Language) -> u32 {
+    match language {
+        Language::English => 0,
+        Language::Japanese => 1,
+        Language::Korean => 2,
+        Language::Spanish => 3,
+        Language::SimplifiedChinese => 4,
+        Language::TraditionalChinese => 5,
+        Language::French => 6,
+        Language::Italian => 7,
+        Language::Czech => 8,
+        Language::Portuguese => 9,
+    }
+}
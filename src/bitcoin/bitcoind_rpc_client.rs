@@ -0,0 +1,249 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+};
+
+use bdk_wallet::{
+    bitcoin::{
+        base64::{engine::general_purpose::STANDARD, Engine},
+        consensus::encode::{deserialize_hex, serialize_hex},
+        Block as BdkBlock, BlockHash, Transaction as BdkTransaction, Txid as BdkTxid,
+    },
+    chain::{BlockId as BdkBlockId, CheckPoint as BdkCheckPoint},
+    serde_json::{self, json, Value},
+};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{js_sys::Date, Request, RequestInit};
+
+use crate::{
+    bitcoin::{UnconfirmedTx, Wallet},
+    types::{BdkError, BdkErrorCode, EvictedTx, Transaction, WalletEvent},
+    utils::fetch::fetch,
+};
+
+/// Bitcoin Core JSON-RPC client, using `fetch`.
+///
+/// Syncs a wallet block by block from its latest checkpoint, and from the node's mempool. Meant
+/// for regtest development and self-hosted nodes: every block and mempool transaction is
+/// downloaded. Bitcoin Core does not send CORS headers, so browsers need to go through a proxy.
+#[wasm_bindgen]
+pub struct BitcoindRpcClient {
+    url: String,
+    authorization: Option<String>,
+    next_id: Cell<u64>,
+    /// Mempool transactions already downloaded, so they are not fetched again. All of them are
+    /// applied at each sync, as they may be relevant to another wallet or to scripts revealed since.
+    mempool: RefCell<HashMap<BdkTxid, BdkTransaction>>,
+    /// Events of the blocks applied by a `sync_blocks` that then failed, returned by the next one.
+    undelivered_events: RefCell<Vec<WalletEvent>>,
+}
+
+#[wasm_bindgen]
+impl BitcoindRpcClient {
+    /// Create a client for the node at `url`, authenticating with `rpcuser`/`rpcpassword` (or the
+    /// content of the `.cookie` file split on `:`) if given.
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> BitcoindRpcClient {
+        let authorization = username.map(|username| {
+            let credentials = format!("{username}:{}", password.unwrap_or_default());
+            format!("Basic {}", STANDARD.encode(credentials))
+        });
+        BitcoindRpcClient {
+            url: url.to_string(),
+            authorization,
+            next_id: Cell::new(0),
+            mempool: RefCell::new(HashMap::new()),
+            undelivered_events: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Apply the blocks from the wallet's latest checkpoint up to the node's tip.
+    ///
+    /// Reorgs are handled by going back to the last block both chains agree on. If given, blocks
+    /// below `start_height` are skipped, which avoids downloading the whole chain for a new
+    /// wallet whose descriptors were never used before that height.
+    ///
+    /// Returns the `WalletEvent`s of all the applied blocks. When a block fails, the blocks before
+    /// it stay applied and their events are returned by the next `sync_blocks`.
+    pub async fn sync_blocks(&self, wallet: &Wallet, start_height: Option<u32>) -> Result<Vec<WalletEvent>, BdkError> {
+        let mut events = self.undelivered_events.take();
+
+        match self.apply_blocks(wallet, start_height, &mut events).await {
+            Ok(()) => Ok(events),
+            Err(e) => {
+                self.undelivered_events.replace(events);
+                Err(e)
+            }
+        }
+    }
+
+    /// Apply the transactions of the node's mempool, and mark the wallet's unconfirmed
+    /// transactions that left it as evicted.
+    ///
+    /// Run it after `sync_blocks`, so transactions that left the mempool because they were
+    /// confirmed are not considered evicted.
+    pub async fn sync_mempool(&self, wallet: &Wallet) -> Result<(), BdkError> {
+        let now = (Date::now() / 1000.0) as u64;
+        let mempool: HashSet<BdkTxid> = self.call("getrawmempool", json!([])).await?;
+
+        for txid in &mempool {
+            if self.mempool.borrow().contains_key(txid) {
+                continue;
+            }
+            // The transaction may have been mined or evicted since `getrawmempool`.
+            let hex: String = match self.call("getrawtransaction", json!([txid])).await {
+                Ok(hex) => hex,
+                Err(_) => continue,
+            };
+            let tx: BdkTransaction =
+                deserialize_hex(&hex).map_err(|e| rpc_error(format!("Invalid transaction {txid}: {e}"), ()))?;
+            self.mempool.borrow_mut().insert(*txid, tx);
+        }
+        self.mempool.borrow_mut().retain(|txid, _| mempool.contains(txid));
+
+        // The wallet only keeps the transactions relevant to its scripts.
+        let unconfirmed_txs = self
+            .mempool
+            .borrow()
+            .values()
+            .map(|tx| UnconfirmedTx::new(tx.clone().into(), now))
+            .collect();
+        wallet.apply_unconfirmed_txs(unconfirmed_txs);
+
+        let evicted_txs = wallet
+            .transactions()
+            .into_iter()
+            .filter(|tx| !tx.chain_position().is_confirmed() && !mempool.contains(&*tx.txid()))
+            .map(|tx| EvictedTx::new(tx.txid(), now))
+            .collect::<Vec<_>>();
+        if !evicted_txs.is_empty() {
+            wallet.apply_evicted_txs(evicted_txs);
+        }
+
+        Ok(())
+    }
+
+    /// Run `sync_blocks` then `sync_mempool`, returning the events of the applied blocks. If
+    /// `sync_mempool` fails, these events are returned by the next sync.
+    pub async fn sync(&self, wallet: &Wallet, start_height: Option<u32>) -> Result<Vec<WalletEvent>, BdkError> {
+        let events = self.sync_blocks(wallet, start_height).await?;
+        if let Err(e) = self.sync_mempool(wallet).await {
+            // The blocks are applied, so their events are returned by the next sync instead.
+            self.undelivered_events.replace(events);
+            return Err(e);
+        }
+        Ok(events)
+    }
+
+    pub async fn broadcast(&self, transaction: &Transaction) -> Result<(), BdkError> {
+        let _: BdkTxid = self
+            .call("sendrawtransaction", json!([serialize_hex(&**transaction)]))
+            .await?;
+        Ok(())
+    }
+}
+
+impl BitcoindRpcClient {
+    /// Apply the blocks from the wallet's latest checkpoint up to the node's tip, adding their
+    /// events to `events` as they are applied.
+    async fn apply_blocks(
+        &self,
+        wallet: &Wallet,
+        start_height: Option<u32>,
+        events: &mut Vec<WalletEvent>,
+    ) -> Result<(), BdkError> {
+        'sync: loop {
+            let tip_height: u32 = self.call("getblockcount", json!([])).await?;
+            let mut connected_to = self.point_of_agreement(&wallet.latest_checkpoint(), tip_height).await?;
+            let first_height = (connected_to.height + 1).max(start_height.unwrap_or_default());
+
+            for height in first_height..=tip_height {
+                let hash: BlockHash = self.call("getblockhash", json!([height])).await?;
+                let hex: String = self.call("getblock", json!([hash, 0])).await?;
+                let block: BdkBlock =
+                    deserialize_hex(&hex).map_err(|e| rpc_error(format!("Invalid block {hash}: {e}"), ()))?;
+
+                // The node switched to another chain while we were walking it.
+                if connected_to.height == height - 1 && block.header.prev_blockhash != connected_to.hash {
+                    continue 'sync;
+                }
+
+                events.extend(wallet.apply_block_connected_to_events(&block.into(), height, connected_to.into())?);
+                connected_to = BdkBlockId { height, hash };
+            }
+
+            return Ok(());
+        }
+    }
+
+    /// Find the most recent checkpoint of the wallet that is also in the node's chain.
+    async fn point_of_agreement(&self, tip: &BdkCheckPoint, tip_height: u32) -> Result<BdkBlockId, BdkError> {
+        for cp in tip.iter().filter(|cp| cp.height() <= tip_height) {
+            let hash: BlockHash = self.call("getblockhash", json!([cp.height()])).await?;
+            if hash == cp.hash() {
+                return Ok(cp.block_id());
+            }
+        }
+        Err(BdkError::new(
+            BdkErrorCode::CannotConnect,
+            "The node's chain does not connect to the wallet's chain, is it on the same network?",
+            (),
+        ))
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, BdkError> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let body = json!({ "jsonrpc": "1.0", "id": id, "method": method, "params": params });
+
+        let init = RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&JsValue::from_str(&body.to_string()));
+        let request = Request::new_with_str_and_init(&self.url, &init).map_err(fetch_error)?;
+        request
+            .headers()
+            .set("Content-Type", "application/json")
+            .map_err(fetch_error)?;
+        if let Some(authorization) = &self.authorization {
+            request
+                .headers()
+                .set("Authorization", authorization)
+                .map_err(fetch_error)?;
+        }
+
+        let response = fetch(&request).await.map_err(fetch_error)?;
+        if response.status() == 401 || response.status() == 403 {
+            return Err(rpc_error("Authentication failed", response.status()));
+        }
+        let text = JsFuture::from(response.text().map_err(fetch_error)?)
+            .await
+            .map_err(fetch_error)?
+            .as_string()
+            .unwrap_or_default();
+
+        // Errors come with a JSON body, but a non-200 status before Bitcoin Core 28.
+        let mut reply: Value = serde_json::from_str(&text)
+            .map_err(|_| rpc_error(format!("Unexpected HTTP {} response: {text}", response.status()), ()))?;
+        match reply.get_mut("error").map(Value::take) {
+            Some(error) if !error.is_null() => {
+                let message = error.get("message").and_then(Value::as_str).unwrap_or("Unknown error");
+                Err(rpc_error(
+                    format!("{method} failed: {message}"),
+                    error.get("code").and_then(Value::as_i64),
+                ))
+            }
+            _ => serde_json::from_value(reply.get_mut("result").map(Value::take).unwrap_or_default())
+                .map_err(|e| rpc_error(format!("Unexpected response to {method}: {e}"), ())),
+        }
+    }
+}
+
+fn fetch_error(e: JsValue) -> BdkError {
+    rpc_error(format!("Request to the node failed: {e:?}"), ())
+}
+
+fn rpc_error<D: serde::Serialize>(message: impl Into<String>, data: D) -> BdkError {
+    BdkError::new(BdkErrorCode::BitcoindRpc, message, data)
+}
@@ -0,0 +1,389 @@
+use bdk_wallet::{
+    bitcoin::{
+        bip158::{BlockFilter, FilterHeader},
+        block::Header,
+        consensus::deserialize,
+        hex::FromHex,
+        params::Params,
+        Block as BdkBlock, BlockHash, CompactTarget, Network as BdkNetwork,
+    },
+    chain::{BlockId as BdkBlockId, CheckPoint as BdkCheckPoint},
+    serde_json,
+};
+use futures::future::try_join_all;
+use serde::{de::DeserializeOwned, Deserialize};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{js_sys::Uint8Array, Request, Response};
+
+use crate::{
+    bitcoin::Wallet,
+    types::{BdkError, BdkErrorCode, WalletEvent},
+    utils::fetch::fetch,
+};
+
+/// Maximum number of headers returned by a single `/rest/headers` or `/rest/blockfilterheaders`
+/// request.
+const MAX_HEADERS: u32 = 2000;
+
+/// Number of filters downloaded concurrently.
+const PARALLEL_REQUESTS: usize = 10;
+
+/// BIP157/158 compact block filter client, using the REST interface of a Bitcoin Core node.
+///
+/// Instead of sending the wallet's scripts to a server, it downloads the headers and the BIP158
+/// basic filter of every block, matches the filters locally against the wallet's script pubkeys,
+/// and only downloads the blocks that match. The node must run with `-rest` and
+/// `-blockfilterindex`.
+///
+/// The headers must form a chain from the wallet's latest checkpoint with valid proof of work at
+/// the expected difficulty, and each filter must hash into the BIP157 filter header chain, so the
+/// node can neither serve blocks of another chain nor filters that do not commit to its blocks.
+#[wasm_bindgen]
+pub struct CompactFiltersClient {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct ChainInfo {
+    blocks: u32,
+}
+
+#[derive(Deserialize)]
+struct BlockHashByHeight {
+    blockhash: BlockHash,
+}
+
+#[derive(Deserialize)]
+struct Filter {
+    filter: String,
+}
+
+#[wasm_bindgen]
+impl CompactFiltersClient {
+    /// Create a client for the REST interface at `url`, e.g. `http://localhost:8332/rest`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str) -> CompactFiltersClient {
+        CompactFiltersClient {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Apply the blocks matching the wallet's filters, from its latest checkpoint up to the node's
+    /// tip.
+    ///
+    /// Scripts revealed while applying a block are matched against the following ones. If given,
+    /// blocks below `start_height` are skipped, which avoids scanning the whole chain for a new
+    /// wallet whose descriptors were never used before that height.
+    ///
+    /// Returns the `WalletEvent`s of all the applied blocks.
+    pub async fn sync(&self, wallet: &Wallet, start_height: Option<u32>) -> Result<Vec<WalletEvent>, BdkError> {
+        let params = Params::new(BdkNetwork::from(wallet.network()));
+        'sync: loop {
+            let tip_height = self.get_json::<ChainInfo>("chaininfo.json").await?.blocks;
+            let agreement = self.point_of_agreement(&wallet.latest_checkpoint(), tip_height).await?;
+            let from = (agreement.height + 1).max(start_height.unwrap_or_default());
+            if from > tip_height {
+                return Ok(Vec::new());
+            }
+
+            // The headers below `start_height` are checked too, so the chain is anchored in the wallet's.
+            // `None` means that the node switched to another chain since we found the point of agreement.
+            let Some(headers) = self.headers(agreement, tip_height, &params).await? else {
+                continue 'sync;
+            };
+            let scanned = &headers[(from - agreement.height - 1) as usize..];
+            let Some(filter_headers) = self.filter_headers(agreement, &headers, from).await? else {
+                continue 'sync;
+            };
+
+            let mut events = Vec::new();
+            let mut connected_to = agreement;
+            let mut spks = wallet.derived_spks();
+            for batch in scanned.chunks(PARALLEL_REQUESTS) {
+                let filters = try_join_all(batch.iter().map(|(_, header)| self.filter(header.block_hash()))).await?;
+
+                for ((height, header), filter) in batch.iter().zip(filters) {
+                    let hash = header.block_hash();
+                    // `filter_headers` starts with the filter header of the block before `from`.
+                    let index = (height - from) as usize;
+                    if filter.filter_header(&filter_headers[index]) != filter_headers[index + 1] {
+                        return Err(filters_error(format!(
+                            "The filter of block {hash} does not match its filter header"
+                        )));
+                    }
+
+                    let matched = filter
+                        .match_any(&hash, spks.iter().map(|spk| spk.as_bytes()))
+                        .map_err(|e| filters_error(format!("Invalid filter for block {hash}: {e}")))?;
+                    if !matched {
+                        continue;
+                    }
+
+                    let block: BdkBlock = deserialize(&self.get_bytes(&format!("block/{hash}.bin")).await?)
+                        .map_err(|e| filters_error(format!("Invalid block {hash}: {e}")))?;
+                    if block.block_hash() != hash {
+                        return Err(filters_error(format!("Received the wrong block for {hash}")));
+                    }
+
+                    events.extend(wallet.apply_block_connected_to_events(
+                        &block.into(),
+                        *height,
+                        connected_to.into(),
+                    )?);
+                    connected_to = BdkBlockId { height: *height, hash };
+                    spks = wallet.derived_spks();
+                }
+            }
+
+            // Move the wallet's tip to the node's, even if the last blocks had nothing for us.
+            if let Some((height, header)) = headers.last() {
+                if connected_to.height != *height {
+                    let block = BdkBlock {
+                        header: *header,
+                        txdata: Vec::new(),
+                    };
+                    events.extend(wallet.apply_block_connected_to_events(
+                        &block.into(),
+                        *height,
+                        connected_to.into(),
+                    )?);
+                }
+            }
+
+            return Ok(events);
+        }
+    }
+}
+
+impl CompactFiltersClient {
+    /// Find the most recent checkpoint of the wallet that is also in the node's chain.
+    async fn point_of_agreement(&self, tip: &BdkCheckPoint, tip_height: u32) -> Result<BdkBlockId, BdkError> {
+        for cp in tip.iter().filter(|cp| cp.height() <= tip_height) {
+            if self.block_hash(cp.height()).await? == cp.hash() {
+                return Ok(cp.block_id());
+            }
+        }
+        Err(BdkError::new(
+            BdkErrorCode::CannotConnect,
+            "The node's chain does not connect to the wallet's chain, is it on the same network?",
+            (),
+        ))
+    }
+
+    /// Download the headers from the block after `agreement` up to `to`, checking that they chain
+    /// up to `agreement` with valid proof of work at the difficulty of `params`.
+    ///
+    /// Returns `None` if the node's chain changed and no longer contains all of these blocks.
+    async fn headers(
+        &self,
+        agreement: BdkBlockId,
+        to: u32,
+        params: &Params,
+    ) -> Result<Option<Vec<(u32, Header)>>, BdkError> {
+        let interval = params.difficulty_adjustment_interval() as u32;
+        let mut height = agreement.height;
+        let mut hash = agreement.hash;
+
+        let mut headers: Vec<(u32, Header)> = Vec::new();
+        while height < to {
+            // The response starts with the header of `hash` itself.
+            let count = (to - height + 1).min(MAX_HEADERS);
+            let bytes = self.get_bytes(&format!("headers/{hash}.bin?count={count}")).await?;
+            let mut batch = bytes.chunks(80).map(deserialize::<Header>);
+            let mut prev = match batch.next() {
+                Some(Ok(first)) if first.block_hash() == hash => first,
+                _ => return Ok(None),
+            };
+
+            let mut received = 0;
+            for header in batch {
+                let header = header.map_err(|e| filters_error(format!("Invalid header: {e}")))?;
+                if header.prev_blockhash != hash {
+                    return Ok(None);
+                }
+                height += 1;
+
+                // Test networks allowing minimum difficulty blocks are only checked between retargets.
+                let retarget = height % interval == 0 && !params.no_pow_retargeting;
+                let epoch_start = if retarget && !params.allow_min_difficulty_blocks {
+                    Some(self.epoch_start(agreement, &headers, height - interval).await?)
+                } else {
+                    None
+                };
+                check_difficulty(params, height, &prev, &header, retarget, epoch_start)?;
+                hash = header
+                    .validate_pow(header.target())
+                    .map_err(|e| filters_error(format!("Invalid header at height {height}: {e}")))?;
+
+                prev = header;
+                received += 1;
+                headers.push((height, header));
+            }
+            if received == 0 {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(headers))
+    }
+
+    /// The header at `height`, the start of a difficulty period, from `headers` which start right
+    /// after `agreement` or from the node.
+    async fn epoch_start(
+        &self,
+        agreement: BdkBlockId,
+        headers: &[(u32, Header)],
+        height: u32,
+    ) -> Result<Header, BdkError> {
+        if let Some(index) = height.checked_sub(agreement.height + 1) {
+            return Ok(headers[index as usize].1);
+        }
+
+        let hash = self.block_hash(height).await?;
+        let bytes = self.get_bytes(&format!("headers/{hash}.bin?count=1")).await?;
+        match deserialize::<Header>(&bytes) {
+            Ok(header) if header.block_hash() == hash => Ok(header),
+            _ => Err(filters_error(format!("Invalid header at height {height}"))),
+        }
+    }
+
+    /// Download the BIP157 filter headers of the blocks from `from - 1` to the last of `headers`,
+    /// which start right after `agreement`.
+    ///
+    /// Returns `None` if the node's chain changed and no longer contains all of these blocks.
+    async fn filter_headers(
+        &self,
+        agreement: BdkBlockId,
+        headers: &[(u32, Header)],
+        from: u32,
+    ) -> Result<Option<Vec<FilterHeader>>, BdkError> {
+        let hash_at = |height: u32| match height.checked_sub(agreement.height + 1) {
+            Some(index) => headers[index as usize].1.block_hash(),
+            None => agreement.hash,
+        };
+        let to = agreement.height + headers.len() as u32;
+
+        let mut filter_headers: Vec<FilterHeader> = Vec::new();
+        while filter_headers.len() < (to - from + 2) as usize {
+            // The response starts with the filter header of the requested block, which is the last
+            // one we have after the first request.
+            let height = from - 1 + filter_headers.len().saturating_sub(1) as u32;
+            let count = (to - height + 1).min(MAX_HEADERS);
+            let batch: Vec<FilterHeader> = self
+                .get_json(&format!(
+                    "blockfilterheaders/basic/{}.json?count={count}",
+                    hash_at(height)
+                ))
+                .await?;
+
+            let mut batch = batch.into_iter();
+            if let Some(last) = filter_headers.last() {
+                if batch.next().as_ref() != Some(last) {
+                    return Ok(None);
+                }
+            }
+            let received = filter_headers.len();
+            filter_headers.extend(batch);
+            if filter_headers.len() == received {
+                return Ok(None);
+            }
+        }
+        filter_headers.truncate((to - from + 2) as usize);
+
+        Ok(Some(filter_headers))
+    }
+
+    async fn block_hash(&self, height: u32) -> Result<BlockHash, BdkError> {
+        let result: BlockHashByHeight = self.get_json(&format!("blockhashbyheight/{height}.json")).await?;
+        Ok(result.blockhash)
+    }
+
+    async fn filter(&self, hash: BlockHash) -> Result<BlockFilter, BdkError> {
+        let result: Filter = self.get_json(&format!("blockfilter/basic/{hash}.json")).await?;
+        let content = Vec::<u8>::from_hex(&result.filter)
+            .map_err(|e| filters_error(format!("Invalid filter for block {hash}: {e}")))?;
+        Ok(BlockFilter::new(&content))
+    }
+
+    async fn get(&self, path: &str) -> Result<Response, BdkError> {
+        let url = format!("{}/{path}", self.url);
+        let request = Request::new_with_str(&url).map_err(request_error)?;
+        let response = fetch(&request).await.map_err(request_error)?;
+        if !response.ok() {
+            let text = match response.text() {
+                Ok(text) => JsFuture::from(text).await.ok().and_then(|t| t.as_string()),
+                Err(_) => None,
+            };
+            return Err(filters_error(format!(
+                "GET {url} failed with HTTP {}: {}",
+                response.status(),
+                text.unwrap_or_default().trim()
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, BdkError> {
+        let response = self.get(path).await?;
+        let text = JsFuture::from(response.text().map_err(request_error)?)
+            .await
+            .map_err(request_error)?
+            .as_string()
+            .unwrap_or_default();
+        serde_json::from_str(&text).map_err(|e| filters_error(format!("Unexpected response to {path}: {e}")))
+    }
+
+    async fn get_bytes(&self, path: &str) -> Result<Vec<u8>, BdkError> {
+        let response = self.get(path).await?;
+        let buffer = JsFuture::from(response.array_buffer().map_err(request_error)?)
+            .await
+            .map_err(request_error)?;
+        Ok(Uint8Array::new(&buffer).to_vec())
+    }
+}
+
+/// Check the difficulty declared by `header`, at `height` after `prev`.
+///
+/// At `retarget` heights, the difficulty must follow the adjustment from `epoch_start`, the first
+/// header of the period ending with `prev`, unless it is `None`. Otherwise it must not change,
+/// except for the minimum difficulty blocks of test networks, which are allowed when mined 20
+/// minutes after the previous block and followed by a return to the difficulty of the period.
+fn check_difficulty(
+    params: &Params,
+    height: u32,
+    prev: &Header,
+    header: &Header,
+    retarget: bool,
+    epoch_start: Option<Header>,
+) -> Result<(), BdkError> {
+    let min_difficulty = params.max_attainable_target.to_compact_lossy();
+    let valid = match epoch_start {
+        Some(epoch_start) => {
+            header.bits == CompactTarget::from_header_difficulty_adjustment(epoch_start, *prev, params)
+        }
+        None if retarget => true,
+        None if params.allow_min_difficulty_blocks => {
+            header.bits == prev.bits
+                || prev.bits == min_difficulty
+                || (header.bits == min_difficulty
+                    && u64::from(header.time) > u64::from(prev.time) + 2 * params.pow_target_spacing)
+        }
+        None => header.bits == prev.bits,
+    };
+
+    if !valid || header.target() > params.max_attainable_target {
+        return Err(filters_error(format!(
+            "Unexpected difficulty of the header at height {height}"
+        )));
+    }
+    Ok(())
+}
+
+fn request_error(e: JsValue) -> BdkError {
+    filters_error(format!("Request to the node failed: {e:?}"))
+}
+
+fn filters_error(message: impl Into<String>) -> BdkError {
+    BdkError::new(BdkErrorCode::CompactFilters, message, ())
+}
@@ -1,12 +1,15 @@
+use std::fmt::Display;
+
 use anyhow::{anyhow, Error};
 use bdk_wallet::{
     bitcoin::{
-        bip32::{ChainCode, Fingerprint, Xpriv, Xpub},
+        bip32::{ChainCode, ChildNumber, DerivationPath, Fingerprint, Xpriv, Xpub},
         hex::FromHex,
-        secp256k1::{PublicKey, SecretKey},
+        secp256k1::{PublicKey, Secp256k1, SecretKey},
         AddressType, Network,
     },
-    keys::{DerivableKey, ExtendedKey},
+    descriptor::IntoWalletDescriptor,
+    keys::{mainnet_network, test_networks, DerivableKey, ExtendedKey},
     template::{
         Bip44, Bip44Public, Bip49, Bip49Public, Bip84, Bip84Public, Bip86, Bip86Public, DescriptorTemplate,
         DescriptorTemplateOut,
@@ -16,20 +19,123 @@ use bdk_wallet::{
 
 use crate::types::SLIP10Node;
 
+/// Options of the descriptors built from a key.
+#[derive(Clone, Debug, Default)]
+pub struct DescriptorOptions {
+    /// Build legacy P2SH descriptors, `sh(pk(...))`, instead of BIP49 P2SH-wrapped P2WPKH for `AddressType::P2sh`.
+    pub legacy_p2sh: bool,
+    /// Index of the BIP32 account.
+    pub account: u32,
+    /// Derivation path of the account, replacing the standard one of the address type.
+    pub derivation_path: Option<DerivationPath>,
+}
+
+impl DescriptorOptions {
+    fn is_default(&self) -> bool {
+        !self.legacy_p2sh && self.account == 0 && self.derivation_path.is_none()
+    }
+}
+
+/// Script of single key descriptors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScriptKind {
+    /// `pkh(KEY)`, BIP44
+    Pkh,
+    /// `sh(wpkh(KEY))`, BIP49
+    ShWpkh,
+    /// `sh(pk(KEY))`
+    Sh,
+    /// `wpkh(KEY)`, BIP84
+    Wpkh,
+    /// `wsh(pk(KEY))`
+    Wsh,
+    /// `tr(KEY)`, BIP86
+    Tr,
+}
+
+impl ScriptKind {
+    fn new(address_type: AddressType, legacy_p2sh: bool) -> Result<Self, Error> {
+        match address_type {
+            AddressType::P2pkh => Ok(ScriptKind::Pkh),
+            AddressType::P2sh if legacy_p2sh => Ok(ScriptKind::Sh),
+            AddressType::P2sh => Ok(ScriptKind::ShWpkh),
+            AddressType::P2wpkh => Ok(ScriptKind::Wpkh),
+            AddressType::P2wsh => Ok(ScriptKind::Wsh),
+            AddressType::P2tr => Ok(ScriptKind::Tr),
+            _ => Err(anyhow!("Unsupported address type")),
+        }
+    }
+
+    /// The derivation path of the account, the standard one unless a custom path is given.
+    ///
+    /// No BIP defines the paths of single key legacy P2SH and P2WSH descriptors, so they
+    /// require a custom path.
+    fn account_path(self, network: Network, options: &DescriptorOptions) -> Result<DerivationPath, Error> {
+        if let Some(path) = &options.derivation_path {
+            return Ok(path.clone());
+        }
+        let purpose = self.purpose().ok_or_else(|| {
+            anyhow!(
+                "No standard derivation path for {} descriptors, a derivation path is required",
+                self.descriptor("KEY")
+            )
+        })?;
+
+        let coin_type = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        Ok(vec![
+            ChildNumber::from_hardened_idx(purpose)?,
+            ChildNumber::from_hardened_idx(coin_type)?,
+            ChildNumber::from_hardened_idx(options.account)?,
+        ]
+        .into())
+    }
+
+    fn purpose(self) -> Option<u32> {
+        match self {
+            ScriptKind::Pkh => Some(44),
+            ScriptKind::ShWpkh => Some(49),
+            ScriptKind::Wpkh => Some(84),
+            ScriptKind::Tr => Some(86),
+            ScriptKind::Sh | ScriptKind::Wsh => None,
+        }
+    }
+
+    fn descriptor(self, key: &str) -> String {
+        match self {
+            ScriptKind::Pkh => format!("pkh({key})"),
+            ScriptKind::ShWpkh => format!("sh(wpkh({key}))"),
+            ScriptKind::Sh => format!("sh(pk({key}))"),
+            ScriptKind::Wpkh => format!("wpkh({key})"),
+            ScriptKind::Wsh => format!("wsh(pk({key}))"),
+            ScriptKind::Tr => format!("tr({key})"),
+        }
+    }
+}
+
 pub fn seed_to_descriptor(
     seed: &[u8],
     network: Network,
     address_type: AddressType,
+    options: &DescriptorOptions,
 ) -> Result<(DescriptorTemplateOut, DescriptorTemplateOut), Error> {
     let xprv = seed_to_xpriv(seed, network)?;
 
-    match address_type {
-        AddressType::P2pkh => build_xpriv_descriptor(Bip44, xprv, network),
-        AddressType::P2sh => build_xpriv_descriptor(Bip49, xprv, network),
-        AddressType::P2wpkh => build_xpriv_descriptor(Bip84, xprv, network),
-        AddressType::P2tr => build_xpriv_descriptor(Bip86, xprv, network),
-        _ => Err(anyhow!("Unsupported address type")),
+    if options.is_default() {
+        match address_type {
+            AddressType::P2pkh => return build_xpriv_descriptor(Bip44, xprv, network),
+            AddressType::P2sh => return build_xpriv_descriptor(Bip49, xprv, network),
+            AddressType::P2wpkh => return build_xpriv_descriptor(Bip84, xprv, network),
+            AddressType::P2tr => return build_xpriv_descriptor(Bip86, xprv, network),
+            _ => {}
+        }
     }
+
+    let script = ScriptKind::new(address_type, options.legacy_p2sh)?;
+    let path = script.account_path(network, options)?;
+    build_key_descriptor(script, &derived_key(xprv, &path), network)
 }
 
 pub fn xpriv_to_descriptor(
@@ -37,14 +143,21 @@ pub fn xpriv_to_descriptor(
     fingerprint: Fingerprint,
     network: Network,
     address_type: AddressType,
+    options: &DescriptorOptions,
 ) -> Result<(DescriptorTemplateOut, DescriptorTemplateOut), Error> {
-    match address_type {
-        AddressType::P2pkh => build_descriptor(Bip44Public, xprv, fingerprint, network),
-        AddressType::P2sh => build_descriptor(Bip49Public, xprv, fingerprint, network),
-        AddressType::P2wpkh => build_descriptor(Bip84Public, xprv, fingerprint, network),
-        AddressType::P2tr => build_descriptor(Bip86Public, xprv, fingerprint, network),
-        _ => Err(anyhow!("Unsupported address type")),
+    if options.is_default() {
+        match address_type {
+            AddressType::P2pkh => return build_descriptor(Bip44Public, xprv, fingerprint, network),
+            AddressType::P2sh => return build_descriptor(Bip49Public, xprv, fingerprint, network),
+            AddressType::P2wpkh => return build_descriptor(Bip84Public, xprv, fingerprint, network),
+            AddressType::P2tr => return build_descriptor(Bip86Public, xprv, fingerprint, network),
+            _ => {}
+        }
     }
+
+    let script = ScriptKind::new(address_type, options.legacy_p2sh)?;
+    let path = script.account_path(network, options)?;
+    build_key_descriptor(script, &origin_key(fingerprint, &path, xprv), network)
 }
 
 pub fn xpub_to_descriptor(
@@ -52,14 +165,21 @@ pub fn xpub_to_descriptor(
     fingerprint: Fingerprint,
     network: Network,
     address_type: AddressType,
+    options: &DescriptorOptions,
 ) -> Result<(DescriptorTemplateOut, DescriptorTemplateOut), Error> {
-    match address_type {
-        AddressType::P2pkh => build_descriptor(Bip44Public, xpub, fingerprint, network),
-        AddressType::P2sh => build_descriptor(Bip49Public, xpub, fingerprint, network),
-        AddressType::P2wpkh => build_descriptor(Bip84Public, xpub, fingerprint, network),
-        AddressType::P2tr => build_descriptor(Bip86Public, xpub, fingerprint, network),
-        _ => Err(anyhow!("Unsupported address type")),
+    if options.is_default() {
+        match address_type {
+            AddressType::P2pkh => return build_descriptor(Bip44Public, xpub, fingerprint, network),
+            AddressType::P2sh => return build_descriptor(Bip49Public, xpub, fingerprint, network),
+            AddressType::P2wpkh => return build_descriptor(Bip84Public, xpub, fingerprint, network),
+            AddressType::P2tr => return build_descriptor(Bip86Public, xpub, fingerprint, network),
+            _ => {}
+        }
     }
+
+    let script = ScriptKind::new(address_type, options.legacy_p2sh)?;
+    let path = script.account_path(network, options)?;
+    build_key_descriptor(script, &origin_key(fingerprint, &path, xpub), network)
 }
 
 pub fn slip10_to_extended(node: SLIP10Node, network: Network) -> Result<ExtendedKey, anyhow::Error> {
@@ -147,6 +267,40 @@ where
     Ok((ext, int))
 }
 
+/// A key followed by a derivation path, `key/path`.
+fn derived_key(key: impl Display, path: &DerivationPath) -> String {
+    path.into_iter()
+        .fold(key.to_string(), |key, child| format!("{key}/{child}"))
+}
+
+/// An account key with its origin, `[fingerprint/path]key`.
+pub(crate) fn origin_key(fingerprint: Fingerprint, path: &DerivationPath, key: impl Display) -> String {
+    format!("[{}]{key}", derived_key(fingerprint, path))
+}
+
+/// Build the descriptors of both keychains from an account `key`, deriving the
+/// external addresses from `key/0/*` and the internal ones from `key/1/*`.
+fn build_key_descriptor(
+    script: ScriptKind,
+    key: &str,
+    network: Network,
+) -> Result<(DescriptorTemplateOut, DescriptorTemplateOut), Error> {
+    let secp = Secp256k1::new();
+    let valid_networks = match network {
+        Network::Bitcoin => mainnet_network(),
+        _ => test_networks(),
+    };
+
+    let build = |keychain: u32| -> Result<DescriptorTemplateOut, Error> {
+        let descriptor = script.descriptor(&format!("{key}/{keychain}/*"));
+        let (descriptor, keymap) = descriptor.as_str().into_wallet_descriptor(&secp, network)?;
+        descriptor.sanity_check()?;
+        Ok((descriptor, keymap, valid_networks.clone()))
+    };
+
+    Ok((build(0)?, build(1)?))
+}
+
 fn strip_0x_prefix(s: &str) -> &str {
     if s.starts_with("0x") || s.starts_with("0X") {
         &s[2..]
@@ -0,0 +1,133 @@
+mod rpc;
+mod scan;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use bdk_wallet::{
+    bitcoin::{Transaction as BdkTransaction, Txid as BdkTxid},
+    chain::spk_client::{
+        FullScanRequest as BdkFullScanRequest, FullScanResponse, SyncRequest as BdkSyncRequest, SyncResponse,
+    },
+    KeychainKind,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{BdkError, FullScanRequest, SyncRequest, Transaction, Txid, Update};
+
+use rpc::Connection;
+use scan::Scan;
+
+const DEFAULT_TIMEOUT_MS: u32 = 30_000;
+
+/// Electrum client speaking JSON-RPC over a WebSocket.
+///
+/// Works with servers exposing a WebSocket endpoint (e.g. Fulcrum) or with an Electrum server
+/// (e.g. electrs) behind a WebSocket proxy such as websockify. Requires a global `WebSocket`,
+/// available in browsers and in Node 22+.
+#[wasm_bindgen]
+pub struct ElectrumClient {
+    connection: Rc<Connection>,
+    tx_cache: RefCell<HashMap<BdkTxid, Arc<BdkTransaction>>>,
+}
+
+#[wasm_bindgen]
+impl ElectrumClient {
+    /// Connect to the Electrum server at `url` (`ws://` or `wss://`).
+    ///
+    /// Requests the server does not answer within `timeout_ms` milliseconds (30 seconds by default)
+    /// fail with `ElectrumServer`, as do all the requests in flight when it sends a malformed message.
+    pub async fn connect(url: &str, timeout_ms: Option<u32>) -> Result<ElectrumClient, BdkError> {
+        let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).into());
+        Ok(ElectrumClient {
+            connection: Connection::open(url, timeout).await?,
+            tx_cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub async fn full_scan(
+        &self,
+        request: FullScanRequest,
+        stop_gap: usize,
+        parallel_requests: usize,
+    ) -> Result<Update, BdkError> {
+        let mut request: BdkFullScanRequest<KeychainKind> = request.into();
+        let mut scan = Scan::new(
+            &self.connection,
+            &self.tx_cache,
+            request.start_time(),
+            parallel_requests,
+        );
+
+        let chain_tip = request.chain_tip();
+        let latest_blocks = match chain_tip {
+            Some(_) => Some(scan.latest_blocks().await?),
+            None => None,
+        };
+
+        let mut last_active_indices = BTreeMap::new();
+        for keychain in request.keychains() {
+            let spks = request.iter_spks(keychain).map(|(index, spk)| (index, spk.into()));
+            if let Some(index) = scan.scan_spks(spks, stop_gap).await? {
+                last_active_indices.insert(keychain, index);
+            }
+        }
+
+        let (tx_update, chain_update) = scan.finish(chain_tip, latest_blocks).await?;
+        Ok(FullScanResponse {
+            tx_update,
+            last_active_indices,
+            chain_update,
+        }
+        .into())
+    }
+
+    pub async fn sync(&self, request: SyncRequest, parallel_requests: usize) -> Result<Update, BdkError> {
+        let mut request: BdkSyncRequest<(KeychainKind, u32)> = request.into();
+        let mut scan = Scan::new(
+            &self.connection,
+            &self.tx_cache,
+            request.start_time(),
+            parallel_requests,
+        );
+
+        let chain_tip = request.chain_tip();
+        let latest_blocks = match chain_tip {
+            Some(_) => Some(scan.latest_blocks().await?),
+            None => None,
+        };
+
+        let spks = request.iter_spks_with_expected_txids().enumerate();
+        scan.scan_spks(spks.map(|(index, spk)| (index as u32, spk)), usize::MAX)
+            .await?;
+        scan.scan_txids(request.iter_txids()).await?;
+        scan.scan_outpoints(request.iter_outpoints()).await?;
+
+        let (tx_update, chain_update) = scan.finish(chain_tip, latest_blocks).await?;
+        Ok(SyncResponse {
+            tx_update,
+            chain_update,
+        }
+        .into())
+    }
+
+    pub async fn broadcast(&self, transaction: &Transaction) -> Result<(), BdkError> {
+        self.connection.broadcast(transaction).await?;
+        Ok(())
+    }
+
+    pub async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>, BdkError> {
+        let tx = self.connection.transaction(txid.into()).await?;
+        Ok(tx.map(Into::into))
+    }
+
+    /// Close the connection. Pending and later requests fail with `ElectrumConnection`.
+    pub fn close(&self) {
+        self.connection.close();
+    }
+}
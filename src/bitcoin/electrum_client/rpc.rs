@@ -0,0 +1,364 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    pin::pin,
+    rc::{Rc, Weak},
+    time::Duration,
+};
+
+use bdk_wallet::{
+    bitcoin::{
+        block::Header,
+        consensus::encode::{deserialize, deserialize_hex, serialize_hex},
+        hashes::{sha256, Hash},
+        hex::{DisplayHex, FromHex},
+        ScriptBuf, Transaction, TxMerkleNode, Txid,
+    },
+    serde_json::{self, json, Value},
+};
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{
+    js_sys::{ArrayBuffer, Uint8Array},
+    BinaryType, MessageEvent, WebSocket,
+};
+
+use crate::{
+    types::{BdkError, BdkErrorCode},
+    utils::timer::sleep,
+};
+
+type Response = Result<Value, BdkError>;
+type Handler = Closure<dyn FnMut(JsValue)>;
+
+/// Electrum JSON-RPC connection over a WebSocket.
+///
+/// Requests are pipelined: each one gets an id and its response is routed back to the awaiting
+/// future when it arrives, so several requests can be in flight at once.
+pub(super) struct Connection {
+    socket: WebSocket,
+    next_id: Cell<u64>,
+    pending: RefCell<HashMap<u64, oneshot::Sender<Response>>>,
+    timeout: Duration,
+    closed: Cell<bool>,
+    /// Partial message received from proxies that forward the raw TCP stream.
+    buffer: RefCell<String>,
+    handlers: RefCell<Vec<Handler>>,
+}
+
+/// An entry of `blockchain.scripthash.get_history`.
+#[derive(Deserialize)]
+pub(super) struct HistoryItem {
+    pub tx_hash: Txid,
+    /// Confirmation height, `0` or `-1` for mempool transactions.
+    pub height: i32,
+}
+
+/// Result of `blockchain.transaction.get_merkle`.
+#[derive(Deserialize)]
+pub(super) struct MerkleProof {
+    pub merkle: Vec<TxMerkleNode>,
+    pub pos: usize,
+}
+
+#[derive(Deserialize)]
+struct HeaderNotification {
+    height: u32,
+    hex: String,
+}
+
+#[derive(Deserialize)]
+struct HeadersResult {
+    hex: String,
+}
+
+impl Connection {
+    /// Open a WebSocket to `url` and perform the protocol version handshake. Requests not answered
+    /// within `timeout` fail.
+    pub(super) async fn open(url: &str, timeout: Duration) -> Result<Rc<Self>, BdkError> {
+        let socket = WebSocket::new(url).map_err(|e| connection_error(format!("Invalid URL {url}: {e:?}")))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let connection = Rc::new(Connection {
+            socket,
+            next_id: Cell::new(0),
+            pending: RefCell::new(HashMap::new()),
+            timeout,
+            closed: Cell::new(false),
+            buffer: RefCell::new(String::new()),
+            handlers: RefCell::new(Vec::new()),
+        });
+
+        let (opened_tx, opened_rx) = oneshot::channel::<bool>();
+        let opened_tx = Rc::new(RefCell::new(Some(opened_tx)));
+
+        let on_open = {
+            let opened_tx = opened_tx.clone();
+            Handler::new(move |_| {
+                if let Some(tx) = opened_tx.borrow_mut().take() {
+                    let _ = tx.send(true);
+                }
+            })
+        };
+        let on_close = {
+            let connection = Rc::downgrade(&connection);
+            Handler::new(move |_| {
+                if let Some(tx) = opened_tx.borrow_mut().take() {
+                    let _ = tx.send(false);
+                }
+                if let Some(connection) = connection.upgrade() {
+                    connection.fail_pending("Connection to the Electrum server was closed");
+                }
+            })
+        };
+        let on_message = {
+            let connection = Rc::downgrade(&connection);
+            Handler::new(move |event: JsValue| {
+                on_message(&connection, event.unchecked_into());
+            })
+        };
+
+        // Errors are always followed by a close event, which is handled above.
+        connection.socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        connection.socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        connection
+            .socket
+            .set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        connection.handlers.borrow_mut().extend([on_open, on_close, on_message]);
+
+        if !opened_rx.await.unwrap_or(false) {
+            return Err(connection_error(format!(
+                "Could not connect to the Electrum server at {url}"
+            )));
+        }
+
+        connection
+            .request(
+                "server.version",
+                json!([concat!("bdk-wasm ", env!("CARGO_PKG_VERSION")), "1.4"]),
+            )
+            .await?;
+        Ok(connection)
+    }
+
+    /// Close the WebSocket, failing all in-flight requests.
+    pub(super) fn close(&self) {
+        self.fail_pending("Connection to the Electrum server was closed");
+        let _ = self.socket.close();
+    }
+
+    pub(super) async fn request(&self, method: &str, params: Value) -> Response {
+        if self.closed.get() {
+            return Err(connection_error("Connection to the Electrum server is closed"));
+        }
+
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.borrow_mut().insert(id, tx);
+        if let Err(e) = self.socket.send_with_str(&format!("{message}\n")) {
+            self.pending.borrow_mut().remove(&id);
+            return Err(connection_error(format!("Failed to send request: {e:?}")));
+        }
+
+        match select(rx, pin!(sleep(self.timeout))).await {
+            Either::Left((response, _)) => {
+                response.unwrap_or_else(|_| Err(connection_error("Connection to the Electrum server was closed")))
+            }
+            Either::Right(_) => {
+                self.pending.borrow_mut().remove(&id);
+                Err(server_error(
+                    format!(
+                        "Electrum request {method} timed out after {} ms",
+                        self.timeout.as_millis()
+                    ),
+                    (),
+                ))
+            }
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, BdkError> {
+        let result = self.request(method, params).await?;
+        serde_json::from_value(result).map_err(|e| server_error(format!("Unexpected response to {method}: {e}"), ()))
+    }
+
+    /// Height and hash of the current chain tip.
+    pub(super) async fn tip(&self) -> Result<(u32, Header), BdkError> {
+        let tip: HeaderNotification = self.call("blockchain.headers.subscribe", json!([])).await?;
+        Ok((tip.height, parse_header(&tip.hex)?))
+    }
+
+    pub(super) async fn block_header(&self, height: u32) -> Result<Header, BdkError> {
+        let hex: String = self.call("blockchain.block.header", json!([height])).await?;
+        parse_header(&hex)
+    }
+
+    /// Up to `count` consecutive block headers starting at `start_height`.
+    pub(super) async fn block_headers(&self, start_height: u32, count: u32) -> Result<Vec<Header>, BdkError> {
+        let result: HeadersResult = self
+            .call("blockchain.block.headers", json!([start_height, count]))
+            .await?;
+        let bytes = Vec::<u8>::from_hex(&result.hex).map_err(|e| server_error(format!("Invalid headers: {e}"), ()))?;
+        bytes
+            .chunks(80)
+            .map(|chunk| deserialize(chunk).map_err(|e| server_error(format!("Invalid header: {e}"), ())))
+            .collect()
+    }
+
+    pub(super) async fn script_history(&self, script: &ScriptBuf) -> Result<Vec<HistoryItem>, BdkError> {
+        self.call("blockchain.scripthash.get_history", json!([script_hash(script)]))
+            .await
+    }
+
+    /// The transaction with the given `txid`, or `None` if the server does not know it.
+    pub(super) async fn transaction(&self, txid: Txid) -> Result<Option<Transaction>, BdkError> {
+        match self.call::<String>("blockchain.transaction.get", json!([txid])).await {
+            Ok(hex) => deserialize_hex(&hex)
+                .map(Some)
+                .map_err(|e| server_error(format!("Invalid transaction {txid}: {e}"), ())),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(super) async fn merkle_proof(&self, txid: Txid, height: u32) -> Result<MerkleProof, BdkError> {
+        self.call("blockchain.transaction.get_merkle", json!([txid, height]))
+            .await
+    }
+
+    pub(super) async fn broadcast(&self, tx: &Transaction) -> Result<Txid, BdkError> {
+        self.call("blockchain.transaction.broadcast", json!([serialize_hex(tx)]))
+            .await
+    }
+
+    fn fail_pending(&self, message: &str) {
+        self.closed.set(true);
+        self.reject_pending(|| connection_error(message));
+    }
+
+    fn reject_pending(&self, error: impl Fn() -> BdkError) {
+        for (_, tx) in self.pending.borrow_mut().drain() {
+            let _ = tx.send(Err(error()));
+        }
+    }
+
+    fn dispatch(&self, message: Value) {
+        match message {
+            Value::Array(batch) => batch.into_iter().for_each(|message| self.dispatch(message)),
+            Value::Object(mut response) => {
+                // Messages without an id are subscription notifications, which are not used.
+                let Some(id) = response.get("id").and_then(Value::as_u64) else {
+                    return;
+                };
+                let Some(tx) = self.pending.borrow_mut().remove(&id) else {
+                    return;
+                };
+                let result = match response.remove("error") {
+                    Some(error) if !error.is_null() => Err(server_error(
+                        rpc_error_message(&error),
+                        error.get("code").and_then(Value::as_i64),
+                    )),
+                    _ => Ok(response.remove("result").unwrap_or(Value::Null)),
+                };
+                let _ = tx.send(result);
+            }
+            // The response cannot be matched to its request, which would otherwise wait forever.
+            _ => self.reject_pending(protocol_error),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onclose(None);
+        self.socket.set_onmessage(None);
+        let _ = self.socket.close();
+    }
+}
+
+fn on_message(connection: &Weak<Connection>, event: MessageEvent) {
+    let Some(connection) = connection.upgrade() else {
+        return;
+    };
+    let data = event.data();
+    let text = match data.as_string() {
+        Some(text) => text,
+        None => match data.dyn_into::<ArrayBuffer>() {
+            Ok(buffer) => String::from_utf8_lossy(&Uint8Array::new(&buffer).to_vec()).into_owned(),
+            Err(_) => return,
+        },
+    };
+
+    // Servers speaking WebSocket natively send one message per frame, while proxies forwarding the
+    // TCP stream may split or merge newline-delimited messages across frames.
+    let mut messages = Vec::new();
+    let mut invalid = false;
+    {
+        let mut buffer = connection.buffer.borrow_mut();
+        buffer.push_str(&text);
+        while let Some(end) = buffer.find('\n') {
+            let line: String = buffer.drain(..=end).collect();
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(message) => messages.push(message),
+                Err(_) => invalid = true,
+            }
+        }
+        if let Ok(message) = serde_json::from_str(&buffer) {
+            messages.push(message);
+            buffer.clear();
+        }
+    }
+    messages.into_iter().for_each(|message| connection.dispatch(message));
+    // The id of a malformed message is unknown, so all the requests still waiting are failed.
+    if invalid {
+        connection.reject_pending(protocol_error);
+    }
+}
+
+/// Electrum identifies scripts by the reversed SHA256 of the script, in hex.
+fn script_hash(script: &ScriptBuf) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    hash.to_lower_hex_string()
+}
+
+fn parse_header(hex: &str) -> Result<Header, BdkError> {
+    deserialize_hex(hex).map_err(|e| server_error(format!("Invalid header: {e}"), ()))
+}
+
+fn rpc_error_message(error: &Value) -> String {
+    match error.get("message").and_then(Value::as_str) {
+        Some(message) => message.to_string(),
+        None => error.to_string(),
+    }
+}
+
+/// Servers disagree on the error code for unknown transactions, but all of them mention it.
+fn is_not_found(error: &BdkError) -> bool {
+    let message = error.message().to_lowercase();
+    matches!(error.code(), BdkErrorCode::ElectrumServer)
+        && (message.contains("no such") || message.contains("not found"))
+}
+
+fn protocol_error() -> BdkError {
+    server_error("Invalid message from the Electrum server", ())
+}
+
+fn connection_error(message: impl Into<String>) -> BdkError {
+    BdkError::new(BdkErrorCode::ElectrumConnection, message, ())
+}
+
+pub(super) fn server_error<D: serde::Serialize>(message: impl Into<String>, data: D) -> BdkError {
+    BdkError::new(BdkErrorCode::ElectrumServer, message, data)
+}
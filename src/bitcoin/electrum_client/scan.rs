@@ -0,0 +1,369 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+};
+
+use bdk_wallet::{
+    bitcoin::{
+        block::Header,
+        hashes::{sha256d, Hash, HashEngine},
+        BlockHash, OutPoint, Transaction, Txid,
+    },
+    chain::{spk_client::SpkWithExpectedTxids, BlockId, CheckPoint, ConfirmationBlockTime, Indexed, TxUpdate},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::types::BdkError;
+
+use super::rpc::{server_error, Connection, MerkleProof};
+
+/// Number of blocks fetched from the tip to build the chain update.
+const CHAIN_SUFFIX_LENGTH: u32 = 10;
+
+/// State of a single `full_scan` or `sync`.
+///
+/// Scripts, txids and outpoints are first resolved to the transactions related to them and their
+/// confirmation height, then [`Scan::finish`] fetches the transactions, their anchors and
+/// previous outputs, and builds the chain update.
+pub(super) struct Scan<'a> {
+    connection: &'a Connection,
+    tx_cache: &'a RefCell<HashMap<Txid, Arc<Transaction>>>,
+    start_time: u64,
+    parallel_requests: usize,
+    /// Confirmation height of every related transaction, `None` if unconfirmed.
+    heights: BTreeMap<Txid, Option<u32>>,
+    evicted: HashSet<Txid>,
+}
+
+impl<'a> Scan<'a> {
+    pub(super) fn new(
+        connection: &'a Connection,
+        tx_cache: &'a RefCell<HashMap<Txid, Arc<Transaction>>>,
+        start_time: u64,
+        parallel_requests: usize,
+    ) -> Self {
+        Scan {
+            connection,
+            tx_cache,
+            start_time,
+            parallel_requests: parallel_requests.max(1),
+            heights: BTreeMap::new(),
+            evicted: HashSet::new(),
+        }
+    }
+
+    /// Fetch the `CHAIN_SUFFIX_LENGTH` most recent blocks.
+    ///
+    /// This must happen before scanning, as the tip of the chain update marks the height the
+    /// wallet is synced up to: it's better to be conservative and use an earlier tip than to skip
+    /// blocks.
+    pub(super) async fn latest_blocks(&self) -> Result<BTreeMap<u32, BlockHash>, BdkError> {
+        let (tip_height, _) = self.connection.tip().await?;
+        let start_height = tip_height.saturating_sub(CHAIN_SUFFIX_LENGTH - 1);
+        let headers = self
+            .connection
+            .block_headers(start_height, tip_height - start_height + 1)
+            .await?;
+        Ok((start_height..).zip(headers.iter().map(Header::block_hash)).collect())
+    }
+
+    /// Scan the history of the given scripts, stopping after `stop_gap` consecutive scripts
+    /// without history, and return the index of the last script with history.
+    pub(super) async fn scan_spks(
+        &mut self,
+        mut spks: impl Iterator<Item = Indexed<SpkWithExpectedTxids>>,
+        stop_gap: usize,
+    ) -> Result<Option<u32>, BdkError> {
+        let gap_limit = stop_gap.max(1);
+        let mut last_active_index = None;
+        let mut consecutive_unused = 0;
+
+        loop {
+            let batch = spks.by_ref().take(self.parallel_requests).collect::<Vec<_>>();
+            if batch.is_empty() {
+                break;
+            }
+
+            let histories = self
+                .parallel(batch.iter(), |(_, spk)| self.connection.script_history(&spk.spk))
+                .await?;
+            for ((index, spk), history) in batch.into_iter().zip(histories) {
+                if history.is_empty() {
+                    consecutive_unused += 1;
+                } else {
+                    consecutive_unused = 0;
+                    last_active_index = Some(index);
+                }
+                let txids = history.iter().map(|item| item.tx_hash).collect::<HashSet<_>>();
+                self.evicted.extend(spk.expected_txids.difference(&txids));
+                for item in history {
+                    self.insert(item.tx_hash, item.height);
+                }
+            }
+
+            if consecutive_unused >= gap_limit {
+                break;
+            }
+        }
+
+        Ok(last_active_index)
+    }
+
+    /// Find the confirmation status of the given transactions.
+    ///
+    /// Electrum can only look up histories by script, so this goes through the history of the
+    /// first output of each transaction.
+    pub(super) async fn scan_txids(&mut self, txids: impl Iterator<Item = Txid>) -> Result<(), BdkError> {
+        let txids = txids
+            .filter(|txid| !self.heights.contains_key(txid))
+            .collect::<Vec<_>>();
+        let this = &*self;
+        let found = this
+            .parallel(txids, |txid| async move {
+                let Some(tx) = this.transaction(txid).await? else {
+                    return Ok(None);
+                };
+                let Some(output) = tx.output.first() else {
+                    return Ok(None);
+                };
+                let history = this.connection.script_history(&output.script_pubkey).await?;
+                Ok(history.into_iter().find(|item| item.tx_hash == txid))
+            })
+            .await?;
+
+        for item in found.into_iter().flatten() {
+            self.insert(item.tx_hash, item.height);
+        }
+        Ok(())
+    }
+
+    /// Find the transactions creating and spending the given outpoints.
+    pub(super) async fn scan_outpoints(&mut self, outpoints: impl Iterator<Item = OutPoint>) -> Result<(), BdkError> {
+        let this = &*self;
+        let found = this
+            .parallel(outpoints, |outpoint| async move {
+                let Some(tx) = this.transaction(outpoint.txid).await? else {
+                    return Ok(Vec::new());
+                };
+                let Some(output) = tx.output.get(outpoint.vout as usize) else {
+                    return Ok(Vec::new());
+                };
+                let mut related = Vec::new();
+                for item in this.connection.script_history(&output.script_pubkey).await? {
+                    if item.tx_hash == outpoint.txid {
+                        related.push(item);
+                    } else if let Some(tx) = this.transaction(item.tx_hash).await? {
+                        if tx.input.iter().any(|input| input.previous_output == outpoint) {
+                            related.push(item);
+                        }
+                    }
+                }
+                Ok(related)
+            })
+            .await?;
+
+        for item in found.into_iter().flatten() {
+            self.insert(item.tx_hash, item.height);
+        }
+        Ok(())
+    }
+
+    /// Fetch the transactions found while scanning with their anchors and previous outputs, and
+    /// build the chain update on top of `chain_tip` if there is one.
+    pub(super) async fn finish(
+        self,
+        chain_tip: Option<CheckPoint>,
+        latest_blocks: Option<BTreeMap<u32, BlockHash>>,
+    ) -> Result<(TxUpdate<ConfirmationBlockTime>, Option<CheckPoint>), BdkError> {
+        let this = &self;
+        let mut update = TxUpdate::<ConfirmationBlockTime>::default();
+
+        let txs = self
+            .parallel(self.heights.keys().copied(), |txid| self.transaction(txid))
+            .await?;
+        update.txs = txs.into_iter().flatten().collect();
+
+        // Headers of all the confirmation heights, to anchor the transactions.
+        let heights = self.heights.values().flatten().copied().collect::<HashSet<_>>();
+        let headers = self
+            .parallel(heights, |height| async move {
+                Ok((height, this.connection.block_header(height).await?))
+            })
+            .await?
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+
+        let confirmed = self
+            .heights
+            .iter()
+            .filter_map(|(txid, height)| Some((*txid, (*height)?)));
+        let proofs = self
+            .parallel(confirmed, |(txid, height)| async move {
+                Ok((txid, height, this.connection.merkle_proof(txid, height).await?))
+            })
+            .await?;
+        for (txid, height, proof) in proofs {
+            let header = &headers[&height];
+            // Transactions with an invalid proof are left unanchored, as if they were unknown.
+            if is_valid_proof(txid, &proof, header) {
+                let anchor = ConfirmationBlockTime {
+                    block_id: BlockId {
+                        height,
+                        hash: header.block_hash(),
+                    },
+                    confirmation_time: header.time as u64,
+                };
+                update.anchors.insert((anchor, txid));
+            }
+        }
+
+        for (txid, height) in &self.heights {
+            if height.is_none() {
+                update.seen_ats.insert((*txid, self.start_time));
+            }
+        }
+        update
+            .evicted_ats
+            .extend(self.evicted.iter().map(|txid| (*txid, self.start_time)));
+
+        // Previous outputs let the wallet compute the fee of transactions with foreign inputs.
+        let prevouts = update
+            .txs
+            .iter()
+            .filter(|tx| !tx.is_coinbase())
+            .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+            .collect::<Vec<_>>();
+        let prev_txids = prevouts.iter().map(|outpoint| outpoint.txid).collect::<HashSet<_>>();
+        self.parallel(prev_txids, |txid| self.transaction(txid)).await?;
+        for outpoint in prevouts {
+            let txout = self
+                .tx_cache
+                .borrow()
+                .get(&outpoint.txid)
+                .and_then(|tx| tx.output.get(outpoint.vout as usize).cloned());
+            if let Some(txout) = txout {
+                update.txouts.insert(outpoint, txout);
+            }
+        }
+
+        let chain_update = match (chain_tip, latest_blocks) {
+            (Some(chain_tip), Some(latest_blocks)) => {
+                Some(self.chain_update(&chain_tip, &latest_blocks, &headers).await?)
+            }
+            _ => None,
+        };
+
+        Ok((update, chain_update))
+    }
+
+    /// Build the chain update connecting `local_tip` to the latest blocks, with a checkpoint for
+    /// each anchor height.
+    async fn chain_update(
+        &self,
+        local_tip: &CheckPoint,
+        latest_blocks: &BTreeMap<u32, BlockHash>,
+        headers: &BTreeMap<u32, Header>,
+    ) -> Result<CheckPoint, BdkError> {
+        let tip_height = latest_blocks.keys().last().copied().unwrap_or_default();
+        let fetch_block = |height: u32| async move {
+            if let Some(hash) = latest_blocks.get(&height) {
+                return Ok::<_, BdkError>(Some(*hash));
+            }
+            // Blocks above the latest fetched tip are ignored, as the tip marks the synced height.
+            if height > tip_height {
+                return Ok(None);
+            }
+            match headers.get(&height) {
+                Some(header) => Ok(Some(header.block_hash())),
+                None => Ok(Some(self.connection.block_header(height).await?.block_hash())),
+            }
+        };
+
+        let mut point_of_agreement = None;
+        let mut conflicts = Vec::new();
+        for local_cp in local_tip.iter() {
+            let Some(remote_hash) = fetch_block(local_cp.height()).await? else {
+                continue;
+            };
+            if remote_hash == local_cp.hash() {
+                point_of_agreement = Some(local_cp);
+                break;
+            }
+            conflicts.push(BlockId {
+                height: local_cp.height(),
+                hash: remote_hash,
+            });
+        }
+
+        let mut tip = point_of_agreement
+            .ok_or_else(|| server_error("The server chain does not connect to the local chain", ()))?
+            .extend(conflicts.into_iter().rev())
+            .expect("conflicts are in order");
+
+        for height in headers.keys().copied().filter(|height| *height <= tip_height) {
+            if tip.get(height).is_none() {
+                if let Some(hash) = fetch_block(height).await? {
+                    tip = tip.insert(BlockId { height, hash });
+                }
+            }
+        }
+        for (&height, &hash) in latest_blocks {
+            tip = tip.insert(BlockId { height, hash });
+        }
+
+        Ok(tip)
+    }
+
+    fn insert(&mut self, txid: Txid, height: i32) {
+        let height = u32::try_from(height).ok().filter(|height| *height > 0);
+        let entry = self.heights.entry(txid).or_default();
+        *entry = entry.or(height);
+    }
+
+    async fn transaction(&self, txid: Txid) -> Result<Option<Arc<Transaction>>, BdkError> {
+        if let Some(tx) = self.tx_cache.borrow().get(&txid) {
+            return Ok(Some(tx.clone()));
+        }
+        let tx = self.connection.transaction(txid).await?.map(Arc::new);
+        if let Some(tx) = &tx {
+            self.tx_cache.borrow_mut().insert(txid, tx.clone());
+        }
+        Ok(tx)
+    }
+
+    /// Run `f` on every item with at most `parallel_requests` requests in flight, keeping the
+    /// order of the items.
+    async fn parallel<I, T, F, Fut>(&self, items: impl IntoIterator<Item = I>, f: F) -> Result<Vec<T>, BdkError>
+    where
+        F: FnMut(I) -> Fut,
+        Fut: Future<Output = Result<T, BdkError>>,
+    {
+        stream::iter(items)
+            .map(f)
+            .buffered(self.parallel_requests)
+            .try_collect()
+            .await
+    }
+}
+
+/// Check that the merkle branch returned by the server commits `txid` into the block `header`.
+fn is_valid_proof(txid: Txid, proof: &MerkleProof, header: &Header) -> bool {
+    let mut index = proof.pos;
+    let mut hash = txid.to_raw_hash();
+    for node in &proof.merkle {
+        let node = node.to_raw_hash();
+        let mut engine = sha256d::Hash::engine();
+        if index % 2 == 0 {
+            engine.input(hash.as_ref());
+            engine.input(node.as_ref());
+        } else {
+            engine.input(node.as_ref());
+            engine.input(hash.as_ref());
+        }
+        hash = sha256d::Hash::from_engine(engine);
+        index /= 2;
+    }
+    hash == header.merkle_root.to_raw_hash()
+}
@@ -1,44 +1,52 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::{pin, Pin},
+    task::{Context, Poll},
+    time::Duration,
+};
+
 use bdk_esplora::{
-    esplora_client::{AsyncClient, Builder},
+    esplora_client::{AsyncClient, Builder, Error as EsploraError, Sleeper},
     EsploraAsyncExt,
 };
 use bdk_wallet::{
     chain::spk_client::{FullScanRequest as BdkFullScanRequest, SyncRequest as BdkSyncRequest},
     KeychainKind,
 };
+use futures::{
+    channel::oneshot,
+    future::{pending, select, Either},
+};
 use wasm_bindgen::{
     prelude::{wasm_bindgen, Closure},
-    JsCast, JsValue,
+    JsCast,
 };
-use wasm_bindgen_futures::JsFuture;
-use web_sys::js_sys::{Function, Promise};
+use web_sys::AbortSignal;
 
 use crate::{
-    result::JsResult,
-    types::{FeeEstimates, FullScanRequest, SyncRequest, Transaction, Txid, Update},
-};
-use std::{
-    future::Future,
-    pin::Pin,
-    task::{Context, Poll},
-    time::Duration,
+    types::{BdkError, BdkErrorCode, FeeEstimates, FullScanRequest, SyncRequest, Transaction, Txid, Update},
+    utils::timer::{sleep, WebSleep},
 };
 
-use bdk_esplora::esplora_client::Sleeper;
-
+/// Esplora client, using `fetch`.
+///
+/// Calls taking an optional `AbortSignal` reject with `BdkErrorCode.Aborted` once it is aborted,
+/// cancelling the requests still in flight. Nothing is applied to the wallet until the returned
+/// `Update` is, so an aborted call leaves no partial state behind.
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct EsploraClient {
     client: AsyncClient<WebSleeper>,
+    call_timeout: Option<Duration>,
+    backoff: Backoff,
 }
 
 #[wasm_bindgen]
 impl EsploraClient {
     #[wasm_bindgen(constructor)]
-    pub fn new(url: &str, max_retries: usize) -> JsResult<EsploraClient> {
-        let client = Builder::new(url)
-            .max_retries(max_retries)
-            .build_async_with_sleeper::<WebSleeper>()?;
-        Ok(EsploraClient { client })
+    pub fn new(url: &str, max_retries: usize) -> Result<EsploraClient, BdkError> {
+        EsploraClientBuilder::new(url).max_retries(max_retries).build()
     }
 
     pub async fn full_scan(
@@ -46,46 +54,302 @@ impl EsploraClient {
         request: FullScanRequest,
         stop_gap: usize,
         parallel_requests: usize,
-    ) -> JsResult<Update> {
+        signal: Option<AbortSignal>,
+    ) -> Result<Update, BdkError> {
         let request: BdkFullScanRequest<KeychainKind> = request.into();
-        let result = self.client.full_scan(request, stop_gap, parallel_requests).await?;
-        Ok(result.into())
+        self.run(signal, async {
+            let result = self.client.full_scan(request, stop_gap, parallel_requests).await?;
+            Ok(result.into())
+        })
+        .await
     }
 
-    pub async fn sync(&self, request: SyncRequest, parallel_requests: usize) -> JsResult<Update> {
+    pub async fn sync(
+        &self,
+        request: SyncRequest,
+        parallel_requests: usize,
+        signal: Option<AbortSignal>,
+    ) -> Result<Update, BdkError> {
         let request: BdkSyncRequest<(KeychainKind, u32)> = request.into();
-        let result = self.client.sync(request, parallel_requests).await?;
-        Ok(result.into())
+        self.run(signal, async {
+            let result = self.client.sync(request, parallel_requests).await?;
+            Ok(result.into())
+        })
+        .await
     }
 
-    pub async fn broadcast(&self, transaction: &Transaction) -> JsResult<()> {
-        self.client.broadcast(transaction).await?;
-        Ok(())
+    pub async fn broadcast(&self, transaction: &Transaction, signal: Option<AbortSignal>) -> Result<(), BdkError> {
+        self.run(signal, async {
+            self.client.broadcast(transaction).await?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn get_fee_estimates(&self) -> JsResult<FeeEstimates> {
-        let fee_estimates = self.client.get_fee_estimates().await?;
-        Ok(fee_estimates.into())
+    pub async fn get_fee_estimates(&self) -> Result<FeeEstimates, BdkError> {
+        self.run(None, async {
+            let fee_estimates = self.client.get_fee_estimates().await?;
+            Ok(fee_estimates.into())
+        })
+        .await
     }
 
-    pub async fn get_tx(&self, txid: Txid) -> JsResult<Option<Transaction>> {
-        let tx = self.client.get_tx(&txid.into()).await?;
-        Ok(tx.map(Into::into))
+    pub async fn get_tx(&self, txid: Txid, signal: Option<AbortSignal>) -> Result<Option<Transaction>, BdkError> {
+        self.run(signal, async {
+            let tx = self.client.get_tx(&txid.into()).await?;
+            Ok(tx.map(Into::into))
+        })
+        .await
     }
 }
 
-struct WebSleep(JsFuture);
+impl EsploraClient {
+    /// Run `future` until it completes, the client's call timeout expires or `signal` is aborted.
+    /// Dropping the future aborts the `fetch` requests still in flight.
+    ///
+    /// The requests of `future` are retried with the client's backoff.
+    async fn run<T>(
+        &self,
+        signal: Option<AbortSignal>,
+        future: impl Future<Output = Result<T, BdkError>>,
+    ) -> Result<T, BdkError> {
+        let aborted = match signal {
+            Some(signal) if signal.aborted() => return Err(aborted_error()),
+            Some(signal) => Either::Left(Aborted::new(signal)),
+            None => Either::Right(pending()),
+        };
+        let timeout = match self.call_timeout {
+            Some(timeout) => Either::Left(sleep(timeout)),
+            None => Either::Right(pending()),
+        };
+        let future = WithBackoff {
+            backoff: self.backoff,
+            future: pin!(future),
+        };
+
+        match select(future, select(aborted, timeout)).await {
+            Either::Left((result, _)) => result,
+            Either::Right((Either::Left(_), _)) => Err(aborted_error()),
+            Either::Right((Either::Right(_), _)) => Err(BdkError::new(
+                BdkErrorCode::Esplora,
+                format!(
+                    "Esplora call timed out after {} ms",
+                    self.call_timeout.unwrap_or_default().as_millis()
+                ),
+                (),
+            )),
+        }
+    }
+}
+
+/// Delays between the retries of a request: `base` before the first one, multiplied by `factor`
+/// before each next one, up to `max`.
+#[derive(Clone, Copy)]
+struct Backoff {
+    base: Duration,
+    factor: f64,
+    max: Duration,
+}
+
+impl Default for Backoff {
+    /// The backoff of `esplora-client`.
+    fn default() -> Self {
+        Backoff {
+            base: ESPLORA_BASE_BACKOFF,
+            factor: 2.0,
+            max: Duration::MAX,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before the retry following `attempts` failed ones.
+    fn delay(&self, attempts: u32) -> Duration {
+        let delay = self.base.as_secs_f64() * self.factor.powi(attempts.min(i32::MAX as u32) as i32);
+        Duration::try_from_secs_f64(delay)
+            .unwrap_or(Duration::MAX)
+            .min(self.max)
+    }
+}
+
+/// The first delay of `esplora-client` before retrying a request, doubled before each next retry.
+const ESPLORA_BASE_BACKOFF: Duration = Duration::from_millis(256);
+
+thread_local! {
+    /// The backoff of the client whose call is being polled, for `WebSleeper` to apply when
+    /// `esplora-client` waits before retrying a request.
+    static BACKOFF: Cell<Option<Backoff>> = const { Cell::new(None) };
+}
+
+/// Polls `future` with the `backoff` of its client.
+struct WithBackoff<'a, F> {
+    backoff: Backoff,
+    future: Pin<&'a mut F>,
+}
+
+impl<F: Future> Future for WithBackoff<'_, F> {
+    type Output = F::Output;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let previous = BACKOFF.replace(Some(self.backoff));
+        let poll = self.future.as_mut().poll(cx);
+        BACKOFF.set(previous);
+        poll
+    }
+}
+
+/// Completes when an `AbortSignal` is aborted.
+struct Aborted {
+    receiver: oneshot::Receiver<()>,
+    signal: AbortSignal,
+    listener: Closure<dyn FnMut()>,
+}
 
-impl Future for WebSleep {
+impl Aborted {
+    fn new(signal: AbortSignal) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        let mut sender = Some(sender);
+        let listener = Closure::<dyn FnMut()>::new(move || {
+            if let Some(sender) = sender.take() {
+                let _ = sender.send(());
+            }
+        });
+        let _ = signal.add_event_listener_with_callback("abort", listener.as_ref().unchecked_ref());
+        Aborted {
+            receiver,
+            signal,
+            listener,
+        }
+    }
+}
+
+impl Future for Aborted {
     type Output = ();
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
-        // delegate to the inner JsFuture
-        Pin::new(&mut self.get_mut().0).poll(cx).map(|_| ())
+        Pin::new(&mut self.get_mut().receiver).poll(cx).map(|_| ())
+    }
+}
+
+impl Drop for Aborted {
+    fn drop(&mut self) {
+        let _ = self
+            .signal
+            .remove_event_listener_with_callback("abort", self.listener.as_ref().unchecked_ref());
     }
 }
 
-// SAFETY: Wasm is single-threaded; the value is never accessed concurrently.
-unsafe impl Send for WebSleep {}
+fn aborted_error() -> BdkError {
+    BdkError::new(BdkErrorCode::Aborted, "The operation was aborted", ())
+}
+
+impl From<EsploraError> for BdkError {
+    fn from(e: EsploraError) -> Self {
+        match e {
+            EsploraError::HttpResponse { status, message } => {
+                BdkError::new(BdkErrorCode::Esplora, format!("HTTP {status}: {message}"), status)
+            }
+            e => BdkError::new(BdkErrorCode::Esplora, e.to_string(), ()),
+        }
+    }
+}
+
+impl From<Box<EsploraError>> for BdkError {
+    fn from(e: Box<EsploraError>) -> Self {
+        (*e).into()
+    }
+}
+
+/// Builder of an [`EsploraClient`] with custom headers, call timeout and retries.
+///
+/// Each option setting method takes and returns the builder so you can chain calls, until
+/// [`build`] creates the client.
+#[wasm_bindgen]
+pub struct EsploraClientBuilder {
+    builder: Builder,
+    call_timeout: Option<Duration>,
+    backoff: Backoff,
+}
+
+#[wasm_bindgen]
+impl EsploraClientBuilder {
+    /// Create a builder for the Esplora server at `url`, e.g. `https://mempool.space/api`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str) -> EsploraClientBuilder {
+        EsploraClientBuilder {
+            builder: Builder::new(url),
+            call_timeout: None,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Add a header sent with every request. Invalid names or values make [`build`] fail.
+    pub fn header(mut self, key: &str, value: &str) -> EsploraClientBuilder {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    /// Authenticate every request with an `Authorization: Bearer <token>` header, as required by
+    /// some Esplora providers.
+    pub fn bearer_token(self, token: &str) -> EsploraClientBuilder {
+        self.header("Authorization", &format!("Bearer {token}"))
+    }
+
+    /// Set the `User-Agent` header. Browsers may ignore it and send their own.
+    pub fn user_agent(self, user_agent: &str) -> EsploraClientBuilder {
+        self.header("User-Agent", user_agent)
+    }
+
+    /// Fail the client's calls (`full_scan`, `sync`, `broadcast`, ...) that take longer than
+    /// `timeout_ms` milliseconds. No timeout is set by default.
+    ///
+    /// This is not a per-request timeout: it covers a whole call, with all of its HTTP requests
+    /// and their retries, so it must allow for the size of the scans. Per-request timeouts are not
+    /// supported by `esplora-client` on wasm.
+    pub fn call_timeout(mut self, timeout_ms: u32) -> EsploraClientBuilder {
+        self.call_timeout = Some(Duration::from_millis(timeout_ms.into()));
+        self
+    }
+
+    /// Retry a request up to `max_retries` times when the server answers with HTTP 429, 500 or
+    /// 503. Defaults to 6.
+    pub fn max_retries(mut self, max_retries: usize) -> EsploraClientBuilder {
+        self.builder = self.builder.max_retries(max_retries);
+        self
+    }
+
+    /// Wait `base_delay_ms` milliseconds before the first retry of a request, multiplied by
+    /// `factor` before each next one and capped at `max_delay_ms`. Defaults to 256 ms doubling at
+    /// each retry, without cap.
+    ///
+    /// [`build`] fails if `factor` is below 1.
+    pub fn retry_backoff(mut self, base_delay_ms: u32, factor: f64, max_delay_ms: u32) -> EsploraClientBuilder {
+        self.backoff = Backoff {
+            base: Duration::from_millis(base_delay_ms.into()),
+            factor,
+            max: Duration::from_millis(max_delay_ms.into()),
+        };
+        self
+    }
+
+    pub fn build(self) -> Result<EsploraClient, BdkError> {
+        if self.backoff.factor.is_nan() || self.backoff.factor < 1.0 {
+            return Err(BdkError::new(
+                BdkErrorCode::Esplora,
+                format!(
+                    "Invalid retry backoff factor {}, it must be at least 1",
+                    self.backoff.factor
+                ),
+                (),
+            ));
+        }
+
+        let client = self.builder.build_async_with_sleeper::<WebSleeper>()?;
+        Ok(EsploraClient {
+            client,
+            call_timeout: self.call_timeout,
+            backoff: self.backoff,
+        })
+    }
+}
 
 #[derive(Clone, Copy)]
 struct WebSleeper;
@@ -93,15 +357,16 @@ struct WebSleeper;
 impl Sleeper for WebSleeper {
     type Sleep = WebSleep;
 
+    /// Called by `esplora-client` to wait before retrying a request, with its own backoff which
+    /// gives the number of failed attempts.
     fn sleep(dur: Duration) -> Self::Sleep {
-        let ms = dur.as_millis() as i32;
-        let promise = Promise::new(&mut |resolve, _reject| {
-            let cb = Closure::once_into_js(move || resolve.call0(&JsValue::NULL).unwrap());
-            web_sys::window()
-                .unwrap()
-                .set_timeout_with_callback_and_timeout_and_arguments_0(cb.unchecked_ref::<Function>(), ms)
-                .unwrap();
-        });
-        WebSleep(JsFuture::from(promise))
+        let delay = match BACKOFF.get() {
+            Some(backoff) => {
+                let attempts = (dur.as_millis() / ESPLORA_BASE_BACKOFF.as_millis()).max(1).ilog2();
+                backoff.delay(attempts)
+            }
+            None => dur,
+        };
+        sleep(delay)
     }
 }
@@ -0,0 +1,609 @@
+// The signer API of bdk_wallet is deprecated in favour of `bitcoin::psbt`, but it is still the
+// only way to take part in `Wallet::sign`.
+#![allow(deprecated)]
+
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use bdk_wallet::{
+    bitcoin::{
+        bip32::{Fingerprint, KeySource},
+        ecdsa,
+        hex::DisplayHex,
+        key::{TapTweak, XOnlyPublicKey},
+        psbt::Input as BdkPsbtInput,
+        secp256k1::{
+            ecdsa::Signature as EcdsaSignature, schnorr::Signature as SchnorrSignature, All, Message, Secp256k1,
+        },
+        sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType},
+        taproot::{self, TapLeafHash, TapNodeHash},
+        Psbt as BdkPsbt, PublicKey, TxOut,
+    },
+    signer::{SignerCommon, SignerError, SignerId, SignerOrdering, TransactionSigner},
+    KeychainKind, SignOptions as BdkSignOptions, Wallet as BdkWallet,
+};
+use wasm_bindgen::{convert::TryFromJsValue, prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::js_sys::{Error, Promise, Reflect, Uint8Array};
+
+use crate::types::{BdkError, BdkErrorCode, Psbt};
+
+#[wasm_bindgen(typescript_custom_section)]
+const EXTERNAL_SIGNER: &str = r#"
+/**
+ * A signer implemented by the host, e.g. a bridge to a hardware wallet.
+ *
+ * Either `signPsbt` signs the whole PSBT at once and returns it (or its base64 encoding) with
+ * the new signatures, or `signEcdsa` and `signSchnorr` sign the sighash of a single input and
+ * return the signature bytes: 64-byte compact or DER encoded for ECDSA, 64 bytes for Schnorr.
+ */
+export interface ExternalSigner {
+  signPsbt?(psbt: Psbt): Promise<Psbt | string>;
+  signEcdsa?(request: SignatureRequest): Promise<Uint8Array>;
+  signSchnorr?(request: SignatureRequest): Promise<Uint8Array>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS object implementing the `ExternalSigner` interface.
+    #[wasm_bindgen(typescript_type = "ExternalSigner")]
+    #[derive(Clone)]
+    pub type JsExternalSigner;
+
+    #[wasm_bindgen(method, catch, js_name = signPsbt)]
+    fn js_sign_psbt(this: &JsExternalSigner, psbt: Psbt) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = signEcdsa)]
+    fn js_sign_ecdsa(this: &JsExternalSigner, request: SignatureRequest) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = signSchnorr)]
+    fn js_sign_schnorr(this: &JsExternalSigner, request: SignatureRequest) -> Result<JsValue, JsValue>;
+}
+
+/// External signers run after the signers of the wallet's own keys, using the default ordering.
+const EXTERNAL_SIGNER_ORDERING: usize = 200;
+
+/// A signature an external signer is asked to produce for one input of a PSBT.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SignatureRequest {
+    input_index: usize,
+    sighash: Message,
+    key: RequestKey,
+    key_source: KeySource,
+    merkle_root: Option<TapNodeHash>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RequestKey {
+    Ecdsa(PublicKey, EcdsaSighashType),
+    TapKey(XOnlyPublicKey, TapSighashType),
+    TapScript(XOnlyPublicKey, TapLeafHash, TapSighashType),
+}
+
+#[wasm_bindgen]
+impl SignatureRequest {
+    /// Index of the input to sign
+    #[wasm_bindgen(getter)]
+    pub fn input_index(&self) -> usize {
+        self.input_index
+    }
+
+    /// The 32-byte message to sign
+    #[wasm_bindgen(getter)]
+    pub fn sighash(&self) -> Vec<u8> {
+        self.sighash.as_ref().to_vec()
+    }
+
+    /// Sighash type of the signature
+    #[wasm_bindgen(getter)]
+    pub fn sighash_type(&self) -> u32 {
+        match self.key {
+            RequestKey::Ecdsa(_, sighash_type) => sighash_type.to_u32(),
+            RequestKey::TapKey(_, sighash_type) | RequestKey::TapScript(_, _, sighash_type) => sighash_type as u32,
+        }
+    }
+
+    /// Hex encoded public key to sign with: compressed for ECDSA, x-only for Schnorr
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> String {
+        match self.key {
+            RequestKey::Ecdsa(public_key, _) => public_key.to_string(),
+            RequestKey::TapKey(public_key, _) | RequestKey::TapScript(public_key, _, _) => public_key.to_string(),
+        }
+    }
+
+    /// Fingerprint of the master key of the signer
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.key_source.0.to_string()
+    }
+
+    /// Derivation path of the public key from the master key
+    #[wasm_bindgen(getter)]
+    pub fn derivation_path(&self) -> String {
+        self.key_source.1.to_string()
+    }
+
+    /// Whether the signature is for a taproot key path spend.
+    ///
+    /// The key must then be tweaked with the `merkle_root` before signing.
+    #[wasm_bindgen(getter)]
+    pub fn is_key_spend(&self) -> bool {
+        matches!(self.key, RequestKey::TapKey(..))
+    }
+
+    /// Hex encoded taproot merkle root tweaking the key of a key path spend
+    #[wasm_bindgen(getter)]
+    pub fn merkle_root(&self) -> Option<String> {
+        self.merkle_root.map(|root| root.to_string())
+    }
+
+    /// Hex encoded hash of the leaf script of a taproot script path spend
+    #[wasm_bindgen(getter)]
+    pub fn leaf_hash(&self) -> Option<String> {
+        match self.key {
+            RequestKey::TapScript(_, leaf_hash, _) => Some(leaf_hash.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The signatures requested from one external signer while the wallet signs a PSBT.
+#[derive(Debug, Default)]
+struct Requests {
+    /// Only record requests while `Wallet::sign_async` is collecting them.
+    active: bool,
+    psbt: bool,
+    inputs: Vec<SignatureRequest>,
+    seen: HashSet<(usize, RequestKey)>,
+}
+
+/// Stand-in for an external signer in the wallet's `SignersContainer`.
+///
+/// The container decides when the signer runs, while the JS callbacks are async: the proxy only
+/// records the signatures it is asked for, which `ExternalSigners::sign` then requests from JS and
+/// adds to the PSBT.
+#[derive(Debug)]
+struct ExternalSignerProxy {
+    fingerprint: Fingerprint,
+    sign_psbt: bool,
+    requests: Arc<Mutex<Requests>>,
+}
+
+impl SignerCommon for ExternalSignerProxy {
+    fn id(&self, _secp: &Secp256k1<All>) -> SignerId {
+        SignerId::Fingerprint(self.fingerprint)
+    }
+}
+
+impl TransactionSigner for ExternalSignerProxy {
+    fn sign_transaction(
+        &self,
+        psbt: &mut BdkPsbt,
+        _sign_options: &BdkSignOptions,
+        _secp: &Secp256k1<All>,
+    ) -> Result<(), SignerError> {
+        let mut requests = self.requests.lock().expect("requests lock poisoned");
+        if !requests.active {
+            return Ok(());
+        }
+
+        let inputs = input_requests(psbt, self.fingerprint);
+        if self.sign_psbt {
+            requests.psbt |= !inputs.is_empty();
+        } else {
+            for request in inputs {
+                if requests.seen.insert((request.input_index, request.key)) {
+                    requests.inputs.push(request);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An external signer registered with a wallet.
+#[derive(Clone)]
+struct ExternalSignerEntry {
+    fingerprint: Fingerprint,
+    signer: JsExternalSigner,
+    requests: Arc<Mutex<Requests>>,
+}
+
+/// The external signers of a wallet.
+#[derive(Clone, Default)]
+pub(crate) struct ExternalSigners(Rc<RefCell<Vec<ExternalSignerEntry>>>);
+
+impl ExternalSigners {
+    /// Register `signer` for the keys of `fingerprint`, on both keychains of `wallet`.
+    pub(crate) fn add(
+        &self,
+        wallet: &mut BdkWallet,
+        fingerprint: &str,
+        signer: JsExternalSigner,
+    ) -> Result<(), BdkError> {
+        let fingerprint = Fingerprint::from_str(fingerprint)
+            .map_err(|e| BdkError::new(BdkErrorCode::Signer, format!("Invalid fingerprint: {e}"), ()))?;
+        let sign_psbt = has_method(&signer, "signPsbt");
+        if !sign_psbt && !has_method(&signer, "signEcdsa") && !has_method(&signer, "signSchnorr") {
+            return Err(BdkError::new(
+                BdkErrorCode::Signer,
+                "An external signer must implement `signPsbt`, or `signEcdsa` and `signSchnorr`",
+                (),
+            ));
+        }
+
+        let requests = Arc::new(Mutex::new(Requests::default()));
+        let proxy = Arc::new(ExternalSignerProxy {
+            fingerprint,
+            sign_psbt,
+            requests: requests.clone(),
+        });
+        for keychain in [KeychainKind::External, KeychainKind::Internal] {
+            wallet.add_signer(keychain, SignerOrdering(EXTERNAL_SIGNER_ORDERING), proxy.clone());
+        }
+
+        let mut entries = self.0.borrow_mut();
+        entries.retain(|entry| entry.fingerprint != fingerprint);
+        entries.push(ExternalSignerEntry {
+            fingerprint,
+            signer,
+            requests,
+        });
+        Ok(())
+    }
+
+    /// Sign `psbt` with the wallet's signers, then with the external signers they asked for.
+    ///
+    /// Returns whether the PSBT was finalized, as `Wallet::sign` does.
+    pub(crate) async fn sign(
+        &self,
+        wallet: &RefCell<BdkWallet>,
+        psbt: &mut Psbt,
+        options: BdkSignOptions,
+    ) -> Result<bool, BdkError> {
+        let entries = self.0.borrow().clone();
+        for entry in &entries {
+            *entry.requests.lock().expect("requests lock poisoned") = Requests {
+                active: true,
+                ..Requests::default()
+            };
+        }
+
+        let try_finalize = options.try_finalize;
+        let collect_options = BdkSignOptions {
+            try_finalize: false,
+            ..options.clone()
+        };
+        let collected = wallet.borrow().sign(psbt, collect_options);
+
+        let mut pending = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let mut requests = entry.requests.lock().expect("requests lock poisoned");
+            let requests = std::mem::take(&mut *requests);
+            pending.push((entry, requests.psbt, requests.inputs));
+        }
+        collected?;
+
+        for (entry, sign_psbt, inputs) in pending {
+            if sign_psbt {
+                let signed = call(entry, entry.signer.js_sign_psbt(psbt.clone())).await?;
+                let signed = match signed.as_string() {
+                    Some(base64) => Psbt::from_string(&base64)?,
+                    None => Psbt::try_from_js_value(signed).map_err(|_| {
+                        signer_error(entry, "`signPsbt` must resolve to a `Psbt` or its base64 encoding")
+                    })?,
+                };
+                merge_signatures(wallet, entry, psbt, &signed)?;
+            }
+
+            for request in inputs {
+                let result = match request.key {
+                    RequestKey::Ecdsa(..) => entry.signer.js_sign_ecdsa(request.clone()),
+                    _ => entry.signer.js_sign_schnorr(request.clone()),
+                };
+                let signature = call(entry, result).await?;
+                let signature = signature
+                    .dyn_into::<Uint8Array>()
+                    .map_err(|_| signer_error(entry, "Signatures must be returned as a `Uint8Array`"))?
+                    .to_vec();
+                add_signature(wallet, entry, psbt, &request, &signature)?;
+            }
+        }
+
+        if !try_finalize {
+            return Ok(false);
+        }
+        Ok(wallet.borrow().finalize_psbt(psbt, options)?)
+    }
+}
+
+/// The signatures `fingerprint` can add to the unfinalized inputs of `psbt`.
+fn input_requests(psbt: &BdkPsbt, fingerprint: Fingerprint) -> Vec<SignatureRequest> {
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| input_utxo(psbt, index, input))
+        .collect::<Option<Vec<TxOut>>>();
+
+    let mut requests = Vec::new();
+    for (input_index, input) in psbt.inputs.iter().enumerate() {
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+
+        for (public_key, key_source) in &input.bip32_derivation {
+            let public_key = PublicKey::new(*public_key);
+            if key_source.0 != fingerprint || input.partial_sigs.contains_key(&public_key) {
+                continue;
+            }
+            if let Ok((sighash, sighash_type)) = psbt.sighash_ecdsa(input_index, &mut cache) {
+                requests.push(SignatureRequest {
+                    input_index,
+                    sighash,
+                    key: RequestKey::Ecdsa(public_key, sighash_type),
+                    key_source: key_source.clone(),
+                    merkle_root: None,
+                });
+            }
+        }
+
+        let Some(prevouts) = &prevouts else {
+            continue;
+        };
+        let Ok(sighash_type) = input.sighash_type.map_or(Ok(TapSighashType::Default), |sighash_type| {
+            sighash_type.taproot_hash_ty()
+        }) else {
+            continue;
+        };
+        for (public_key, (leaf_hashes, key_source)) in &input.tap_key_origins {
+            if key_source.0 != fingerprint {
+                continue;
+            }
+
+            if input.tap_internal_key == Some(*public_key) && input.tap_key_sig.is_none() {
+                if let Ok(sighash) =
+                    cache.taproot_key_spend_signature_hash(input_index, &Prevouts::All(prevouts), sighash_type)
+                {
+                    requests.push(SignatureRequest {
+                        input_index,
+                        sighash: sighash.into(),
+                        key: RequestKey::TapKey(*public_key, sighash_type),
+                        key_source: key_source.clone(),
+                        merkle_root: input.tap_merkle_root,
+                    });
+                }
+            }
+
+            for leaf_hash in leaf_hashes {
+                if input.tap_script_sigs.contains_key(&(*public_key, *leaf_hash)) {
+                    continue;
+                }
+                if let Ok(sighash) = cache.taproot_script_spend_signature_hash(
+                    input_index,
+                    &Prevouts::All(prevouts),
+                    *leaf_hash,
+                    sighash_type,
+                ) {
+                    requests.push(SignatureRequest {
+                        input_index,
+                        sighash: sighash.into(),
+                        key: RequestKey::TapScript(*public_key, *leaf_hash, sighash_type),
+                        key_source: key_source.clone(),
+                        merkle_root: None,
+                    });
+                }
+            }
+        }
+    }
+
+    requests
+}
+
+fn input_utxo(psbt: &BdkPsbt, index: usize, input: &BdkPsbtInput) -> Option<TxOut> {
+    if let Some(txout) = &input.witness_utxo {
+        return Some(txout.clone());
+    }
+
+    let vout = psbt.unsigned_tx.input.get(index)?.previous_output.vout as usize;
+    input.non_witness_utxo.as_ref()?.output.get(vout).cloned()
+}
+
+/// Check the signature returned for `request` and add it to its input.
+fn add_signature(
+    wallet: &RefCell<BdkWallet>,
+    entry: &ExternalSignerEntry,
+    psbt: &mut Psbt,
+    request: &SignatureRequest,
+    signature: &[u8],
+) -> Result<(), BdkError> {
+    let wallet = wallet.borrow();
+    let secp = wallet.secp_ctx();
+    let invalid = || {
+        signer_error(
+            entry,
+            format!(
+                "Invalid signature for input {}: {}",
+                request.input_index,
+                signature.as_hex()
+            ),
+        )
+    };
+    let input = &mut psbt.inputs[request.input_index];
+
+    match request.key {
+        RequestKey::Ecdsa(public_key, sighash_type) => {
+            let mut signature = EcdsaSignature::from_compact(signature)
+                .or_else(|_| EcdsaSignature::from_der(signature))
+                .map_err(|_| invalid())?;
+            signature.normalize_s();
+            secp.verify_ecdsa(&request.sighash, &signature, &public_key.inner)
+                .map_err(|_| invalid())?;
+            input.partial_sigs.insert(
+                public_key,
+                ecdsa::Signature {
+                    signature,
+                    sighash_type,
+                },
+            );
+        }
+        RequestKey::TapKey(public_key, sighash_type) => {
+            let signature = SchnorrSignature::from_slice(signature).map_err(|_| invalid())?;
+            let (output_key, _) = public_key.tap_tweak(secp, request.merkle_root);
+            secp.verify_schnorr(&signature, &request.sighash, &output_key.to_x_only_public_key())
+                .map_err(|_| invalid())?;
+            input.tap_key_sig = Some(taproot::Signature {
+                signature,
+                sighash_type,
+            });
+        }
+        RequestKey::TapScript(public_key, leaf_hash, sighash_type) => {
+            let signature = SchnorrSignature::from_slice(signature).map_err(|_| invalid())?;
+            secp.verify_schnorr(&signature, &request.sighash, &public_key)
+                .map_err(|_| invalid())?;
+            input.tap_script_sigs.insert(
+                (public_key, leaf_hash),
+                taproot::Signature {
+                    signature,
+                    sighash_type,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy the signatures of a PSBT signed by `signPsbt` into `psbt`, once checked.
+fn merge_signatures(
+    wallet: &RefCell<BdkWallet>,
+    entry: &ExternalSignerEntry,
+    psbt: &mut Psbt,
+    signed: &Psbt,
+) -> Result<(), BdkError> {
+    if signed.unsigned_tx.compute_txid() != psbt.unsigned_tx.compute_txid() || signed.inputs.len() != psbt.inputs.len()
+    {
+        return Err(signer_error(entry, "`signPsbt` returned a different transaction"));
+    }
+    check_signatures(wallet.borrow().secp_ctx(), entry, psbt, signed)?;
+
+    for (input, signed) in psbt.inputs.iter_mut().zip(&signed.inputs) {
+        input
+            .partial_sigs
+            .extend(signed.partial_sigs.iter().map(|(key, sig)| (*key, *sig)));
+        input
+            .tap_script_sigs
+            .extend(signed.tap_script_sigs.iter().map(|(key, sig)| (*key, *sig)));
+        if input.tap_key_sig.is_none() {
+            input.tap_key_sig = signed.tap_key_sig;
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify the signatures `signed` adds to `psbt` against the sighashes of their inputs, as
+/// `add_signature` does for the signatures of single inputs.
+fn check_signatures(
+    secp: &Secp256k1<All>,
+    entry: &ExternalSignerEntry,
+    psbt: &BdkPsbt,
+    signed: &BdkPsbt,
+) -> Result<(), BdkError> {
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| input_utxo(psbt, index, input))
+        .collect::<Option<Vec<TxOut>>>();
+
+    for (input_index, (input, signed)) in psbt.inputs.iter().zip(&signed.inputs).enumerate() {
+        let invalid = || signer_error(entry, format!("Invalid signature for input {input_index}"));
+
+        for (public_key, signature) in &signed.partial_sigs {
+            if input.partial_sigs.get(public_key) == Some(signature) {
+                continue;
+            }
+            let (sighash, sighash_type) = psbt.sighash_ecdsa(input_index, &mut cache).map_err(|_| invalid())?;
+            if signature.sighash_type != sighash_type {
+                return Err(invalid());
+            }
+            secp.verify_ecdsa(&sighash, &signature.signature, &public_key.inner)
+                .map_err(|_| invalid())?;
+        }
+
+        if let Some(signature) = signed.tap_key_sig.filter(|_| input.tap_key_sig.is_none()) {
+            let (Some(prevouts), Some(internal_key)) = (&prevouts, input.tap_internal_key) else {
+                return Err(invalid());
+            };
+            let sighash = cache
+                .taproot_key_spend_signature_hash(input_index, &Prevouts::All(prevouts), signature.sighash_type)
+                .map_err(|_| invalid())?;
+            let (output_key, _) = internal_key.tap_tweak(secp, input.tap_merkle_root);
+            secp.verify_schnorr(
+                &signature.signature,
+                &sighash.into(),
+                &output_key.to_x_only_public_key(),
+            )
+            .map_err(|_| invalid())?;
+        }
+
+        for ((public_key, leaf_hash), signature) in &signed.tap_script_sigs {
+            if input.tap_script_sigs.get(&(*public_key, *leaf_hash)) == Some(signature) {
+                continue;
+            }
+            let Some(prevouts) = &prevouts else {
+                return Err(invalid());
+            };
+            let sighash = cache
+                .taproot_script_spend_signature_hash(
+                    input_index,
+                    &Prevouts::All(prevouts),
+                    *leaf_hash,
+                    signature.sighash_type,
+                )
+                .map_err(|_| invalid())?;
+            secp.verify_schnorr(&signature.signature, &sighash.into(), public_key)
+                .map_err(|_| invalid())?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn call(entry: &ExternalSignerEntry, result: Result<JsValue, JsValue>) -> Result<JsValue, BdkError> {
+    let js_error = |e: JsValue| {
+        let message = match e.dyn_ref::<Error>() {
+            Some(error) => String::from(error.message()),
+            None => e.as_string().unwrap_or_else(|| format!("{e:?}")),
+        };
+        signer_error(entry, message)
+    };
+
+    JsFuture::from(Promise::resolve(&result.map_err(js_error)?))
+        .await
+        .map_err(js_error)
+}
+
+fn has_method(signer: &JsExternalSigner, name: &str) -> bool {
+    Reflect::get(signer, &JsValue::from_str(name)).is_ok_and(|value| value.is_function())
+}
+
+fn signer_error(entry: &ExternalSignerEntry, message: impl std::fmt::Display) -> BdkError {
+    BdkError::new(
+        BdkErrorCode::Signer,
+        format!("External signer {}: {message}", entry.fingerprint),
+        (),
+    )
+}
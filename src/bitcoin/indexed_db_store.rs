@@ -0,0 +1,219 @@
+use std::{future::Future, pin::Pin};
+
+use bdk_wallet::{chain::Merge, AsyncWalletPersister, ChangeSet as BdkChangeSet};
+use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen, JsCast, JsError, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::{global, Array, Function, Promise, Reflect, Uint8Array},
+    IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStoreParameters, IdbRequest, IdbTransaction, IdbTransactionMode,
+};
+
+use crate::{
+    types::{decode_changeset, encode_changeset, BdkError},
+    utils::future::SendFuture,
+};
+
+use super::persister::persist_error;
+
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "changesets";
+
+/// A [`Wallet`] store backed by IndexedDB.
+///
+/// Every persisted changeset is appended as a new record, so each flush is a single small write.
+/// Records are merged back together, and compacted into one, when the store is initialized.
+///
+/// Works in browsers and web workers. Node does not provide IndexedDB.
+#[wasm_bindgen]
+pub struct IndexedDbStore {
+    name: String,
+    db: Option<IdbDatabase>,
+}
+
+#[wasm_bindgen]
+impl IndexedDbStore {
+    /// Create a store persisting into the IndexedDB database called `name`.
+    ///
+    /// The database is only opened once the store is given to a `Wallet`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: String) -> IndexedDbStore {
+        IndexedDbStore { name, db: None }
+    }
+
+    /// The name of the IndexedDB database.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Delete the IndexedDB database called `name` along with all the wallet data it contains.
+    pub async fn delete(name: String) -> Result<(), BdkError> {
+        let request = factory()
+            .and_then(|f| f.delete_database(&name))
+            .map_err(persist_error)?;
+        request_result(&request).await.map_err(persist_error)?;
+        Ok(())
+    }
+}
+
+impl IndexedDbStore {
+    async fn database(&mut self) -> Result<IdbDatabase, JsValue> {
+        if let Some(db) = &self.db {
+            return Ok(db.clone());
+        }
+
+        let request = factory()?.open_with_u32(&self.name, DB_VERSION)?;
+        let upgrade_request = request.clone();
+        let on_upgrade_needed = Closure::<dyn FnMut() -> Result<(), JsValue>>::new(move || {
+            let db: IdbDatabase = upgrade_request.result()?.unchecked_into();
+            let params = IdbObjectStoreParameters::new();
+            params.set_auto_increment(true);
+            db.create_object_store_with_optional_parameters(STORE_NAME, &params)?;
+            Ok(())
+        });
+        request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+        let result = request_result(&request).await;
+        request.set_onupgradeneeded(None);
+
+        let db: IdbDatabase = result?.unchecked_into();
+        self.db = Some(db.clone());
+        Ok(db)
+    }
+
+    async fn load(&mut self) -> Result<BdkChangeSet, JsValue> {
+        let db = self.database().await?;
+
+        let tx = db.transaction_with_str(STORE_NAME)?;
+        let store = tx.object_store(STORE_NAME)?;
+        let keys_request = store.get_all_keys()?;
+        let values_request = store.get_all()?;
+        let keys: Array = request_result(&keys_request).await?.unchecked_into();
+        let values: Array = request_result(&values_request).await?.unchecked_into();
+
+        let mut changeset = BdkChangeSet::default();
+        for value in values.iter() {
+            changeset.merge(decode(&value)?);
+        }
+
+        // Compact all the records into the most recent one.
+        if keys.length() > 1 {
+            let last_key = keys.get(keys.length() - 1);
+            let tx = db.transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?;
+            let store = tx.object_store(STORE_NAME)?;
+            store.put_with_key(&encode(&changeset)?, &last_key)?;
+            store.delete(&IdbKeyRange::upper_bound_with_open(&last_key, true)?.into())?;
+            transaction_complete(&tx).await?;
+        }
+
+        Ok(changeset)
+    }
+
+    async fn append(&mut self, changeset: &BdkChangeSet) -> Result<(), JsValue> {
+        let db = self.database().await?;
+
+        let tx = db.transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?;
+        tx.object_store(STORE_NAME)?.add(&encode(changeset)?)?;
+        transaction_complete(&tx).await
+    }
+}
+
+impl AsyncWalletPersister for IndexedDbStore {
+    type Error = JsValue;
+
+    fn initialize<'a>(
+        persister: &'a mut Self,
+    ) -> Pin<Box<dyn Future<Output = Result<BdkChangeSet, Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendFuture(persister.load()))
+    }
+
+    fn persist<'a>(
+        persister: &'a mut Self,
+        changeset: &'a BdkChangeSet,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendFuture(persister.append(changeset)))
+    }
+}
+
+fn encode(changeset: &BdkChangeSet) -> Result<JsValue, JsValue> {
+    Ok(Uint8Array::from(encode_changeset(changeset).as_slice()).into())
+}
+
+fn decode(value: &JsValue) -> Result<BdkChangeSet, JsValue> {
+    // Records written before the binary encoding are JSON strings, `from_bytes` reads both.
+    let bytes = match value.as_string() {
+        Some(json) => json.into_bytes(),
+        None => value
+            .dyn_ref::<Uint8Array>()
+            .ok_or_else(|| JsError::new("Invalid changeset record"))?
+            .to_vec(),
+    };
+    decode_changeset(&bytes).map_err(|e| JsError::new(&e.message()).into())
+}
+
+// `indexedDB` is looked up on the global object so the store also works in web workers.
+fn factory() -> Result<IdbFactory, JsValue> {
+    let factory = Reflect::get(&global(), &JsValue::from_str("indexedDB"))?;
+    if factory.is_undefined() {
+        return Err(JsError::new("IndexedDB is not available in this environment").into());
+    }
+    Ok(factory.unchecked_into())
+}
+
+/// Resolves with the result of `request`, or rejects with its error.
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let (promise, resolve, reject) = deferred();
+
+    let success_request = request.clone();
+    let on_success = Closure::<dyn FnMut()>::new(move || {
+        let _ = resolve.call1(&JsValue::NULL, &success_request.result().unwrap_or_default());
+    });
+    let error_request = request.clone();
+    let on_error = Closure::<dyn FnMut()>::new(move || {
+        let error = error_request.error().ok().flatten().map(JsValue::from);
+        let _ = reject.call1(&JsValue::NULL, &error.unwrap_or_default());
+    });
+
+    request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+    request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let result = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    result
+}
+
+/// Resolves once `tx` is committed, or rejects if it fails or is aborted.
+async fn transaction_complete(tx: &IdbTransaction) -> Result<(), JsValue> {
+    let (promise, resolve, reject) = deferred();
+
+    let on_complete = Closure::<dyn FnMut()>::new(move || {
+        let _ = resolve.call0(&JsValue::NULL);
+    });
+    let error_tx = tx.clone();
+    let on_error = Closure::<dyn FnMut()>::new(move || {
+        let error = error_tx.error().map(JsValue::from);
+        let _ = reject.call1(&JsValue::NULL, &error.unwrap_or_default());
+    });
+
+    tx.set_oncomplete(Some(on_complete.as_ref().unchecked_ref()));
+    tx.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    tx.set_onabort(Some(on_error.as_ref().unchecked_ref()));
+    let result = JsFuture::from(promise).await;
+    tx.set_oncomplete(None);
+    tx.set_onerror(None);
+    tx.set_onabort(None);
+    result.map(|_| ())
+}
+
+fn deferred() -> (Promise, Function, Function) {
+    let mut callbacks = None;
+    let promise = Promise::new(&mut |resolve, reject| callbacks = Some((resolve, reject)));
+    // The executor of a `Promise` runs synchronously.
+    let (resolve, reject) = callbacks.expect("Promise executor should have run");
+    (promise, resolve, reject)
+}
@@ -0,0 +1,366 @@
+use bdk_wallet::{
+    bitcoin::{
+        absolute::LockTime,
+        base64::{prelude::BASE64_STANDARD, Engine},
+        bip32::ChildNumber,
+        consensus::{deserialize, serialize},
+        ecdsa,
+        hashes::{sha256, Hash, HashEngine},
+        key::{Keypair, TapTweak, XOnlyPublicKey},
+        opcodes::all::{OP_PUSHBYTES_0, OP_RETURN},
+        script::Builder,
+        secp256k1::{All, Message, Secp256k1, Verification},
+        sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType},
+        sign_message::{signed_msg_hash, MessageSignature},
+        taproot::{self, TapNodeHash},
+        transaction::Version,
+        Address as BdkAddress, AddressType as BdkAddressType, Amount, OutPoint, PrivateKey, PublicKey, ScriptBuf,
+        Sequence, Transaction, TxIn, TxOut, Witness,
+    },
+    miniscript::{
+        descriptor::{DescriptorSecretKey, Wildcard},
+        Descriptor,
+    },
+    KeychainKind, Wallet as BdkWallet,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{Address, BdkError, BdkErrorCode};
+
+/// Tag of the BIP 322 message hash.
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+/// Encoding of a message signature.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageSignatureFormat {
+    /// Legacy "Bitcoin Signed Message" signature, only for P2PKH addresses.
+    Legacy = "legacy",
+    /// BIP 322 simple signature: the witness of the virtual `to_sign` transaction.
+    Simple = "simple",
+    /// BIP 322 full signature: the whole virtual `to_sign` transaction.
+    Full = "full",
+}
+
+/// Signs `message` with the key of a wallet `address`.
+///
+/// Defaults to the legacy format for P2PKH addresses and to the BIP 322 simple format for P2WPKH
+/// and P2TR addresses.
+pub(crate) fn sign_message(
+    wallet: &BdkWallet,
+    address: &BdkAddress,
+    message: &str,
+    format: Option<MessageSignatureFormat>,
+) -> Result<String, BdkError> {
+    let address_type = address.address_type();
+    let format = match (address_type, format) {
+        (Some(BdkAddressType::P2pkh), None | Some(MessageSignatureFormat::Legacy)) => MessageSignatureFormat::Legacy,
+        (Some(BdkAddressType::P2wpkh | BdkAddressType::P2tr), None) => MessageSignatureFormat::Simple,
+        (Some(BdkAddressType::P2wpkh | BdkAddressType::P2tr), Some(format))
+            if format != MessageSignatureFormat::Legacy =>
+        {
+            format
+        }
+        _ => return Err(unsupported_address(address, format)),
+    };
+
+    let script_pubkey = address.script_pubkey();
+    let (keychain, index) = wallet.derivation_of_spk(script_pubkey.clone()).ok_or_else(|| {
+        BdkError::new(
+            BdkErrorCode::UnknownAddress,
+            format!("Address {address} is not derived from the wallet's descriptors"),
+            (),
+        )
+    })?;
+
+    let secp = wallet.secp_ctx();
+    let descriptor = wallet
+        .public_descriptor(keychain)
+        .derived_descriptor(secp, index)
+        .map_err(|e| BdkError::new(BdkErrorCode::Descriptor, e.to_string(), ()))?;
+    let (public_key, merkle_root) = match &descriptor {
+        Descriptor::Pkh(pkh) => (*pkh.as_inner(), None),
+        Descriptor::Wpkh(wpkh) => (*wpkh.as_inner(), None),
+        Descriptor::Tr(tr) => (*tr.internal_key(), tr.spend_info().merkle_root()),
+        _ => return Err(unsupported_address(address, Some(format))),
+    };
+    let x_only = matches!(descriptor, Descriptor::Tr(_));
+    let private_key = private_key_for(wallet, keychain, index, &public_key, x_only).ok_or_else(|| {
+        BdkError::new(
+            BdkErrorCode::MissingPrivateKey,
+            format!("The wallet has no private key for address {address}"),
+            (),
+        )
+    })?;
+
+    if format == MessageSignatureFormat::Legacy {
+        let msg_hash = signed_msg_hash(message);
+        let signature =
+            secp.sign_ecdsa_recoverable(&Message::from_digest(msg_hash.to_byte_array()), &private_key.inner);
+        return Ok(MessageSignature::new(signature, private_key.compressed).to_base64());
+    }
+
+    let to_spend = to_spend(&script_pubkey, message);
+    let mut to_sign = to_sign(&to_spend);
+    to_sign.input[0].witness = match address_type {
+        Some(BdkAddressType::P2wpkh) => sign_p2wpkh(secp, &to_sign, &script_pubkey, &private_key)?,
+        _ => sign_p2tr(secp, &to_sign, &to_spend.output[0], &private_key, merkle_root)?,
+    };
+
+    let bytes = match format {
+        MessageSignatureFormat::Full => serialize(&to_sign),
+        _ => serialize(&to_sign.input[0].witness),
+    };
+    Ok(BASE64_STANDARD.encode(bytes))
+}
+
+/// Verifies a message signature for an address.
+///
+/// Legacy "Bitcoin Signed Message" signatures are checked for P2PKH addresses, BIP 322 simple and
+/// full signatures for P2WPKH and P2TR addresses. Returns `false` for invalid or malformed
+/// signatures, and an error for other address types.
+#[wasm_bindgen]
+pub fn verify_message(address: &Address, message: &str, signature: &str) -> Result<bool, BdkError> {
+    let secp = Secp256k1::verification_only();
+
+    match address.address_type() {
+        Some(BdkAddressType::P2pkh) => {
+            let Ok(signature) = MessageSignature::from_base64(signature) else {
+                return Ok(false);
+            };
+            Ok(signature
+                .is_signed_by_address(&secp, address, signed_msg_hash(message))
+                .unwrap_or(false))
+        }
+        Some(address_type @ (BdkAddressType::P2wpkh | BdkAddressType::P2tr)) => {
+            let Ok(bytes) = BASE64_STANDARD.decode(signature) else {
+                return Ok(false);
+            };
+            let script_pubkey = address.script_pubkey();
+            let to_spend = to_spend(&script_pubkey, message);
+            let Some(to_sign) = decode_to_sign(&bytes, &to_spend) else {
+                return Ok(false);
+            };
+
+            let witness = &to_sign.input[0].witness;
+            Ok(match address_type {
+                BdkAddressType::P2wpkh => verify_p2wpkh(&secp, &to_sign, &script_pubkey, witness),
+                _ => verify_p2tr(&secp, &to_sign, &to_spend.output[0], witness),
+            })
+        }
+        _ => Err(unsupported_address(address, None)),
+    }
+}
+
+/// The BIP 322 tagged hash of a message.
+fn message_hash(message: &str) -> sha256::Hash {
+    let tag = sha256::Hash::hash(BIP322_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+/// The BIP 322 virtual transaction committing to the message and paying to the address.
+fn to_spend(script_pubkey: &ScriptBuf, message: &str) -> Transaction {
+    let script_sig = Builder::new()
+        .push_opcode(OP_PUSHBYTES_0)
+        .push_slice(message_hash(message).to_byte_array())
+        .into_script();
+
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+/// The BIP 322 virtual transaction spending `to_spend`, without its witness.
+fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.compute_txid(), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// Decodes a simple (witness) or full (transaction) BIP 322 signature into the `to_sign` transaction.
+fn decode_to_sign(bytes: &[u8], to_spend: &Transaction) -> Option<Transaction> {
+    if let Ok(witness) = deserialize::<Witness>(bytes) {
+        let mut to_sign = to_sign(to_spend);
+        to_sign.input[0].witness = witness;
+        return Some(to_sign);
+    }
+
+    let to_sign = deserialize::<Transaction>(bytes).ok()?;
+    let spends_to_spend = to_sign.input.len() == 1
+        && to_sign.input[0].previous_output == OutPoint::new(to_spend.compute_txid(), 0)
+        && to_sign.input[0].script_sig.is_empty();
+    let is_empty_op_return = to_sign.output.len() == 1
+        && to_sign.output[0].value == Amount::ZERO
+        && to_sign.output[0].script_pubkey.is_op_return();
+    (spends_to_spend && is_empty_op_return).then_some(to_sign)
+}
+
+fn sign_p2wpkh(
+    secp: &Secp256k1<All>,
+    to_sign: &Transaction,
+    script_pubkey: &ScriptBuf,
+    private_key: &PrivateKey,
+) -> Result<Witness, BdkError> {
+    let sighash = SighashCache::new(to_sign)
+        .p2wpkh_signature_hash(0, script_pubkey, Amount::ZERO, EcdsaSighashType::All)
+        .map_err(|e| BdkError::new(BdkErrorCode::Unexpected, e.to_string(), ()))?;
+    let signature = ecdsa::Signature {
+        signature: secp.sign_ecdsa(&sighash.into(), &private_key.inner),
+        sighash_type: EcdsaSighashType::All,
+    };
+
+    Ok(Witness::p2wpkh(&signature, &private_key.public_key(secp).inner))
+}
+
+fn sign_p2tr(
+    secp: &Secp256k1<All>,
+    to_sign: &Transaction,
+    prevout: &TxOut,
+    private_key: &PrivateKey,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<Witness, BdkError> {
+    let sighash = SighashCache::new(to_sign)
+        .taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), TapSighashType::Default)
+        .map_err(|e| BdkError::new(BdkErrorCode::Unexpected, e.to_string(), ()))?;
+    let keypair = Keypair::from_secret_key(secp, &private_key.inner).tap_tweak(secp, merkle_root);
+    let signature = taproot::Signature {
+        signature: secp.sign_schnorr_no_aux_rand(&sighash.into(), &keypair.to_keypair()),
+        sighash_type: TapSighashType::Default,
+    };
+
+    Ok(Witness::p2tr_key_spend(&signature))
+}
+
+fn verify_p2wpkh<C: Verification>(
+    secp: &Secp256k1<C>,
+    to_sign: &Transaction,
+    script_pubkey: &ScriptBuf,
+    witness: &Witness,
+) -> bool {
+    let (Some(signature), Some(public_key), 2) = (witness.nth(0), witness.nth(1), witness.len()) else {
+        return false;
+    };
+    let (Ok(signature), Ok(public_key)) = (
+        ecdsa::Signature::from_slice(signature),
+        PublicKey::from_slice(public_key),
+    ) else {
+        return false;
+    };
+    let Ok(wpubkey_hash) = public_key.wpubkey_hash() else {
+        return false;
+    };
+    if signature.sighash_type != EcdsaSighashType::All || ScriptBuf::new_p2wpkh(&wpubkey_hash) != *script_pubkey {
+        return false;
+    }
+
+    SighashCache::new(to_sign)
+        .p2wpkh_signature_hash(0, script_pubkey, Amount::ZERO, signature.sighash_type)
+        .is_ok_and(|sighash| {
+            secp.verify_ecdsa(&sighash.into(), &signature.signature, &public_key.inner)
+                .is_ok()
+        })
+}
+
+fn verify_p2tr<C: Verification>(
+    secp: &Secp256k1<C>,
+    to_sign: &Transaction,
+    prevout: &TxOut,
+    witness: &Witness,
+) -> bool {
+    let (Some(signature), 1) = (witness.nth(0), witness.len()) else {
+        return false;
+    };
+    let Ok(signature) = taproot::Signature::from_slice(signature) else {
+        return false;
+    };
+    let Ok(output_key) = XOnlyPublicKey::from_slice(&prevout.script_pubkey.as_bytes()[2..]) else {
+        return false;
+    };
+    if !matches!(signature.sighash_type, TapSighashType::Default | TapSighashType::All) {
+        return false;
+    }
+
+    SighashCache::new(to_sign)
+        .taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), signature.sighash_type)
+        .is_ok_and(|sighash| {
+            secp.verify_schnorr(&signature.signature, &sighash.into(), &output_key)
+                .is_ok()
+        })
+}
+
+/// Finds the private key of the wallet's signers for `public_key`, derived at `index`.
+///
+/// Taproot keys only need to match on their x-only part, the parity being fixed by the tweak.
+#[allow(deprecated)]
+fn private_key_for(
+    wallet: &BdkWallet,
+    keychain: KeychainKind,
+    index: u32,
+    public_key: &PublicKey,
+    x_only: bool,
+) -> Option<PrivateKey> {
+    let secp = wallet.secp_ctx();
+    let matches = |private_key: &PrivateKey| {
+        let candidate = private_key.public_key(secp).inner;
+        candidate == public_key.inner
+            || x_only && candidate.x_only_public_key().0 == public_key.inner.x_only_public_key().0
+    };
+
+    wallet
+        .get_signers(keychain)
+        .signers()
+        .into_iter()
+        .filter_map(|signer| signer.descriptor_secret_key())
+        .flat_map(DescriptorSecretKey::into_single_keys)
+        .filter_map(|secret_key| match secret_key {
+            DescriptorSecretKey::Single(single) => Some(single.key),
+            DescriptorSecretKey::XPrv(xkey) => {
+                let path = match xkey.wildcard {
+                    Wildcard::None => xkey.derivation_path,
+                    Wildcard::Unhardened => xkey.derivation_path.child(ChildNumber::Normal { index }),
+                    Wildcard::Hardened => xkey.derivation_path.child(ChildNumber::Hardened { index }),
+                };
+                xkey.xkey.derive_priv(secp, &path).ok().map(|xpriv| xpriv.to_priv())
+            }
+            DescriptorSecretKey::MultiXPrv(_) => None,
+        })
+        .find(matches)
+}
+
+fn unsupported_address(address: &BdkAddress, format: Option<MessageSignatureFormat>) -> BdkError {
+    let address_type = address
+        .address_type()
+        .map_or_else(|| "unknown".to_string(), |address_type| address_type.to_string());
+    let message = match format {
+        Some(format) => format!("Cannot create a {format:?} message signature for a {address_type} address"),
+        None => format!("Message signatures are not supported for {address_type} addresses"),
+    };
+
+    BdkError::new(BdkErrorCode::UnsupportedAddressType, message, ())
+}
@@ -1,9 +1,23 @@
+mod bip85;
 mod descriptor;
+mod external_signer;
+mod indexed_db_store;
+mod message;
+mod multisig;
+mod persister;
+mod psbt_analysis;
 mod tx_builder;
 mod wallet;
 mod wallet_tx;
 
+pub use bip85::*;
 pub use descriptor::*;
+pub use external_signer::{JsExternalSigner, SignatureRequest};
+pub use indexed_db_store::IndexedDbStore;
+pub use message::{verify_message, MessageSignatureFormat};
+pub use multisig::{MultisigDescriptorBuilder, MultisigScript};
+pub use persister::JsWalletPersister;
+pub use psbt_analysis::*;
 pub use tx_builder::*;
 pub use wallet::*;
 pub use wallet_tx::*;
@@ -12,4 +26,28 @@ pub use wallet_tx::*;
 mod esplora_client;
 
 #[cfg(feature = "esplora")]
-pub use esplora_client::EsploraClient;
+pub use esplora_client::{EsploraClient, EsploraClientBuilder};
+
+#[cfg(feature = "esplora")]
+mod wallet_syncer;
+
+#[cfg(feature = "esplora")]
+pub use wallet_syncer::WalletSyncer;
+
+#[cfg(feature = "electrum")]
+mod electrum_client;
+
+#[cfg(feature = "electrum")]
+pub use electrum_client::ElectrumClient;
+
+#[cfg(feature = "rpc")]
+mod bitcoind_rpc_client;
+
+#[cfg(feature = "rpc")]
+pub use bitcoind_rpc_client::BitcoindRpcClient;
+
+#[cfg(feature = "cbf")]
+mod compact_filters_client;
+
+#[cfg(feature = "cbf")]
+pub use compact_filters_client::CompactFiltersClient;
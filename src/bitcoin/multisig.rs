@@ -0,0 +1,239 @@
+use std::str::FromStr;
+
+use bdk_wallet::{
+    bitcoin::{
+        bip32::{DerivationPath, Fingerprint, Xpub},
+        secp256k1::{All, Secp256k1},
+        Network as BdkNetwork, NetworkKind,
+    },
+    descriptor::{DescriptorError, IntoWalletDescriptor},
+    keys::KeyError,
+    miniscript::ForEachKey,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    types::{BdkError, BdkErrorCode, Network},
+    DescriptorPair,
+};
+
+use super::descriptor::origin_key;
+
+/// Unspendable internal key of taproot multisig descriptors, the NUMS point `H` of BIP341.
+const NUMS_KEY: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// Script of multisig descriptors.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultisigScript {
+    /// Legacy P2SH, `sh(sortedmulti(...))`, of up to 15 cosigners.
+    Sh = "sh",
+    /// P2SH-wrapped P2WSH, `sh(wsh(sortedmulti(...)))`.
+    ShWsh = "sh_wsh",
+    /// P2WSH, `wsh(sortedmulti(...))`.
+    Wsh = "wsh",
+    /// Taproot script path, `tr(H,multi_a(...))` with the unspendable internal key `H` of BIP341.
+    Tr = "tr",
+}
+
+enum Cosigner {
+    Xpub {
+        xpub: String,
+        fingerprint: String,
+        origin_path: String,
+    },
+    Descriptors(DescriptorPair),
+}
+
+/// Builder of the descriptors of a multisig wallet, to pass to `Wallet::create`.
+///
+/// Each keychain derives the keys of the cosigners at the same index: the external
+/// descriptor at `/0/*` and the internal one at `/1/*`.
+#[wasm_bindgen]
+pub struct MultisigDescriptorBuilder {
+    threshold: usize,
+    script: MultisigScript,
+    network: Network,
+    sorted: bool,
+    cosigners: Vec<Cosigner>,
+}
+
+#[wasm_bindgen]
+impl MultisigDescriptorBuilder {
+    /// Create a builder of `threshold`-of-n descriptors for the wallets of `network`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(threshold: usize, script: MultisigScript, network: Network) -> MultisigDescriptorBuilder {
+        MultisigDescriptorBuilder {
+            threshold,
+            script,
+            network,
+            sorted: true,
+            cosigners: Vec::new(),
+        }
+    }
+
+    /// Add a cosigner by the extended public key of its account, the `fingerprint` of its master key
+    /// and the derivation path of the account from the master key, e.g. `m/48'/0'/0'/2'`.
+    pub fn add_cosigner(mut self, xpub: &str, fingerprint: &str, origin_path: &str) -> MultisigDescriptorBuilder {
+        self.cosigners.push(Cosigner::Xpub {
+            xpub: xpub.to_string(),
+            fingerprint: fingerprint.to_string(),
+            origin_path: origin_path.to_string(),
+        });
+        self
+    }
+
+    /// Add a cosigner by its single key descriptors, e.g. from `xpub_to_descriptor`.
+    ///
+    /// The private keys of descriptors from `xpriv_to_descriptor` are kept, for the wallet to sign with them.
+    pub fn add_cosigner_descriptors(mut self, descriptors: &DescriptorPair) -> MultisigDescriptorBuilder {
+        self.cosigners.push(Cosigner::Descriptors(descriptors.clone()));
+        self
+    }
+
+    /// Sort the keys of the cosigners in each script with `sortedmulti` (the default), or keep the order
+    /// in which they are added with `multi`.
+    ///
+    /// Taproot descriptors always use `multi_a` in the order of the cosigners, as there is no sorted variant.
+    pub fn sorted(mut self, sorted: bool) -> MultisigDescriptorBuilder {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Build the descriptors, checking the threshold and that the keys of all the cosigners
+    /// belong to the network.
+    pub fn build(self) -> Result<DescriptorPair, BdkError> {
+        if self.threshold == 0 || self.threshold > self.cosigners.len() {
+            return Err(BdkError::new(
+                BdkErrorCode::InvalidThreshold,
+                format!(
+                    "Invalid threshold of {} for {} cosigners",
+                    self.threshold,
+                    self.cosigners.len()
+                ),
+                self.threshold,
+            ));
+        }
+
+        let secp = Secp256k1::new();
+        let network: BdkNetwork = self.network.into();
+
+        let mut external_keys = Vec::with_capacity(self.cosigners.len());
+        let mut internal_keys = Vec::with_capacity(self.cosigners.len());
+        for (index, cosigner) in self.cosigners.iter().enumerate() {
+            let (external, internal) = cosigner.keys(index, network, &secp)?;
+            external_keys.push(external);
+            internal_keys.push(internal);
+        }
+
+        Ok(DescriptorPair::new(
+            self.build_descriptor(&external_keys, network, &secp)?,
+            self.build_descriptor(&internal_keys, network, &secp)?,
+        ))
+    }
+}
+
+impl MultisigDescriptorBuilder {
+    fn build_descriptor(
+        &self,
+        keys: &[String],
+        network: BdkNetwork,
+        secp: &Secp256k1<All>,
+    ) -> Result<String, BdkError> {
+        let multi = if self.sorted { "sortedmulti" } else { "multi" };
+        let keys = keys.join(",");
+
+        let descriptor = match self.script {
+            MultisigScript::Sh => format!("sh({multi}({},{keys}))", self.threshold),
+            MultisigScript::ShWsh => format!("sh(wsh({multi}({},{keys})))", self.threshold),
+            MultisigScript::Wsh => format!("wsh({multi}({},{keys}))", self.threshold),
+            MultisigScript::Tr => format!("tr({NUMS_KEY},multi_a({},{keys}))", self.threshold),
+            _ => {
+                return Err(BdkError::new(
+                    BdkErrorCode::Descriptor,
+                    "Unsupported multisig script",
+                    (),
+                ))
+            }
+        };
+
+        let (descriptor, keymap) = descriptor
+            .as_str()
+            .into_wallet_descriptor(secp, network)
+            .map_err(|e| descriptor_error(e, None))?;
+        descriptor
+            .sanity_check()
+            .map_err(|e| BdkError::new(BdkErrorCode::Descriptor, e.to_string(), ()))?;
+
+        Ok(descriptor.to_string_with_secret(&keymap))
+    }
+}
+
+impl Cosigner {
+    /// The keys of the cosigner in the external and internal descriptors.
+    fn keys(&self, index: usize, network: BdkNetwork, secp: &Secp256k1<All>) -> Result<(String, String), BdkError> {
+        match self {
+            Cosigner::Xpub {
+                xpub,
+                fingerprint,
+                origin_path,
+            } => {
+                let invalid_key = |e: String| BdkError::new(BdkErrorCode::Descriptor, e, index);
+                let xpub = Xpub::from_str(xpub).map_err(|e| invalid_key(e.to_string()))?;
+                let fingerprint = Fingerprint::from_hex(fingerprint).map_err(|e| invalid_key(e.to_string()))?;
+                let origin_path = DerivationPath::from_str(origin_path).map_err(|e| invalid_key(e.to_string()))?;
+
+                if xpub.network != NetworkKind::from(network) {
+                    return Err(network_mismatch(index));
+                }
+
+                let key = origin_key(fingerprint, &origin_path, xpub);
+                Ok((format!("{key}/0/*"), format!("{key}/1/*")))
+            }
+            Cosigner::Descriptors(descriptors) => Ok((
+                single_key(&descriptors.external(), index, network, secp)?,
+                single_key(&descriptors.internal(), index, network, secp)?,
+            )),
+        }
+    }
+}
+
+/// The key of a single key descriptor, private if the descriptor has its private key.
+fn single_key(descriptor: &str, index: usize, network: BdkNetwork, secp: &Secp256k1<All>) -> Result<String, BdkError> {
+    let (descriptor, keymap) = descriptor
+        .into_wallet_descriptor(secp, network)
+        .map_err(|e| descriptor_error(e, Some(index)))?;
+
+    let mut keys = Vec::new();
+    descriptor.for_each_key(|key| {
+        keys.push(key.clone());
+        true
+    });
+
+    match keys.as_slice() {
+        [key] => Ok(match keymap.get(key) {
+            Some(secret) => secret.to_string(),
+            None => key.to_string(),
+        }),
+        _ => Err(BdkError::new(
+            BdkErrorCode::Descriptor,
+            format!("The descriptors of cosigner {index} must have a single key"),
+            index,
+        )),
+    }
+}
+
+fn descriptor_error(e: DescriptorError, index: Option<usize>) -> BdkError {
+    match (e, index) {
+        (DescriptorError::Key(KeyError::InvalidNetwork), Some(index)) => network_mismatch(index),
+        (e, index) => BdkError::new(BdkErrorCode::Descriptor, e.to_string(), index),
+    }
+}
+
+fn network_mismatch(index: usize) -> BdkError {
+    BdkError::new(
+        BdkErrorCode::NetworkMismatch,
+        format!("The keys of cosigner {index} belong to another network"),
+        index,
+    )
+}
@@ -0,0 +1,244 @@
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
+
+use bdk_wallet::{
+    chain::Merge, AsyncWalletPersister, ChangeSet as BdkChangeSet, CreateParams, LoadParams, Wallet as BdkWallet,
+};
+use futures::lock::Mutex;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::js_sys::{Error, Promise};
+
+use crate::{
+    types::{decode_changeset, BdkError, BdkErrorCode, ChangeSet},
+    utils::future::SendFuture,
+};
+
+use super::IndexedDbStore;
+
+#[wasm_bindgen(typescript_custom_section)]
+const WALLET_PERSISTER: &str = r#"
+/**
+ * A store implemented by the host, letting it decide where the wallet data goes.
+ *
+ * `initialize` must return all the data persisted so far, merged into a single `ChangeSet`,
+ * or `undefined` if there is none. `persist` receives every new `ChangeSet` to store.
+ */
+export interface WalletPersister {
+  initialize(): Promise<ChangeSet | undefined>;
+  persist(changeset: ChangeSet): Promise<void>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS object implementing the `WalletPersister` interface.
+    #[wasm_bindgen(typescript_type = "WalletPersister")]
+    pub type JsWalletPersister;
+
+    #[wasm_bindgen(method, catch, js_name = initialize)]
+    fn js_initialize(this: &JsWalletPersister) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = persist)]
+    fn js_persist(this: &JsWalletPersister, changeset: ChangeSet) -> Result<JsValue, JsValue>;
+
+    /// A `ChangeSet` of the host, read through `to_bytes` so that it remains usable by the host.
+    #[wasm_bindgen(typescript_type = "ChangeSet")]
+    pub type JsChangeSet;
+
+    #[wasm_bindgen(method, catch, js_name = to_bytes)]
+    fn js_to_bytes(this: &JsChangeSet) -> Result<Vec<u8>, JsValue>;
+}
+
+impl JsWalletPersister {
+    async fn load(&self) -> Result<BdkChangeSet, JsValue> {
+        let value = JsFuture::from(Promise::resolve(&self.js_initialize()?)).await?;
+        if value.is_undefined() || value.is_null() {
+            return Ok(BdkChangeSet::default());
+        }
+
+        let bytes = value
+            .unchecked_ref::<JsChangeSet>()
+            .js_to_bytes()
+            .map_err(|_| Error::new("`initialize` must resolve to a `ChangeSet` or `undefined`"))?;
+        decode_changeset(&bytes).map_err(|e| Error::new(&e.message()).into())
+    }
+
+    async fn append(&self, changeset: &BdkChangeSet) -> Result<(), JsValue> {
+        let result = self.js_persist(changeset.clone().into())?;
+        JsFuture::from(Promise::resolve(&result)).await?;
+        Ok(())
+    }
+}
+
+impl AsyncWalletPersister for JsWalletPersister {
+    type Error = JsValue;
+
+    fn initialize<'a>(
+        persister: &'a mut Self,
+    ) -> Pin<Box<dyn Future<Output = Result<BdkChangeSet, Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendFuture(persister.load()))
+    }
+
+    fn persist<'a>(
+        persister: &'a mut Self,
+        changeset: &'a BdkChangeSet,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendFuture(persister.append(changeset)))
+    }
+}
+
+/// The storage backends a [`Wallet`] can be persisted into.
+pub(crate) enum WalletPersister {
+    IndexedDb(IndexedDbStore),
+    Js(JsWalletPersister),
+}
+
+impl From<JsWalletPersister> for WalletPersister {
+    fn from(persister: JsWalletPersister) -> Self {
+        WalletPersister::Js(persister)
+    }
+}
+
+impl From<IndexedDbStore> for WalletPersister {
+    fn from(store: IndexedDbStore) -> Self {
+        WalletPersister::IndexedDb(store)
+    }
+}
+
+impl AsyncWalletPersister for WalletPersister {
+    type Error = JsValue;
+
+    fn initialize<'a>(
+        persister: &'a mut Self,
+    ) -> Pin<Box<dyn Future<Output = Result<BdkChangeSet, Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        match persister {
+            WalletPersister::IndexedDb(store) => IndexedDbStore::initialize(store),
+            WalletPersister::Js(persister) => JsWalletPersister::initialize(persister),
+        }
+    }
+
+    fn persist<'a>(
+        persister: &'a mut Self,
+        changeset: &'a BdkChangeSet,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        match persister {
+            WalletPersister::IndexedDb(store) => IndexedDbStore::persist(store, changeset),
+            WalletPersister::Js(persister) => JsWalletPersister::persist(persister, changeset),
+        }
+    }
+}
+
+struct StoreState {
+    persister: WalletPersister,
+    /// Changes taken from the wallet that are not persisted yet.
+    pending: BdkChangeSet,
+}
+
+/// Owns the persister of a [`Wallet`] and serializes the flushes of its staged changes.
+///
+/// Flushes are queued behind an async lock so changesets always reach the persister in the order
+/// they were staged. A changeset that fails to persist is kept and retried with the next flush.
+pub(crate) struct WalletStore(Mutex<StoreState>);
+
+impl WalletStore {
+    /// Create a new wallet and persist its initial changeset.
+    pub(crate) async fn create(
+        mut persister: WalletPersister,
+        params: CreateParams,
+    ) -> Result<(BdkWallet, WalletStore), BdkError> {
+        let existing = WalletPersister::initialize(&mut persister)
+            .await
+            .map_err(persist_error)?;
+        if !existing.is_empty() {
+            return Err(BdkError::new(
+                BdkErrorCode::DataAlreadyExists,
+                "The store already contains wallet data",
+                (),
+            ));
+        }
+
+        let mut wallet = BdkWallet::create_with_params(params)
+            .map_err(|e| BdkError::new(BdkErrorCode::Descriptor, e.to_string(), ()))?;
+        if let Some(changeset) = wallet.take_staged() {
+            WalletPersister::persist(&mut persister, &changeset)
+                .await
+                .map_err(persist_error)?;
+        }
+
+        Ok((wallet, WalletStore::new(persister)))
+    }
+
+    /// Load a previously persisted wallet, returning `None` if the store is empty.
+    pub(crate) async fn load(
+        mut persister: WalletPersister,
+        params: LoadParams,
+    ) -> Result<Option<(BdkWallet, WalletStore)>, BdkError> {
+        let changeset = WalletPersister::initialize(&mut persister)
+            .await
+            .map_err(persist_error)?;
+        let wallet = BdkWallet::load_with_params(changeset, params)
+            .map_err(|e| BdkError::new(BdkErrorCode::Load, e.to_string(), ()))?;
+
+        Ok(wallet.map(|wallet| (wallet, WalletStore::new(persister))))
+    }
+
+    fn new(persister: WalletPersister) -> Self {
+        WalletStore(Mutex::new(StoreState {
+            persister,
+            pending: BdkChangeSet::default(),
+        }))
+    }
+
+    /// Persist the staged changes of `wallet`.
+    ///
+    /// Returns whether any new changes were persisted.
+    pub(crate) async fn flush(&self, wallet: &RefCell<BdkWallet>) -> Result<bool, BdkError> {
+        let mut state = self.0.lock().await;
+        // The changes stay in the store until they are persisted, so they survive a failure as well
+        // as the cancellation of this future.
+        let StoreState { persister, pending } = &mut *state;
+        if let Some(staged) = wallet.borrow_mut().take_staged() {
+            pending.merge(staged);
+        }
+        if pending.is_empty() {
+            return Ok(false);
+        }
+
+        WalletPersister::persist(persister, pending)
+            .await
+            .map_err(persist_error)?;
+        *pending = BdkChangeSet::default();
+        Ok(true)
+    }
+
+    /// Flush the staged changes of `wallet` in the background.
+    ///
+    /// Errors are not reported here: the changes are kept and surface on the next `flush`.
+    pub(crate) fn schedule_flush(self: &Rc<Self>, wallet: &Rc<RefCell<BdkWallet>>) {
+        let store = self.clone();
+        let wallet = wallet.clone();
+        spawn_local(async move {
+            let _ = store.flush(&wallet).await;
+        });
+    }
+}
+
+pub(crate) fn persist_error(e: JsValue) -> BdkError {
+    let message = match e.dyn_ref::<Error>() {
+        Some(error) => String::from(error.message()),
+        None => e.as_string().unwrap_or_else(|| format!("{e:?}")),
+    };
+    BdkError::new(BdkErrorCode::Persist, message, ())
+}
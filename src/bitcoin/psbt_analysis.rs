@@ -0,0 +1,491 @@
+use std::collections::HashSet;
+
+use bdk_wallet::{
+    bitcoin::{
+        absolute::LockTime, bip32::Fingerprint, psbt::Input as BdkPsbtInput, Amount as BdkAmount, EcdsaSighashType,
+        FeeRate as BdkFeeRate, OutPoint as BdkOutPoint, Psbt as BdkPsbt, TapSighashType, TxOut as BdkTxOut,
+    },
+    miniscript::ForEachKey,
+    KeychainKind as BdkKeychainKind, Wallet as BdkWallet,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{Amount, FeeRate, KeychainKind, OutPoint, ScriptBuf};
+
+/// Where an output of an analyzed PSBT goes.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PsbtOutputKind {
+    /// A script that is not the wallet's, i.e. a payment.
+    Send = "send",
+    /// A script of the wallet's internal keychain.
+    Change = "change",
+    /// A script of the wallet's external keychain.
+    Receive = "receive",
+}
+
+/// The reason of a warning about an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PsbtWarningCode {
+    /// The fee rate is above the maximum fee rate.
+    AbsurdFeeRate = "absurd_fee_rate",
+    /// The fee is higher than the amount sent to other wallets.
+    FeeExceedsAmount = "fee_exceeds_amount",
+    /// An output pays a script that is not the wallet's.
+    ExternalOutput = "external_output",
+    /// An output claims to be derived from a key of the wallet, but its script is not the wallet's.
+    ForeignChange = "foreign_change",
+    /// An input asks for a sighash type other than `SIGHASH_ALL` or `SIGHASH_DEFAULT`.
+    NonDefaultSighash = "non_default_sighash",
+    /// An input has neither its `witness_utxo` nor its `non_witness_utxo`, so its amount cannot be verified.
+    MissingUtxo = "missing_utxo",
+    /// The UTXO data of an input does not match the output it spends.
+    InvalidUtxo = "invalid_utxo",
+    /// The locktime is time-based or prevents the transaction from being mined in the next block.
+    UnusualLocktime = "unusual_locktime",
+}
+
+/// A warning about an analyzed PSBT, to show to the user before signing it.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtWarning {
+    code: PsbtWarningCode,
+    message: String,
+    input_index: Option<usize>,
+    output_index: Option<usize>,
+}
+
+impl PsbtWarning {
+    fn new(code: PsbtWarningCode, message: String) -> Self {
+        PsbtWarning {
+            code,
+            message,
+            input_index: None,
+            output_index: None,
+        }
+    }
+
+    fn input(code: PsbtWarningCode, index: usize, message: String) -> Self {
+        PsbtWarning {
+            input_index: Some(index),
+            ..PsbtWarning::new(code, message)
+        }
+    }
+
+    fn output(code: PsbtWarningCode, index: usize, message: String) -> Self {
+        PsbtWarning {
+            output_index: Some(index),
+            ..PsbtWarning::new(code, message)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl PsbtWarning {
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> PsbtWarningCode {
+        self.code
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// The index of the input the warning is about, if any.
+    #[wasm_bindgen(getter)]
+    pub fn input_index(&self) -> Option<usize> {
+        self.input_index
+    }
+
+    /// The index of the output the warning is about, if any.
+    #[wasm_bindgen(getter)]
+    pub fn output_index(&self) -> Option<usize> {
+        self.output_index
+    }
+}
+
+/// An input of an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtInputAnalysis {
+    index: usize,
+    previous_output: BdkOutPoint,
+    value: Option<BdkAmount>,
+    keychain: Option<KeychainKind>,
+    derivation_index: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl PsbtInputAnalysis {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn previous_output(&self) -> OutPoint {
+        self.previous_output.into()
+    }
+
+    /// The amount of the spent output, if known from the PSBT or the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<Amount> {
+        self.value.map(Into::into)
+    }
+
+    /// Whether the input spends an output of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn is_mine(&self) -> bool {
+        self.keychain.is_some()
+    }
+
+    /// The keychain of the spent output, for inputs of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn keychain(&self) -> Option<KeychainKind> {
+        self.keychain
+    }
+
+    /// The derivation index of the spent output, for inputs of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn derivation_index(&self) -> Option<u32> {
+        self.derivation_index
+    }
+}
+
+/// An output of an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtOutputAnalysis {
+    index: usize,
+    kind: PsbtOutputKind,
+    txout: BdkTxOut,
+    derivation_index: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl PsbtOutputAnalysis {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> PsbtOutputKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Amount {
+        self.txout.value.into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        self.txout.script_pubkey.clone().into()
+    }
+
+    /// The derivation index of the script, for outputs to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn derivation_index(&self) -> Option<u32> {
+        self.derivation_index
+    }
+}
+
+/// A review of a PSBT from the wallet's point of view, returned by `Wallet::analyze_psbt`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtAnalysis {
+    inputs: Vec<PsbtInputAnalysis>,
+    outputs: Vec<PsbtOutputAnalysis>,
+    sent: BdkAmount,
+    received: BdkAmount,
+    fee: Option<BdkAmount>,
+    fee_rate: Option<BdkFeeRate>,
+    warnings: Vec<PsbtWarning>,
+}
+
+#[wasm_bindgen]
+impl PsbtAnalysis {
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<PsbtInputAnalysis> {
+        self.inputs.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn outputs(&self) -> Vec<PsbtOutputAnalysis> {
+        self.outputs.clone()
+    }
+
+    /// The sum of the inputs of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn sent(&self) -> Amount {
+        self.sent.into()
+    }
+
+    /// The sum of the outputs to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> Amount {
+        self.received.into()
+    }
+
+    /// The fee, if the amounts of all the inputs are known.
+    #[wasm_bindgen(getter)]
+    pub fn fee(&self) -> Option<Amount> {
+        self.fee.map(Into::into)
+    }
+
+    /// The fee rate, if the fee is known.
+    ///
+    /// Unfinalized inputs of the wallet are counted with their largest signatures. The ones of other
+    /// wallets are counted without, making the fee rate higher than the one of the final transaction.
+    #[wasm_bindgen(getter)]
+    pub fn fee_rate(&self) -> Option<FeeRate> {
+        self.fee_rate.map(Into::into)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Vec<PsbtWarning> {
+        self.warnings.clone()
+    }
+
+    /// Whether a warning has the given code.
+    pub fn has_warning(&self, code: PsbtWarningCode) -> bool {
+        self.warnings.iter().any(|warning| warning.code == code)
+    }
+}
+
+/// Analyze `psbt` from the point of view of `wallet`, warning about fee rates above `max_fee_rate`.
+pub(crate) fn analyze_psbt(wallet: &BdkWallet, psbt: &BdkPsbt, max_fee_rate: BdkFeeRate) -> PsbtAnalysis {
+    let tx = &psbt.unsigned_tx;
+    let mut warnings = Vec::new();
+
+    let mut inputs = Vec::with_capacity(tx.input.len());
+    for (index, (txin, input)) in tx.input.iter().zip(&psbt.inputs).enumerate() {
+        let previous_output = txin.previous_output;
+        let txout = input_utxo(input, previous_output, index, &mut warnings)
+            .or_else(|| wallet.tx_graph().get_txout(previous_output).cloned());
+        let value = txout.as_ref().map(|txout| txout.value);
+        let derivation = txout.and_then(|txout| wallet.derivation_of_spk(txout.script_pubkey));
+
+        if let Some(sighash_type) = input.sighash_type {
+            let default_types = [
+                EcdsaSighashType::All.into(),
+                TapSighashType::All.into(),
+                TapSighashType::Default.into(),
+            ];
+            if !default_types.contains(&sighash_type) {
+                warnings.push(PsbtWarning::input(
+                    PsbtWarningCode::NonDefaultSighash,
+                    index,
+                    format!("Input {index} asks for the non-default sighash type {sighash_type}"),
+                ));
+            }
+        }
+
+        inputs.push(PsbtInputAnalysis {
+            index,
+            previous_output,
+            value,
+            keychain: derivation.map(|(keychain, _)| keychain.into()),
+            derivation_index: derivation.map(|(_, index)| index),
+        });
+    }
+
+    let fingerprints = wallet_fingerprints(wallet);
+    let mut outputs = Vec::with_capacity(tx.output.len());
+    for (index, (txout, output)) in tx.output.iter().zip(&psbt.outputs).enumerate() {
+        let derivation = wallet.derivation_of_spk(txout.script_pubkey.clone());
+        let kind = match derivation {
+            Some((BdkKeychainKind::Internal, _)) => PsbtOutputKind::Change,
+            Some((BdkKeychainKind::External, _)) => PsbtOutputKind::Receive,
+            None => PsbtOutputKind::Send,
+        };
+
+        if kind == PsbtOutputKind::Send {
+            let claims_wallet_key = output
+                .bip32_derivation
+                .values()
+                .map(|(fingerprint, _)| fingerprint)
+                .chain(output.tap_key_origins.values().map(|(_, (fingerprint, _))| fingerprint))
+                .any(|fingerprint| fingerprints.contains(fingerprint));
+            if claims_wallet_key {
+                warnings.push(PsbtWarning::output(
+                    PsbtWarningCode::ForeignChange,
+                    index,
+                    format!("Output {index} is derived from a key of the wallet but does not pay the wallet"),
+                ));
+            } else {
+                warnings.push(PsbtWarning::output(
+                    PsbtWarningCode::ExternalOutput,
+                    index,
+                    format!("Output {index} sends {} to another wallet", txout.value),
+                ));
+            }
+        }
+
+        outputs.push(PsbtOutputAnalysis {
+            index,
+            kind,
+            txout: txout.clone(),
+            derivation_index: derivation.map(|(_, index)| index),
+        });
+    }
+
+    let sent = inputs
+        .iter()
+        .filter(|input| input.keychain.is_some())
+        .filter_map(|input| input.value)
+        .sum();
+    let received = outputs
+        .iter()
+        .filter(|output| output.kind != PsbtOutputKind::Send)
+        .map(|output| output.txout.value)
+        .sum();
+
+    let input_sum = inputs.iter().map(|input| input.value).sum::<Option<BdkAmount>>();
+    let output_sum: BdkAmount = tx.output.iter().map(|txout| txout.value).sum();
+    let fee = input_sum.and_then(|input_sum| input_sum.checked_sub(output_sum));
+    // The signatures of unfinalized inputs are missing, so we add the largest ones of the wallet's inputs.
+    let mut weight = psbt.clone().extract_tx_unchecked_fee_rate().weight();
+    for (input, analysis) in psbt.inputs.iter().zip(&inputs) {
+        let finalized = input.final_script_sig.is_some() || input.final_script_witness.is_some();
+        if let (false, Some(keychain)) = (finalized, analysis.keychain) {
+            if let Ok(satisfaction_weight) = wallet.public_descriptor(keychain.into()).max_weight_to_satisfy() {
+                weight += satisfaction_weight;
+            }
+        }
+    }
+    let fee_rate = fee.filter(|_| weight.to_wu() > 0).map(|fee| fee / weight);
+
+    if let Some(fee_rate) = fee_rate {
+        if fee_rate > max_fee_rate {
+            warnings.push(PsbtWarning::new(
+                PsbtWarningCode::AbsurdFeeRate,
+                format!(
+                    "The fee rate of {} sat/vB is above the maximum of {} sat/vB",
+                    fee_rate.to_sat_per_vb_floor(),
+                    max_fee_rate.to_sat_per_vb_floor()
+                ),
+            ));
+        }
+    }
+    if let Some(fee) = fee {
+        let amount_sent: BdkAmount = outputs
+            .iter()
+            .filter(|output| output.kind == PsbtOutputKind::Send)
+            .map(|output| output.txout.value)
+            .sum();
+        if amount_sent > BdkAmount::ZERO && fee > amount_sent {
+            warnings.push(PsbtWarning::new(
+                PsbtWarningCode::FeeExceedsAmount,
+                format!("The fee of {fee} is higher than the {amount_sent} sent"),
+            ));
+        }
+    }
+
+    if let Some(warning) = locktime_warning(wallet, psbt) {
+        warnings.push(warning);
+    }
+
+    PsbtAnalysis {
+        inputs,
+        outputs,
+        sent,
+        received,
+        fee,
+        fee_rate,
+        warnings,
+    }
+}
+
+/// The output spent by `input` according to the PSBT, checking its UTXO data.
+fn input_utxo(
+    input: &BdkPsbtInput,
+    previous_output: BdkOutPoint,
+    index: usize,
+    warnings: &mut Vec<PsbtWarning>,
+) -> Option<BdkTxOut> {
+    let non_witness_txout = match &input.non_witness_utxo {
+        Some(tx) if tx.compute_txid() != previous_output.txid => {
+            warnings.push(PsbtWarning::input(
+                PsbtWarningCode::InvalidUtxo,
+                index,
+                format!("The non-witness UTXO of input {index} is not the transaction it spends"),
+            ));
+            return None;
+        }
+        Some(tx) => match tx.output.get(previous_output.vout as usize) {
+            Some(txout) => Some(txout.clone()),
+            None => {
+                warnings.push(PsbtWarning::input(
+                    PsbtWarningCode::InvalidUtxo,
+                    index,
+                    format!(
+                        "The non-witness UTXO of input {index} has no output {}",
+                        previous_output.vout
+                    ),
+                ));
+                return None;
+            }
+        },
+        None => None,
+    };
+
+    match (non_witness_txout, &input.witness_utxo) {
+        (Some(txout), Some(witness_utxo)) if &txout != witness_utxo => {
+            warnings.push(PsbtWarning::input(
+                PsbtWarningCode::InvalidUtxo,
+                index,
+                format!("The witness UTXO of input {index} does not match its non-witness UTXO"),
+            ));
+            None
+        }
+        (Some(txout), _) => Some(txout),
+        (None, Some(witness_utxo)) => Some(witness_utxo.clone()),
+        (None, None) => {
+            warnings.push(PsbtWarning::input(
+                PsbtWarningCode::MissingUtxo,
+                index,
+                format!("Input {index} has no UTXO data"),
+            ));
+            None
+        }
+    }
+}
+
+/// The fingerprints of the master keys of the wallet's descriptors.
+fn wallet_fingerprints(wallet: &BdkWallet) -> HashSet<Fingerprint> {
+    let mut fingerprints = HashSet::new();
+    for (keychain, _) in wallet.keychains() {
+        wallet.public_descriptor(keychain).for_each_key(|key| {
+            fingerprints.insert(key.master_fingerprint());
+            true
+        });
+    }
+    fingerprints
+}
+
+fn locktime_warning(wallet: &BdkWallet, psbt: &BdkPsbt) -> Option<PsbtWarning> {
+    let tx = &psbt.unsigned_tx;
+    if !tx.is_lock_time_enabled() {
+        return None;
+    }
+
+    match tx.lock_time {
+        LockTime::Seconds(time) => Some(PsbtWarning::new(
+            PsbtWarningCode::UnusualLocktime,
+            format!("The transaction cannot be mined before the time {time}"),
+        )),
+        LockTime::Blocks(height) => {
+            let tip = wallet.latest_checkpoint().height();
+            (tip > 0 && height.to_consensus_u32() > tip + 1).then(|| {
+                PsbtWarning::new(
+                    PsbtWarningCode::UnusualLocktime,
+                    format!("The transaction cannot be mined before block {height}, the tip is at block {tip}"),
+                )
+            })
+        }
+    }
+}
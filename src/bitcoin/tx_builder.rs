@@ -10,6 +10,8 @@ use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::types::{Amount, BdkError, BdkErrorCode, FeeRate, OutPoint, Psbt, Recipient, ScriptBuf};
 
+use super::persister::WalletStore;
+
 /// Fee policy: either a rate (sat/vB) or an absolute amount.
 enum FeePolicy {
     Rate(FeeRate),
@@ -26,6 +28,7 @@ enum FeePolicy {
 #[wasm_bindgen]
 pub struct TxBuilder {
     wallet: Rc<RefCell<BdkWallet>>,
+    store: Option<Rc<WalletStore>>,
     recipients: Vec<Recipient>,
     utxos: Vec<OutPoint>,
     unspendable: Vec<OutPoint>,
@@ -45,6 +48,7 @@ pub struct TxBuilder {
     add_global_xpubs: bool,
     exact_sequence: Option<u32>,
     data: Option<Vec<u8>>,
+    psbt_version: u32,
     is_fee_bump: bool,
     fee_bump_txid: Option<bdk_wallet::bitcoin::Txid>,
 }
@@ -52,9 +56,10 @@ pub struct TxBuilder {
 #[wasm_bindgen]
 impl TxBuilder {
     // We make this constructor only visible to the crate to hide the use of the `Rc<RefCell<BdkWallet>>` in `Wallet::build_tx`.
-    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>) -> TxBuilder {
+    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>, store: Option<Rc<WalletStore>>) -> TxBuilder {
         TxBuilder {
             wallet,
+            store,
             recipients: vec![],
             utxos: vec![],
             unspendable: vec![],
@@ -74,13 +79,18 @@ impl TxBuilder {
             add_global_xpubs: false,
             exact_sequence: None,
             data: None,
+            psbt_version: 0,
             is_fee_bump: false,
             fee_bump_txid: None,
         }
     }
 
-    pub(crate) fn new_fee_bump(wallet: Rc<RefCell<BdkWallet>>, txid: bdk_wallet::bitcoin::Txid) -> TxBuilder {
-        let mut builder = TxBuilder::new(wallet);
+    pub(crate) fn new_fee_bump(
+        wallet: Rc<RefCell<BdkWallet>>,
+        store: Option<Rc<WalletStore>>,
+        txid: bdk_wallet::bitcoin::Txid,
+    ) -> TxBuilder {
+        let mut builder = TxBuilder::new(wallet, store);
         builder.is_fee_bump = true;
         builder.fee_bump_txid = Some(txid);
         builder
@@ -343,10 +353,50 @@ impl TxBuilder {
         self
     }
 
+    /// Set the version of the created PSBT: 0 ([BIP 174], the default) or 2 ([BIP 370]).
+    ///
+    /// Version 2 PSBTs are created with modifiable inputs and outputs, so other participants can
+    /// add theirs with [`Psbt::add_input`] and [`Psbt::add_output`].
+    ///
+    /// [BIP 174]: https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki
+    /// [BIP 370]: https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
+    pub fn psbt_version(mut self, version: u32) -> Self {
+        self.psbt_version = version;
+        self
+    }
+
     /// Finish building the transaction.
     ///
-    /// Returns a new [`Psbt`] per [`BIP174`].
+    /// Returns a new [`Psbt`] per [`BIP174`], or per [`BIP370`] if [`psbt_version`] is 2.
     pub fn finish(self) -> Result<Psbt, BdkError> {
+        let wallet = self.wallet.clone();
+        let store = self.store.clone();
+        let psbt_version = self.psbt_version;
+
+        if psbt_version != 0 && psbt_version != 2 {
+            return Err(BdkError::new(
+                BdkErrorCode::UnsupportedPsbtVersion,
+                format!("Unsupported PSBT version {psbt_version}"),
+                psbt_version,
+            ));
+        }
+
+        let mut psbt = self.build_psbt()?;
+        if psbt_version == 2 {
+            psbt = psbt.to_v2();
+            psbt.set_tx_modifiable(true, true)?;
+        }
+
+        // Building the transaction may have revealed a new change address.
+        if let Some(store) = store {
+            store.schedule_flush(&wallet);
+        }
+        Ok(psbt)
+    }
+}
+
+impl TxBuilder {
+    fn build_psbt(self) -> Result<Psbt, BdkError> {
         let mut wallet = self.wallet.borrow_mut();
 
         if self.is_fee_bump {
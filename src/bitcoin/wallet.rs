@@ -1,8 +1,15 @@
-use std::{cell::RefCell, rc::Rc};
-
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use bdk_wallet::{
+    bitcoin::{psbt::Input as BdkPsbtInput, Psbt as BdkPsbt, Txid as BdkTxid, Witness},
+    chain::spk_client::SyncRequest as BdkSyncRequest,
+    event::WalletEvent as BdkWalletEvent,
+    miniscript::psbt::PsbtExt,
+    psbt::PsbtUtils,
+    LoadParams, Wallet as BdkWallet,
+};
 #[allow(deprecated)]
-use bdk_wallet::SignOptions as BdkSignOptions;
-use bdk_wallet::Wallet as BdkWallet;
+use bdk_wallet::{signer::SignerError, SignOptions as BdkSignOptions};
 use wasm_bindgen::{prelude::wasm_bindgen, JsError};
 use web_sys::js_sys::Date;
 
@@ -10,13 +17,22 @@ use crate::{
     bitcoin::WalletTx,
     result::JsResult,
     types::{
-        AddressInfo, Amount, Balance, Block, ChangeSet, CheckPoint, EvictedTx, FeeRate, FullScanRequest, KeychainKind,
-        LocalOutput, Network, NetworkKind, OutPoint, Psbt, ScriptBuf, SentAndReceived, SpkIndexed, SyncRequest,
-        Transaction, TxDetails, TxOut, Txid, Update, WalletEvent,
+        Address, AddressInfo, Amount, Balance, Block, ChangeSet, CheckPoint, EvictedTx, FeeRate, FullScanInspector,
+        FullScanRequest, KeychainKind, LocalOutput, Network, NetworkKind, OutPoint, Psbt, PsbtInputStatus, ScriptBuf,
+        SentAndReceived, SpkIndexed, SyncInspector, SyncRequest, Transaction, TxDetails, TxOut, Txid, Update,
+        WalletEvent,
     },
+    utils::psbt_v2::update_tx_modifiable,
 };
 
-use super::{TxBuilder, UnconfirmedTx};
+use super::{
+    external_signer::ExternalSigners,
+    message::sign_message,
+    persister::{WalletPersister, WalletStore},
+    psbt_analysis::analyze_psbt,
+    IndexedDbStore, JsExternalSigner, JsWalletPersister, MessageSignatureFormat, PsbtAnalysis, TxBuilder,
+    UnconfirmedTx,
+};
 
 use crate::types::{BdkError, BdkErrorCode, BlockId};
 use bdk_wallet::chain::local_chain::{ApplyHeaderError, CannotConnectError};
@@ -24,9 +40,15 @@ use bdk_wallet::chain::local_chain::{ApplyHeaderError, CannotConnectError};
 // We wrap a `BdkWallet` in `Rc<RefCell<...>>` because `wasm_bindgen` do not
 // support Rust's lifetimes. This allows us to forward a reference to the
 // internal wallet when using `build_tx` and to enforce the lifetime at runtime
-// and to preserve "safe mutability".
+// and to preserve "safe mutability". Cloning shares the same wallet, which is how
+// a `WalletSyncer` keeps updating the wallet held by JS.
 #[wasm_bindgen]
-pub struct Wallet(Rc<RefCell<BdkWallet>>);
+#[derive(Clone)]
+pub struct Wallet {
+    inner: Rc<RefCell<BdkWallet>>,
+    store: Option<Rc<WalletStore>>,
+    external_signers: ExternalSigners,
+}
 
 #[wasm_bindgen]
 impl Wallet {
@@ -39,7 +61,7 @@ impl Wallet {
             .network(network.into())
             .create_wallet_no_persist()?;
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+        Ok(Wallet::from_wallet(wallet, None))
     }
 
     pub fn create(network: Network, external_descriptor: String, internal_descriptor: String) -> JsResult<Wallet> {
@@ -47,7 +69,7 @@ impl Wallet {
             .network(network.into())
             .create_wallet_no_persist()?;
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+        Ok(Wallet::from_wallet(wallet, None))
     }
 
     /// Create a new [`Wallet`] from a BIP-389 two-path multipath descriptor.
@@ -63,7 +85,7 @@ impl Wallet {
             .network(network.into())
             .create_wallet_no_persist()?;
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+        Ok(Wallet::from_wallet(wallet, None))
     }
 
     pub fn load(
@@ -71,44 +93,135 @@ impl Wallet {
         external_descriptor: Option<String>,
         internal_descriptor: Option<String>,
     ) -> JsResult<Wallet> {
-        let mut builder = BdkWallet::load();
-
-        if external_descriptor.is_some() {
-            builder = builder.descriptor(KeychainKind::External.into(), external_descriptor);
-        }
-
-        if internal_descriptor.is_some() {
-            builder = builder.descriptor(KeychainKind::Internal.into(), internal_descriptor);
-        }
-
-        let wallet_opt = builder.extract_keys().load_wallet_no_persist(changeset.into())?;
+        let wallet_opt =
+            Wallet::load_params(external_descriptor, internal_descriptor).load_wallet_no_persist(changeset.into())?;
 
         let wallet = match wallet_opt {
             Some(wallet) => wallet,
             None => return Err(JsError::new("Failed to load wallet, check the changeset")),
         };
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+        Ok(Wallet::from_wallet(wallet, None))
     }
 
-    pub fn start_full_scan(&self) -> FullScanRequest {
-        self.0
-            .borrow()
-            .start_full_scan_at((Date::now() / 1000.0) as u64)
-            .build()
-            .into()
+    /// Create a new [`Wallet`] persisted into `store`.
+    ///
+    /// Fails if `store` already contains wallet data, use `load_with_store` instead. The changes
+    /// staged by every call mutating the wallet are then flushed to `store` automatically.
+    pub async fn create_with_store(
+        store: IndexedDbStore,
+        network: Network,
+        external_descriptor: String,
+        internal_descriptor: String,
+    ) -> Result<Wallet, BdkError> {
+        Wallet::create_persisted(store.into(), network, external_descriptor, internal_descriptor).await
+    }
+
+    /// Load a [`Wallet`] previously persisted into `store`.
+    ///
+    /// Returns `undefined` if `store` contains no wallet data. As with `create_with_store`,
+    /// staged changes are then flushed to `store` automatically.
+    pub async fn load_with_store(
+        store: IndexedDbStore,
+        external_descriptor: Option<String>,
+        internal_descriptor: Option<String>,
+    ) -> Result<Option<Wallet>, BdkError> {
+        Wallet::load_persisted(store.into(), external_descriptor, internal_descriptor).await
+    }
+
+    /// Create a new [`Wallet`] persisted through a host-provided `WalletPersister`.
+    ///
+    /// `persister.initialize()` is called first and must not return any data. Every changeset
+    /// staged afterwards is handed to `persister.persist()`, in order, automatically.
+    pub async fn create_with_persister(
+        persister: JsWalletPersister,
+        network: Network,
+        external_descriptor: String,
+        internal_descriptor: String,
+    ) -> Result<Wallet, BdkError> {
+        Wallet::create_persisted(persister.into(), network, external_descriptor, internal_descriptor).await
+    }
+
+    /// Load a [`Wallet`] from the data returned by `persister.initialize()`.
+    ///
+    /// Returns `undefined` if the persister holds no wallet data.
+    pub async fn load_with_persister(
+        persister: JsWalletPersister,
+        external_descriptor: Option<String>,
+        internal_descriptor: Option<String>,
+    ) -> Result<Option<Wallet>, BdkError> {
+        Wallet::load_persisted(persister.into(), external_descriptor, internal_descriptor).await
+    }
+
+    /// Persist the staged changes of the wallet into its store or persister.
+    ///
+    /// Changes are already flushed in the background after every mutation; awaiting this
+    /// guarantees they have been written and reports any failure to write them.
+    /// Returns whether any new changes were persisted.
+    pub async fn persist(&self) -> Result<bool, BdkError> {
+        match &self.store {
+            Some(store) => store.flush(&self.inner).await,
+            None => Err(BdkError::new(
+                BdkErrorCode::Persist,
+                "Wallet was not created with a store or persister",
+                (),
+            )),
+        }
+    }
+
+    /// Create a request to scan all the wallet's keychains until the stop gap is reached.
+    ///
+    /// If given, `inspect` is called for every script the client checks, e.g. to report progress.
+    pub fn start_full_scan(&self, inspect: Option<FullScanInspector>) -> FullScanRequest {
+        let mut builder = self.inner.borrow().start_full_scan_at((Date::now() / 1000.0) as u64);
+        if let Some(inspect) = inspect {
+            builder = builder.inspect(inspect.into_fn());
+        }
+        builder.build().into()
     }
 
-    pub fn start_sync_with_revealed_spks(&self) -> SyncRequest {
-        self.0
+    /// Create a request to sync the scripts revealed so far, and the wallet's unconfirmed
+    /// transactions and UTXOs.
+    ///
+    /// If given, `inspect` is called for every item the client checks, with the progress of the
+    /// whole request.
+    pub fn start_sync_with_revealed_spks(&self, inspect: Option<SyncInspector>) -> SyncRequest {
+        let mut builder = self
+            .inner
             .borrow()
-            .start_sync_with_revealed_spks_at((Date::now() / 1000.0) as u64)
-            .build()
-            .into()
+            .start_sync_with_revealed_spks_at((Date::now() / 1000.0) as u64);
+        if let Some(inspect) = inspect {
+            builder = builder.inspect(inspect.into_fn());
+        }
+        builder.build().into()
+    }
+
+    /// Create a request to check only what can change between two syncs: the confirmation
+    /// status of the unconfirmed transactions and the spend status of the UTXOs.
+    ///
+    /// Cheaper than `start_sync_with_revealed_spks` for frequent polling, but it does not find
+    /// new transactions received on the wallet's scripts.
+    pub fn start_sync_unconfirmed_and_utxos(&self, inspect: Option<SyncInspector>) -> SyncRequest {
+        let wallet = self.inner.borrow();
+        let unconfirmed_txids = wallet
+            .transactions()
+            .filter(|tx| !tx.chain_position.is_confirmed())
+            .map(|tx| tx.tx_node.txid);
+        let utxos = wallet.list_unspent().map(|utxo| utxo.outpoint);
+
+        let mut builder = BdkSyncRequest::builder_at((Date::now() / 1000.0) as u64)
+            .chain_tip(wallet.latest_checkpoint())
+            .txids(unconfirmed_txids)
+            .outpoints(utxos);
+        if let Some(inspect) = inspect {
+            builder = builder.inspect(inspect.into_fn());
+        }
+        builder.build().into()
     }
 
     pub fn apply_update(&self, update: Update) -> JsResult<()> {
-        self.0.borrow_mut().apply_update(update)?;
+        self.inner.borrow_mut().apply_update(update)?;
+        self.schedule_persist();
         Ok(())
     }
 
@@ -116,47 +229,53 @@ impl Wallet {
     ///
     /// Returns a list of `WalletEvent`s such as new transactions, confirmations, replacements, etc.
     pub fn apply_update_events(&self, update: Update) -> JsResult<Vec<WalletEvent>> {
-        let events = self.0.borrow_mut().apply_update_events(update)?;
-        Ok(events.into_iter().map(WalletEvent::from).collect())
+        Ok(self.apply_with_events(|wallet| wallet.apply_update_events(update))?)
     }
 
     #[wasm_bindgen(getter)]
     pub fn network(&self) -> Network {
-        self.0.borrow().network().into()
+        self.inner.borrow().network().into()
     }
 
     #[wasm_bindgen(getter)]
     pub fn network_kind(&self) -> NetworkKind {
-        self.0.borrow().network().into()
+        self.inner.borrow().network().into()
     }
 
     #[wasm_bindgen(getter)]
     pub fn balance(&self) -> Balance {
-        self.0.borrow().balance().into()
+        self.inner.borrow().balance().into()
     }
 
     pub fn next_unused_address(&self, keychain: KeychainKind) -> AddressInfo {
-        self.0.borrow_mut().next_unused_address(keychain.into()).into()
+        let address = self.inner.borrow_mut().next_unused_address(keychain.into());
+        self.schedule_persist();
+        address.into()
     }
 
     pub fn peek_address(&self, keychain: KeychainKind, index: u32) -> AddressInfo {
-        self.0.borrow().peek_address(keychain.into(), index).into()
+        self.inner.borrow().peek_address(keychain.into(), index).into()
     }
 
     pub fn reveal_next_address(&self, keychain: KeychainKind) -> AddressInfo {
-        self.0.borrow_mut().reveal_next_address(keychain.into()).into()
+        let address = self.inner.borrow_mut().reveal_next_address(keychain.into());
+        self.schedule_persist();
+        address.into()
     }
 
     pub fn reveal_addresses_to(&self, keychain: KeychainKind, index: u32) -> Vec<AddressInfo> {
-        self.0
+        let addresses = self
+            .inner
             .borrow_mut()
             .reveal_addresses_to(keychain.into(), index)
             .map(Into::into)
-            .collect()
+            .collect();
+        self.schedule_persist();
+        addresses
     }
 
     pub fn list_unused_addresses(&self, keychain: KeychainKind) -> Vec<AddressInfo> {
-        self.0
+        self.inner
             .borrow()
             .list_unused_addresses(keychain.into())
             .map(Into::into)
@@ -164,49 +283,82 @@ impl Wallet {
     }
 
     pub fn list_unspent(&self) -> Vec<LocalOutput> {
-        self.0.borrow().list_unspent().map(Into::into).collect()
+        self.inner.borrow().list_unspent().map(Into::into).collect()
     }
 
     pub fn list_output(&self) -> Vec<LocalOutput> {
-        self.0.borrow().list_output().map(Into::into).collect()
+        self.inner.borrow().list_output().map(Into::into).collect()
     }
 
     pub fn get_utxo(&self, op: OutPoint) -> Option<LocalOutput> {
-        self.0.borrow().get_utxo(op.into()).map(Into::into)
+        self.inner.borrow().get_utxo(op.into()).map(Into::into)
     }
 
     pub fn transactions(&self) -> Vec<WalletTx> {
-        self.0.borrow().transactions().map(Into::into).collect()
+        self.inner.borrow().transactions().map(Into::into).collect()
     }
 
     pub fn get_tx(&self, txid: Txid) -> Option<WalletTx> {
-        self.0.borrow().get_tx(txid.into()).map(Into::into)
+        self.inner.borrow().get_tx(txid.into()).map(Into::into)
     }
 
     #[wasm_bindgen(getter)]
     pub fn latest_checkpoint(&self) -> CheckPoint {
-        self.0.borrow().latest_checkpoint().into()
+        self.inner.borrow().latest_checkpoint().into()
     }
 
-    pub fn take_staged(&self) -> Option<ChangeSet> {
-        self.0.borrow_mut().take_staged().map(Into::into)
+    /// Take the staged changes of the wallet, to persist them yourself.
+    ///
+    /// Fails for wallets with a store or persister, whose staged changes are theirs to persist.
+    pub fn take_staged(&self) -> Result<Option<ChangeSet>, BdkError> {
+        if self.store.is_some() {
+            return Err(BdkError::new(
+                BdkErrorCode::Persist,
+                "The staged changes of a wallet with a store or persister are persisted by it",
+                (),
+            ));
+        }
+        Ok(self.inner.borrow_mut().take_staged().map(Into::into))
     }
 
     pub fn public_descriptor(&self, keychain: KeychainKind) -> String {
-        self.0.borrow().public_descriptor(keychain.into()).to_string()
+        self.inner.borrow().public_descriptor(keychain.into()).to_string()
     }
 
     pub fn sign(&self, psbt: &mut Psbt, options: SignOptions) -> JsResult<bool> {
-        let result = self.0.borrow().sign(psbt, options.into())?;
-        Ok(result)
+        let result = self.inner.borrow().sign(psbt, options.into());
+        update_tx_modifiable(psbt);
+        Ok(result?)
+    }
+
+    /// Register a host-implemented signer, e.g. a hardware wallet bridge, for the keys derived
+    /// from the master key `fingerprint`.
+    ///
+    /// The signer takes part in signing on both keychains, after the wallet's own keys. Its
+    /// callbacks being async, it is only used by `sign_async`: `sign` skips it. Registering a
+    /// signer again for the same fingerprint replaces it.
+    pub fn add_external_signer(&self, fingerprint: &str, signer: JsExternalSigner) -> Result<(), BdkError> {
+        self.external_signers
+            .add(&mut self.inner.borrow_mut(), fingerprint, signer)
+    }
+
+    /// Sign a PSBT like `sign`, also awaiting the external signers registered with
+    /// `add_external_signer`.
+    ///
+    /// The wallet's signers decide which inputs each external signer is asked to sign: those
+    /// with a key of its fingerprint and no signature for it yet.
+    pub async fn sign_async(&self, psbt: &mut Psbt, options: SignOptions) -> Result<bool, BdkError> {
+        let result = self.external_signers.sign(&self.inner, psbt, options.into()).await;
+        update_tx_modifiable(psbt);
+        result
     }
 
     pub fn derivation_index(&self, keychain: KeychainKind) -> Option<u32> {
-        self.0.borrow().derivation_index(keychain.into())
+        self.inner.borrow().derivation_index(keychain.into())
     }
 
     pub fn build_tx(&self) -> TxBuilder {
-        TxBuilder::new(self.0.clone())
+        TxBuilder::new(self.inner.clone(), self.store.clone())
     }
 
     /// Create a new transaction builder for fee-bumping (RBF) an existing transaction.
@@ -215,14 +367,18 @@ impl Wallet {
     /// Returns a `TxBuilder` pre-configured for fee bumping. You can then set the new fee rate
     /// or absolute fee and call `finish()`.
     pub fn build_fee_bump(&self, txid: Txid) -> JsResult<TxBuilder> {
-        Ok(TxBuilder::new_fee_bump(self.0.clone(), txid.into()))
+        Ok(TxBuilder::new_fee_bump(
+            self.inner.clone(),
+            self.store.clone(),
+            txid.into(),
+        ))
     }
 
     /// Mark an address as used at the given keychain and derivation index.
     ///
     /// Returns whether the given index was present in the unused set and was removed.
     pub fn mark_used(&self, keychain: KeychainKind, index: u32) -> bool {
-        self.0.borrow_mut().mark_used(keychain.into(), index)
+        self.inner.borrow_mut().mark_used(keychain.into(), index)
     }
 
     /// Undo a previous `mark_used` call.
@@ -230,7 +386,7 @@ impl Wallet {
     /// Returns whether the index was inserted back into the unused set.
     /// Has no effect if the address was actually used in a transaction.
     pub fn unmark_used(&self, keychain: KeychainKind, index: u32) -> bool {
-        self.0.borrow_mut().unmark_used(keychain.into(), index)
+        self.inner.borrow_mut().unmark_used(keychain.into(), index)
     }
 
     /// Insert a `TxOut` at the given `OutPoint` into the wallet's transaction graph.
@@ -239,30 +395,31 @@ impl Wallet {
     /// `calculate_fee` and `calculate_fee_rate` work on transactions with
     /// inputs not owned by this wallet.
     pub fn insert_txout(&self, outpoint: OutPoint, txout: TxOut) {
-        self.0.borrow_mut().insert_txout(outpoint.into(), txout.into());
+        self.inner.borrow_mut().insert_txout(outpoint.into(), txout.into());
+        self.schedule_persist();
     }
 
     pub fn calculate_fee(&self, tx: &Transaction) -> JsResult<Amount> {
-        let fee = self.0.borrow().calculate_fee(tx)?;
+        let fee = self.inner.borrow().calculate_fee(tx)?;
         Ok(fee.into())
     }
 
     pub fn calculate_fee_rate(&self, tx: &Transaction) -> JsResult<FeeRate> {
-        let fee_rate = self.0.borrow().calculate_fee_rate(tx)?;
+        let fee_rate = self.inner.borrow().calculate_fee_rate(tx)?;
         Ok(fee_rate.into())
     }
 
     pub fn sent_and_received(&self, tx: &Transaction) -> JsResult<SentAndReceived> {
-        let (sent, received) = self.0.borrow().sent_and_received(tx);
+        let (sent, received) = self.inner.borrow().sent_and_received(tx);
         Ok(SentAndReceived(sent.into(), received.into()))
     }
 
     pub fn is_mine(&self, script: ScriptBuf) -> bool {
-        self.0.borrow().is_mine(script.into())
+        self.inner.borrow().is_mine(script.into())
     }
 
     pub fn derivation_of_spk(&self, spk: ScriptBuf) -> Option<SpkIndexed> {
-        self.0
+        self.inner
             .borrow()
             .derivation_of_spk(spk.into())
             .map(|(keychain, index)| SpkIndexed(keychain.into(), index))
@@ -274,23 +431,140 @@ impl Wallet {
     /// be finalized. Use `SignOptions::try_finalize` to control whether finalization is
     /// attempted.
     pub fn finalize_psbt(&self, psbt: &mut Psbt, options: SignOptions) -> JsResult<bool> {
-        let result = self.0.borrow().finalize_psbt(psbt, options.into())?;
+        let result = self.inner.borrow().finalize_psbt(psbt, options.into())?;
         Ok(result)
     }
 
+    /// Sign the inputs of a PSBT owned by this wallet, leaving the other inputs untouched.
+    ///
+    /// Unlike `sign`, the PSBT can have inputs of other wallets (coinjoins, payjoins, multisig
+    /// with other wallets): their missing UTXO data or non-default sighash types don't prevent
+    /// signing ours. Owned inputs missing their UTXO data get it from the wallet. With
+    /// `SignOptions::try_finalize`, every input is then finalized as with `finalize_psbt_inputs`.
+    ///
+    /// Returns the status of each input.
+    #[allow(deprecated)]
+    pub fn sign_owned_inputs(&self, psbt: &mut Psbt, options: SignOptions) -> Result<Vec<PsbtInputStatus>, BdkError> {
+        let wallet = self.inner.borrow();
+        let mut options: BdkSignOptions = options.into();
+        let try_finalize = options.try_finalize;
+        options.try_finalize = false;
+
+        let owned = Wallet::owned_inputs(&wallet, psbt);
+
+        // BDK checks the UTXO data and sighash types of every input, so we sign a copy where the
+        // foreign inputs look finalized, which signers skip.
+        let mut copy = BdkPsbt::clone(psbt);
+        for (index, input) in copy.inputs.iter_mut().enumerate() {
+            if owned[index] {
+                if input.non_witness_utxo.is_none() {
+                    let txid = copy.unsigned_tx.input[index].previous_output.txid;
+                    input.non_witness_utxo = wallet.get_tx(txid).map(|tx| tx.tx_node.tx.as_ref().clone());
+                }
+            } else if input.final_script_sig.is_none() && input.final_script_witness.is_none() {
+                input.sighash_type = None;
+                input.final_script_witness = Some(Witness::new());
+            }
+        }
+        wallet.sign(&mut copy, options.clone())?;
+
+        let mut signed = vec![false; owned.len()];
+        for (index, input) in copy.inputs.into_iter().enumerate() {
+            if owned[index] {
+                signed[index] = signature_count(&input) > signature_count(&psbt.inputs[index]);
+                psbt.inputs[index] = input;
+            }
+        }
+        psbt.outputs = copy.outputs;
+        update_tx_modifiable(psbt);
+
+        let finalized = if try_finalize {
+            Wallet::finalize_inputs(&wallet, psbt, options)?
+        } else {
+            psbt.inputs.iter().map(|input| (is_finalized(input), None)).collect()
+        };
+
+        Ok(owned
+            .into_iter()
+            .zip(signed)
+            .zip(finalized)
+            .enumerate()
+            .map(|(index, ((is_mine, signed), (finalized, error)))| PsbtInputStatus {
+                index,
+                is_mine,
+                signed,
+                finalized,
+                error,
+            })
+            .collect())
+    }
+
+    /// Finalize every input of a PSBT that has enough data, including the inputs of other wallets.
+    ///
+    /// The inputs of this wallet are finalized with its descriptors, as with `finalize_psbt`,
+    /// and the other ones with miniscript from the scripts and signatures of the PSBT.
+    ///
+    /// Returns the status of each input, with the reason why it could not be finalized.
+    pub fn finalize_psbt_inputs(
+        &self,
+        psbt: &mut Psbt,
+        options: SignOptions,
+    ) -> Result<Vec<PsbtInputStatus>, BdkError> {
+        let wallet = self.inner.borrow();
+        let owned = Wallet::owned_inputs(&wallet, psbt);
+        let finalized = Wallet::finalize_inputs(&wallet, psbt, options.into())?;
+
+        Ok(owned
+            .into_iter()
+            .zip(finalized)
+            .enumerate()
+            .map(|(index, (is_mine, (finalized, error)))| PsbtInputStatus {
+                index,
+                is_mine,
+                signed: false,
+                finalized,
+                error,
+            })
+            .collect())
+    }
+
+    /// Review a PSBT before signing it: the inputs and outputs of the wallet, the fee and
+    /// warnings about what could make it dangerous to sign.
+    ///
+    /// Fee rates above `max_fee_rate` are reported as absurd, 25 000 sat/kwu (100 sat/vB) by default.
+    pub fn analyze_psbt(&self, psbt: &Psbt, max_fee_rate: Option<FeeRate>) -> PsbtAnalysis {
+        let max_fee_rate = max_fee_rate.map_or(BdkPsbt::DEFAULT_MAX_FEE_RATE, Into::into);
+        analyze_psbt(&self.inner.borrow(), psbt, max_fee_rate)
+    }
+
+    /// Sign a message with the key of one of the wallet's addresses.
+    ///
+    /// P2PKH addresses produce a legacy "Bitcoin Signed Message" signature, P2WPKH and P2TR
+    /// addresses a BIP 322 signature, in the simple format unless `format` asks for the full one.
+    /// The signature is base64 encoded and can be checked with `verify_message`.
+    pub fn sign_message(
+        &self,
+        address: &Address,
+        message: &str,
+        format: Option<MessageSignatureFormat>,
+    ) -> Result<String, BdkError> {
+        sign_message(&self.inner.borrow(), address, message, format)
+    }
+
     /// Inform the wallet that a transaction built from it will not be broadcast.
     ///
     /// This frees up the change address that was reserved when creating the transaction,
     /// making it available for future transactions.
     pub fn cancel_tx(&self, tx: &Transaction) {
-        self.0.borrow_mut().cancel_tx(tx);
+        self.inner.borrow_mut().cancel_tx(tx);
+        self.schedule_persist();
     }
 
     /// Get the descriptor checksum for the given keychain.
     ///
     /// Returns the checksum portion of the descriptor string (the part after `#`).
     pub fn descriptor_checksum(&self, keychain: KeychainKind) -> String {
-        self.0.borrow().descriptor_checksum(keychain.into())
+        self.inner.borrow().descriptor_checksum(keychain.into())
     }
 
     /// Get the next derivation index for the given keychain.
@@ -298,7 +572,7 @@ impl Wallet {
     /// This is one more than the highest index that has been derived so far.
     /// Unlike `derivation_index`, this always returns a value (0 if nothing has been derived).
     pub fn next_derivation_index(&self, keychain: KeychainKind) -> u32 {
-        self.0.borrow().next_derivation_index(keychain.into())
+        self.inner.borrow().next_derivation_index(keychain.into())
     }
 
     /// Get detailed information about a transaction in the wallet.
@@ -308,13 +582,14 @@ impl Wallet {
     ///
     /// Returns `None` if the transaction is not found in the wallet.
     pub fn tx_details(&self, txid: Txid) -> Option<TxDetails> {
-        self.0.borrow().tx_details(txid.into()).map(Into::into)
+        self.inner.borrow().tx_details(txid.into()).map(Into::into)
     }
 
     pub fn apply_unconfirmed_txs(&self, unconfirmed_txs: Vec<UnconfirmedTx>) {
-        self.0
+        self.inner
             .borrow_mut()
-            .apply_unconfirmed_txs(unconfirmed_txs.into_iter().map(Into::into))
+            .apply_unconfirmed_txs(unconfirmed_txs.into_iter().map(Into::into));
+        self.schedule_persist();
     }
 
     /// Apply a block to the wallet, connecting it via its `prev_blockhash`.
@@ -325,12 +600,8 @@ impl Wallet {
     /// Returns a list of `WalletEvent`s describing what changed (new transactions,
     /// confirmations, etc.).
     pub fn apply_block_events(&self, block: &Block, height: u32) -> Result<Vec<WalletEvent>, BdkError> {
-        let events = self
-            .0
-            .borrow_mut()
-            .apply_block_events(block, height)
-            .map_err(BdkError::from)?;
-        Ok(events.into_iter().map(WalletEvent::from).collect())
+        self.apply_with_events(|wallet| wallet.apply_block_events(block, height))
+            .map_err(BdkError::from)
     }
 
     /// Apply a block to the wallet, explicitly specifying the connection point.
@@ -346,12 +617,8 @@ impl Wallet {
         height: u32,
         connected_to: BlockId,
     ) -> Result<Vec<WalletEvent>, BdkError> {
-        let events = self
-            .0
-            .borrow_mut()
-            .apply_block_connected_to_events(block, height, connected_to.into())
-            .map_err(BdkError::from)?;
-        Ok(events.into_iter().map(WalletEvent::from).collect())
+        self.apply_with_events(|wallet| wallet.apply_block_connected_to_events(block, height, connected_to.into()))
+            .map_err(BdkError::from)
     }
 
     /// Mark unconfirmed transactions as evicted from the mempool.
@@ -364,16 +631,170 @@ impl Wallet {
     /// can become canonical again if it is later observed on-chain or in the mempool
     /// with higher priority.
     pub fn apply_evicted_txs(&self, evicted_txs: Vec<EvictedTx>) {
-        self.0
+        self.inner
             .borrow_mut()
             .apply_evicted_txs(evicted_txs.into_iter().map(|e| (e.txid, e.evicted_at)));
+        self.schedule_persist();
     }
 
     /// List all checkpoints in the wallet's internal chain, ordered by height.
     ///
     /// Returns an array of `CheckPoint`s representing the wallet's view of the blockchain.
     pub fn checkpoints(&self) -> Vec<CheckPoint> {
-        self.0.borrow().checkpoints().map(Into::into).collect()
+        self.inner.borrow().checkpoints().map(Into::into).collect()
+    }
+}
+
+impl Wallet {
+    async fn create_persisted(
+        persister: WalletPersister,
+        network: Network,
+        external_descriptor: String,
+        internal_descriptor: String,
+    ) -> Result<Wallet, BdkError> {
+        let params = BdkWallet::create(external_descriptor, internal_descriptor).network(network.into());
+        let (wallet, store) = WalletStore::create(persister, params).await?;
+
+        Ok(Wallet::from_wallet(wallet, Some(store)))
+    }
+
+    async fn load_persisted(
+        persister: WalletPersister,
+        external_descriptor: Option<String>,
+        internal_descriptor: Option<String>,
+    ) -> Result<Option<Wallet>, BdkError> {
+        let params = Wallet::load_params(external_descriptor, internal_descriptor);
+        let loaded = WalletStore::load(persister, params).await?;
+
+        Ok(loaded.map(|(wallet, store)| Wallet::from_wallet(wallet, Some(store))))
+    }
+
+    fn load_params(external_descriptor: Option<String>, internal_descriptor: Option<String>) -> LoadParams {
+        let mut builder = BdkWallet::load();
+
+        if external_descriptor.is_some() {
+            builder = builder.descriptor(KeychainKind::External.into(), external_descriptor);
+        }
+
+        if internal_descriptor.is_some() {
+            builder = builder.descriptor(KeychainKind::Internal.into(), internal_descriptor);
+        }
+
+        builder.extract_keys()
+    }
+
+    fn from_wallet(wallet: BdkWallet, store: Option<WalletStore>) -> Wallet {
+        Wallet {
+            inner: Rc::new(RefCell::new(wallet)),
+            store: store.map(Rc::new),
+            external_signers: ExternalSigners::default(),
+        }
+    }
+
+    /// Same as `apply_update_events`, with the error as a `BdkError`.
+    #[cfg(feature = "esplora")]
+    pub(crate) fn apply_update_with_events(&self, update: Update) -> Result<Vec<WalletEvent>, BdkError> {
+        Ok(self.apply_with_events(|wallet| wallet.apply_update_events(update))?)
+    }
+
+    #[cfg(feature = "esplora")]
+    pub(crate) fn has_store(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// Run `apply` and return its events, followed by the `tx_received`/`tx_sent` events of the
+    /// transactions it added and a `balance_changed` event, found by diffing the wallet state.
+    fn apply_with_events<E>(
+        &self,
+        apply: impl FnOnce(&mut BdkWallet) -> Result<Vec<BdkWalletEvent>, E>,
+    ) -> Result<Vec<WalletEvent>, E> {
+        let (known_txids, old_balance) = {
+            let wallet = self.inner.borrow();
+            let known_txids: HashSet<BdkTxid> = wallet.tx_graph().full_txs().map(|tx| tx.txid).collect();
+            (known_txids, wallet.balance())
+        };
+
+        let events = apply(&mut self.inner.borrow_mut())?;
+        self.schedule_persist();
+
+        let wallet = self.inner.borrow();
+        let mut events: Vec<WalletEvent> = events.into_iter().map(WalletEvent::from).collect();
+        for tx in wallet.transactions() {
+            if known_txids.contains(&tx.tx_node.txid) {
+                continue;
+            }
+            let (sent, received) = wallet.sent_and_received(&tx.tx_node.tx);
+            events.push(WalletEvent::new_tx(
+                tx.tx_node.txid,
+                tx.tx_node.tx.clone(),
+                sent,
+                received,
+            ));
+        }
+        let new_balance = wallet.balance();
+        if new_balance != old_balance {
+            events.push(WalletEvent::balance_changed(old_balance, new_balance));
+        }
+        Ok(events)
+    }
+
+    /// Whether each input of `psbt` spends an output of this wallet.
+    fn owned_inputs(wallet: &BdkWallet, psbt: &BdkPsbt) -> Vec<bool> {
+        psbt.unsigned_tx
+            .input
+            .iter()
+            .enumerate()
+            .map(|(index, txin)| {
+                psbt.get_utxo_for(index)
+                    .or_else(|| wallet.tx_graph().get_txout(txin.previous_output).cloned())
+                    .is_some_and(|txout| wallet.is_mine(txout.script_pubkey))
+            })
+            .collect()
+    }
+
+    /// Finalize the inputs of `psbt`, first with the wallet's descriptors then with miniscript.
+    ///
+    /// Returns whether each input is finalized, with the miniscript error of those that are not.
+    #[allow(deprecated)]
+    fn finalize_inputs(
+        wallet: &BdkWallet,
+        psbt: &mut BdkPsbt,
+        options: BdkSignOptions,
+    ) -> Result<Vec<(bool, Option<String>)>, BdkError> {
+        // Inputs the wallet cannot finalize are left untouched.
+        wallet.finalize_psbt(psbt, options)?;
+
+        let mut statuses = Vec::with_capacity(psbt.inputs.len());
+        for index in 0..psbt.inputs.len() {
+            if is_finalized(&psbt.inputs[index]) {
+                statuses.push((true, None));
+                continue;
+            }
+            match psbt.finalize_inp_mut(wallet.secp_ctx(), index) {
+                Ok(()) => statuses.push((true, None)),
+                Err(e) => statuses.push((false, Some(e.to_string()))),
+            }
+        }
+        Ok(statuses)
+    }
+
+    fn schedule_persist(&self) {
+        if let Some(store) = &self.store {
+            store.schedule_flush(&self.inner);
+        }
+    }
+
+    /// All the script pubkeys derived by the wallet: the revealed ones and the lookahead.
+    #[cfg(feature = "cbf")]
+    pub(crate) fn derived_spks(&self) -> Vec<bdk_wallet::bitcoin::ScriptBuf> {
+        self.inner
+            .borrow()
+            .spk_index()
+            .inner()
+            .all_spks()
+            .values()
+            .cloned()
+            .collect()
     }
 }
 
@@ -469,6 +890,21 @@ impl Default for SignOptions {
     }
 }
 
+#[allow(deprecated)]
+impl From<SignerError> for BdkError {
+    fn from(e: SignerError) -> Self {
+        BdkError::new(BdkErrorCode::Signer, e.to_string(), ())
+    }
+}
+
+fn is_finalized(input: &BdkPsbtInput) -> bool {
+    input.final_script_sig.is_some() || input.final_script_witness.is_some()
+}
+
+fn signature_count(input: &BdkPsbtInput) -> usize {
+    input.partial_sigs.len() + input.tap_script_sigs.len() + usize::from(input.tap_key_sig.is_some())
+}
+
 impl From<CannotConnectError> for BdkError {
     fn from(e: CannotConnectError) -> Self {
         BdkError::new(BdkErrorCode::CannotConnect, e.to_string(), ())
@@ -0,0 +1,185 @@
+use std::{cell::RefCell, pin::pin, rc::Rc, time::Duration};
+
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::js_sys::Function;
+
+use crate::{
+    types::{BdkError, Update, WalletEvent, WalletEventKind},
+    utils::timer::sleep,
+};
+
+use super::{EsploraClient, Wallet};
+
+/// Keeps a wallet in sync with an Esplora server in the background.
+///
+/// Once started, the syncer syncs the wallet's revealed scripts, applies the update, persists the
+/// wallet if it has a store or persister, and dispatches the resulting `WalletEvent`s to the
+/// listeners registered with `on`, then waits for the interval before the next round. Errors are
+/// dispatched to the `on_error` listeners and the loop keeps going.
+///
+/// The syncer shares the wallet and the client with the JS objects it was created from, so the
+/// wallet can be used as usual while it runs. Restored wallets should be fully scanned first.
+#[wasm_bindgen]
+pub struct WalletSyncer {
+    state: Rc<SyncerState>,
+    stop: RefCell<Option<oneshot::Sender<()>>>,
+}
+
+struct SyncerState {
+    wallet: Wallet,
+    client: EsploraClient,
+    interval: Duration,
+    parallel_requests: usize,
+    listeners: RefCell<Vec<(WalletEventKind, Function)>>,
+    error_listeners: RefCell<Vec<Function>>,
+}
+
+#[wasm_bindgen]
+impl WalletSyncer {
+    /// Create a syncer running a round every `interval_ms` milliseconds, with up to
+    /// `parallel_requests` concurrent requests (5 by default).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        wallet: &Wallet,
+        client: &EsploraClient,
+        interval_ms: u32,
+        parallel_requests: Option<usize>,
+    ) -> WalletSyncer {
+        WalletSyncer {
+            state: Rc::new(SyncerState {
+                wallet: wallet.clone(),
+                client: client.clone(),
+                interval: Duration::from_millis(interval_ms.into()),
+                parallel_requests: parallel_requests.unwrap_or(5),
+                listeners: RefCell::new(Vec::new()),
+                error_listeners: RefCell::new(Vec::new()),
+            }),
+            stop: RefCell::new(None),
+        }
+    }
+
+    /// Call `listener` with every `WalletEvent` of the given kind.
+    pub fn on(&self, kind: WalletEventKind, listener: Function) {
+        self.state.listeners.borrow_mut().push((kind, listener));
+    }
+
+    /// Call `listener` with the `BdkError` of every failed round.
+    pub fn on_error(&self, listener: Function) {
+        self.state.error_listeners.borrow_mut().push(listener);
+    }
+
+    /// Start syncing in the background, with a first round right away. Does nothing if the syncer
+    /// is already running.
+    pub fn start(&self) {
+        if self.is_running() {
+            return;
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.stop.replace(Some(sender));
+        spawn_local(run(self.state.clone(), receiver));
+    }
+
+    /// Stop syncing. A round in progress is cancelled if its update is not fetched yet, otherwise it
+    /// completes first.
+    pub fn stop(&self) {
+        if let Some(sender) = self.stop.take() {
+            let _ = sender.send(());
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_running(&self) -> bool {
+        self.stop.borrow().as_ref().is_some_and(|sender| !sender.is_canceled())
+    }
+
+    /// Run a single round now, independently of the background loop, and return its events.
+    pub async fn sync(&self) -> Result<Vec<WalletEvent>, BdkError> {
+        self.state.sync().await
+    }
+}
+
+impl Drop for WalletSyncer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn run(state: Rc<SyncerState>, mut stop: oneshot::Receiver<()>) {
+    loop {
+        // Stopping cancels the requests of a round, but once its update is fetched the round
+        // applies, persists and dispatches it to completion: the wallet would otherwise change
+        // without its events, and the changes taken out to persist them could be lost.
+        let result = match select(pin!(state.fetch()), &mut stop).await {
+            Either::Left((Ok(update), _)) => state.apply(update).await.map(|_| ()),
+            Either::Left((Err(e), _)) => Err(e),
+            Either::Right(_) => return,
+        };
+        if let Err(e) = result {
+            state.dispatch_error(e);
+        }
+
+        if let Either::Right(_) = select(pin!(sleep(state.interval)), &mut stop).await {
+            return;
+        }
+    }
+}
+
+impl SyncerState {
+    async fn sync(&self) -> Result<Vec<WalletEvent>, BdkError> {
+        let update = self.fetch().await?;
+        self.apply(update).await
+    }
+
+    async fn fetch(&self) -> Result<Update, BdkError> {
+        let request = self.wallet.start_sync_with_revealed_spks(None);
+        self.client.sync(request, self.parallel_requests, None).await
+    }
+
+    async fn apply(&self, update: Update) -> Result<Vec<WalletEvent>, BdkError> {
+        let events = self.wallet.apply_update_with_events(update)?;
+        // The events are dispatched even if persisting fails, as the wallet has changed already.
+        let persisted = if self.wallet.has_store() {
+            self.wallet.persist().await.map(|_| ())
+        } else {
+            Ok(())
+        };
+
+        for event in &events {
+            self.dispatch(event);
+        }
+        persisted.map(|_| events)
+    }
+
+    fn dispatch(&self, event: &WalletEvent) {
+        // Listeners may register other listeners, so they are called outside of the borrow.
+        let listeners: Vec<Function> = self
+            .listeners
+            .borrow()
+            .iter()
+            .filter(|(kind, _)| *kind == event.kind())
+            .map(|(_, listener)| listener.clone())
+            .collect();
+        if listeners.is_empty() {
+            return;
+        }
+
+        let event = JsValue::from(event.clone());
+        for listener in listeners {
+            // A failing listener must not stop the others nor the loop.
+            let _ = listener.call1(&JsValue::NULL, &event);
+        }
+    }
+
+    fn dispatch_error(&self, error: BdkError) {
+        let listeners = self.error_listeners.borrow().clone();
+        let error = JsValue::from(error);
+        for listener in listeners {
+            let _ = listener.call1(&JsValue::NULL, &error);
+        }
+    }
+}
@@ -1,16 +1,21 @@
+use std::any::Any;
+
 use bdk_wallet::{
+    bitcoin::Script,
     chain::{
         spk_client::{
-            FullScanRequest as BdkFullScanRequest, FullScanResponse as BdkFullScanResponse,
-            SyncRequest as BdkSyncRequest, SyncResponse as BdkSyncResponse,
+            FullScanRequest as BdkFullScanRequest, FullScanResponse as BdkFullScanResponse, SyncItem as BdkSyncItem,
+            SyncProgress as BdkSyncProgress, SyncRequest as BdkSyncRequest,
+            SyncRequestBuilder as BdkSyncRequestBuilder, SyncResponse as BdkSyncResponse,
         },
         ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime,
     },
     KeychainKind, Update as BdkUpdate,
 };
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use web_sys::js_sys::{Date, Function};
 
-use super::{ConfirmationBlockTime, Txid};
+use super::{CheckPoint, ConfirmationBlockTime, KeychainKind as JsKeychainKind, OutPoint, ScriptBuf, Txid};
 
 /// Data required to perform a spk-based blockchain client sync.
 ///
@@ -21,6 +26,72 @@ pub struct SyncRequest(BdkSyncRequest<(KeychainKind, u32)>);
 
 impl_inner_wrapper!(SyncRequest, BdkSyncRequest<(KeychainKind, u32)>, into_inner);
 
+/// Index of the scripts added to a [`SyncRequestBuilder`] without keychain nor index. It is not a
+/// valid derivation index, so it never stands for a script of a wallet.
+const UNINDEXED: (KeychainKind, u32) = (KeychainKind::External, u32::MAX);
+
+/// Builder of a [`SyncRequest`] for an arbitrary set of scripts, transactions and outputs.
+///
+/// Each option setting method takes and returns the builder so you can chain calls, until
+/// [`build`] creates the request. Set the `chain_tip` to the wallet's latest checkpoint, so the
+/// resulting update connects to the wallet's chain and confirmations are recorded.
+#[wasm_bindgen]
+pub struct SyncRequestBuilder(BdkSyncRequestBuilder<(KeychainKind, u32)>);
+
+#[wasm_bindgen]
+impl SyncRequestBuilder {
+    /// Create an empty builder. The time of the sync, used for the mempool transactions, is now.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SyncRequestBuilder {
+        SyncRequestBuilder(BdkSyncRequest::builder_at((Date::now() / 1000.0) as u64))
+    }
+
+    /// Set the checkpoint the update must connect to, usually `wallet.latest_checkpoint`.
+    pub fn chain_tip(self, checkpoint: &CheckPoint) -> SyncRequestBuilder {
+        SyncRequestBuilder(self.0.chain_tip((**checkpoint).clone()))
+    }
+
+    /// Add script pubkeys to fetch the history of, e.g. scripts that do not belong to the wallet.
+    pub fn spks(self, scripts: Vec<ScriptBuf>) -> SyncRequestBuilder {
+        SyncRequestBuilder(
+            self.0
+                .spks_with_indexes(scripts.into_iter().map(|script| (UNINDEXED, script.into()))),
+        )
+    }
+
+    /// Add a script pubkey of the wallet to fetch the history of, along with its keychain and
+    /// derivation index.
+    pub fn spk_with_index(self, keychain: JsKeychainKind, index: u32, script: ScriptBuf) -> SyncRequestBuilder {
+        SyncRequestBuilder(self.0.spks_with_indexes([((keychain.into(), index), script.into())]))
+    }
+
+    /// Add transactions to check the confirmation status of.
+    pub fn txids(self, txids: Vec<Txid>) -> SyncRequestBuilder {
+        SyncRequestBuilder(self.0.txids(txids.into_iter().map(Into::into)))
+    }
+
+    /// Add outputs to check the spend status of.
+    pub fn outpoints(self, outpoints: Vec<OutPoint>) -> SyncRequestBuilder {
+        SyncRequestBuilder(self.0.outpoints(outpoints.into_iter().map(Into::into)))
+    }
+
+    /// Set a callback called for every item the client checks, with the progress of the whole
+    /// request.
+    pub fn inspect(self, inspect: SyncInspector) -> SyncRequestBuilder {
+        SyncRequestBuilder(self.0.inspect(inspect.into_fn()))
+    }
+
+    pub fn build(self) -> SyncRequest {
+        self.0.build().into()
+    }
+}
+
+impl Default for SyncRequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Data required to perform a spk-based blockchain client full scan.
 ///
 /// A client full scan iterates through all the scripts for the given keychains, fetching relevant
@@ -32,6 +103,140 @@ pub struct FullScanRequest(BdkFullScanRequest<KeychainKind>);
 
 impl_inner_wrapper!(FullScanRequest, BdkFullScanRequest<KeychainKind>, into_inner);
 
+#[wasm_bindgen(typescript_custom_section)]
+const INSPECTORS: &str = r#"
+/**
+ * Called by a full scan for every script it checks, e.g. to drive a progress indicator.
+ */
+export type FullScanInspector = (keychain: KeychainKind, index: number, script: ScriptBuf) => void;
+
+/**
+ * Called by a sync for every item it checks, with a description of the item (script, txid or
+ * outpoint) and the progress of the whole request.
+ */
+export type SyncInspector = (item: string, progress: SyncProgress) => void;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS function implementing the `FullScanInspector` type.
+    #[wasm_bindgen(typescript_type = "FullScanInspector")]
+    pub type FullScanInspector;
+
+    /// A JS function implementing the `SyncInspector` type.
+    #[wasm_bindgen(typescript_type = "SyncInspector")]
+    pub type SyncInspector;
+}
+
+/// A JS callback handed to BDK's request builders, which require `Send` inspectors.
+struct Inspector(Function);
+
+// SAFETY: Wasm is single-threaded; the value is never accessed concurrently.
+unsafe impl Send for Inspector {}
+
+impl Inspector {
+    // Exceptions thrown by the callback are ignored: a broken progress bar must not fail the scan.
+    fn call(&self, args: &[JsValue]) {
+        let _ = self.0.apply(&JsValue::NULL, &args.iter().collect());
+    }
+}
+
+impl FullScanInspector {
+    pub(crate) fn into_fn(self) -> impl FnMut(KeychainKind, u32, &Script) + Send + 'static {
+        let inspector = Inspector(self.unchecked_into());
+        move |keychain, index, script| {
+            inspector.call(&[
+                JsKeychainKind::from(keychain).into(),
+                index.into(),
+                ScriptBuf::from(script.to_owned()).into(),
+            ])
+        }
+    }
+}
+
+impl SyncInspector {
+    pub(crate) fn into_fn<I>(self) -> impl FnMut(BdkSyncItem<I>, BdkSyncProgress) + Send + 'static
+    where
+        I: std::fmt::Debug + Any,
+    {
+        let inspector = Inspector(self.unchecked_into());
+        move |item, progress| {
+            let item = match &item {
+                BdkSyncItem::Spk(index, spk) if (index as &dyn Any).downcast_ref() == Some(&UNINDEXED) => {
+                    format!("script '{spk}'")
+                }
+                item => item.to_string(),
+            };
+            inspector.call(&[item.into(), SyncProgress::from(progress).into()])
+        }
+    }
+}
+
+/// The progress of a [`SyncRequest`], in number of items checked (consumed) and left to check
+/// (remaining).
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct SyncProgress(BdkSyncProgress);
+
+impl_inner_wrapper!(SyncProgress, BdkSyncProgress);
+
+#[wasm_bindgen]
+impl SyncProgress {
+    /// Script pubkeys consumed by the request.
+    #[wasm_bindgen(getter)]
+    pub fn spks_consumed(&self) -> usize {
+        self.0.spks_consumed
+    }
+
+    /// Script pubkeys remaining in the request.
+    #[wasm_bindgen(getter)]
+    pub fn spks_remaining(&self) -> usize {
+        self.0.spks_remaining
+    }
+
+    /// Txids consumed by the request.
+    #[wasm_bindgen(getter)]
+    pub fn txids_consumed(&self) -> usize {
+        self.0.txids_consumed
+    }
+
+    /// Txids remaining in the request.
+    #[wasm_bindgen(getter)]
+    pub fn txids_remaining(&self) -> usize {
+        self.0.txids_remaining
+    }
+
+    /// Outpoints consumed by the request.
+    #[wasm_bindgen(getter)]
+    pub fn outpoints_consumed(&self) -> usize {
+        self.0.outpoints_consumed
+    }
+
+    /// Outpoints remaining in the request.
+    #[wasm_bindgen(getter)]
+    pub fn outpoints_remaining(&self) -> usize {
+        self.0.outpoints_remaining
+    }
+
+    /// Total consumed items of the request.
+    #[wasm_bindgen(getter)]
+    pub fn consumed(&self) -> usize {
+        self.0.consumed()
+    }
+
+    /// Total remaining items of the request.
+    #[wasm_bindgen(getter)]
+    pub fn remaining(&self) -> usize {
+        self.0.remaining()
+    }
+
+    /// Total items, consumed and remaining, of the request.
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> usize {
+        self.0.total()
+    }
+}
+
 /// An update to [`Wallet`].
 #[wasm_bindgen]
 #[derive(Clone)]
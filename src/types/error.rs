@@ -3,6 +3,7 @@ use serde_wasm_bindgen::to_value;
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 #[wasm_bindgen]
+#[derive(Debug)]
 pub struct BdkError {
     code: BdkErrorCode,
     message: String,
@@ -41,7 +42,7 @@ impl BdkError {
 }
 
 #[wasm_bindgen]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum BdkErrorCode {
     /// ------- Transaction creation errors -------
 
@@ -140,6 +141,74 @@ pub enum BdkErrorCode {
     /// Connected-to hash does not match the expected hash
     UnexpectedConnectedToHash,
 
+    /// ------- Persistence errors -------
+
+    /// The persister failed to initialize or to persist a changeset
+    Persist,
+    /// Tried to create a wallet in a store that already contains wallet data
+    DataAlreadyExists,
+    /// The persisted changeset could not be loaded into a wallet
+    Load,
+    /// The changeset blob is malformed and could not be decoded
+    ChangeSetEncoding,
+    /// The changeset blob was written with a newer, unknown format version
+    UnsupportedChangeSetVersion,
+    /// The encrypted changeset could not be decrypted (wrong passphrase or tampered data)
+    Decryption,
+
+    /// ------- Chain source errors -------
+
+    /// The Esplora request failed, timed out or the server returned an error
+    Esplora,
+    /// The operation was cancelled with an `AbortSignal`
+    Aborted,
+    /// The connection to the Electrum server could not be opened or was closed
+    ElectrumConnection,
+    /// The Electrum server returned an error or a response that could not be understood
+    ElectrumServer,
+    /// The Bitcoin Core RPC request failed or returned an error
+    BitcoindRpc,
+    /// The compact block filter source failed or returned invalid data
+    CompactFilters,
+
+    /// ------- PSBT errors -------
+
+    /// The PSBT version is not supported, only versions 0 and 2 are
+    UnsupportedPsbtVersion,
+    /// Inputs or outputs cannot be added to the PSBT, as per its version and modifiable flags
+    PsbtNotModifiable,
+    /// The wallet's signers failed to sign the PSBT
+    Signer,
+
+    /// ------- Message signing errors -------
+
+    /// Messages cannot be signed or verified for this address type, or in the requested format
+    UnsupportedAddressType,
+    /// The address is not derived from the wallet's descriptors
+    UnknownAddress,
+    /// The wallet has no private key for the address
+    MissingPrivateKey,
+
+    /// ------- Mnemonic errors -------
+
+    /// The mnemonic does not have 12, 15, 18, 21 or 24 words
+    BadWordCount,
+    /// The mnemonic contains a word that is not in the wordlist
+    UnknownWord,
+    /// The entropy is not 128 to 256 bits long, in multiples of 32 bits
+    BadEntropyBitCount,
+    /// The checksum of the mnemonic is invalid
+    InvalidChecksum,
+    /// The words of the mnemonic belong to several wordlists, the language must be given
+    AmbiguousLanguages,
+
+    /// ------- Multisig errors -------
+
+    /// The threshold is 0 or above the number of cosigners
+    InvalidThreshold,
+    /// The keys of a cosigner belong to another network
+    NetworkMismatch,
+
     /// ------- Other errors -------
     /// Unexpected error, should never happen
     Unexpected,
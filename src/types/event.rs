@@ -1,7 +1,13 @@
-use bdk_wallet::event::WalletEvent as BdkWalletEvent;
+use std::sync::Arc;
+
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, Transaction as BdkTransaction, Txid as BdkTxid},
+    event::WalletEvent as BdkWalletEvent,
+    Balance as BdkBalance,
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use super::{BlockId, ConfirmationBlockTime, Transaction, Txid};
+use super::{Amount, Balance, BlockId, ConfirmationBlockTime, Transaction, Txid};
 
 /// The kind of wallet event.
 #[wasm_bindgen]
@@ -17,13 +23,59 @@ pub enum WalletEventKind {
     TxReplaced = "tx_replaced",
     /// A transaction was dropped.
     TxDropped = "tx_dropped",
+    /// A transaction only receiving funds to the wallet was seen for the first time.
+    TxReceived = "tx_received",
+    /// A transaction spending funds of the wallet was seen for the first time.
+    TxSent = "tx_sent",
+    /// The balance of the wallet changed.
+    BalanceChanged = "balance_changed",
+    /// An event of a kind not supported by this version of the library.
+    Unknown = "unknown",
 }
 
 /// An event representing a change to the wallet state.
 ///
-/// Returned by `Wallet::apply_update_events`.
+/// Returned by `Wallet::apply_update_events`, `Wallet::apply_block_events` and
+/// `Wallet::apply_block_connected_to_events`. The `tx_received`, `tx_sent` and `balance_changed`
+/// events follow the ones reported by BDK.
 #[wasm_bindgen]
-pub struct WalletEvent(BdkWalletEvent);
+#[derive(Clone)]
+pub struct WalletEvent(Event);
+
+#[derive(Clone)]
+enum Event {
+    Bdk(BdkWalletEvent),
+    NewTx {
+        txid: BdkTxid,
+        tx: Arc<BdkTransaction>,
+        sent: BdkAmount,
+        received: BdkAmount,
+    },
+    BalanceChanged {
+        old_balance: BdkBalance,
+        new_balance: BdkBalance,
+    },
+}
+
+impl WalletEvent {
+    /// A transaction seen for the first time, `tx_sent` if it spends wallet funds and
+    /// `tx_received` otherwise.
+    pub(crate) fn new_tx(txid: BdkTxid, tx: Arc<BdkTransaction>, sent: BdkAmount, received: BdkAmount) -> Self {
+        WalletEvent(Event::NewTx {
+            txid,
+            tx,
+            sent,
+            received,
+        })
+    }
+
+    pub(crate) fn balance_changed(old_balance: BdkBalance, new_balance: BdkBalance) -> Self {
+        WalletEvent(Event::BalanceChanged {
+            old_balance,
+            new_balance,
+        })
+    }
+}
 
 #[wasm_bindgen]
 impl WalletEvent {
@@ -31,39 +83,71 @@ impl WalletEvent {
     #[wasm_bindgen(getter)]
     pub fn kind(&self) -> WalletEventKind {
         match &self.0 {
-            BdkWalletEvent::ChainTipChanged { .. } => WalletEventKind::ChainTipChanged,
-            BdkWalletEvent::TxConfirmed { .. } => WalletEventKind::TxConfirmed,
-            BdkWalletEvent::TxUnconfirmed { .. } => WalletEventKind::TxUnconfirmed,
-            BdkWalletEvent::TxReplaced { .. } => WalletEventKind::TxReplaced,
-            BdkWalletEvent::TxDropped { .. } => WalletEventKind::TxDropped,
-            _ => WalletEventKind::ChainTipChanged, // non_exhaustive fallback
+            Event::Bdk(event) => match event {
+                BdkWalletEvent::ChainTipChanged { .. } => WalletEventKind::ChainTipChanged,
+                BdkWalletEvent::TxConfirmed { .. } => WalletEventKind::TxConfirmed,
+                BdkWalletEvent::TxUnconfirmed { .. } => WalletEventKind::TxUnconfirmed,
+                BdkWalletEvent::TxReplaced { .. } => WalletEventKind::TxReplaced,
+                BdkWalletEvent::TxDropped { .. } => WalletEventKind::TxDropped,
+                // `BdkWalletEvent` is non_exhaustive: do not disguise new variants as known ones.
+                _ => WalletEventKind::Unknown,
+            },
+            Event::NewTx { sent, .. } if *sent > BdkAmount::ZERO => WalletEventKind::TxSent,
+            Event::NewTx { .. } => WalletEventKind::TxReceived,
+            Event::BalanceChanged { .. } => WalletEventKind::BalanceChanged,
         }
     }
 
     /// The transaction id, if applicable.
     ///
-    /// Available for: `tx_confirmed`, `tx_unconfirmed`, `tx_replaced`, `tx_dropped`.
+    /// Available for: `tx_confirmed`, `tx_unconfirmed`, `tx_replaced`, `tx_dropped`, `tx_received`,
+    /// `tx_sent`.
     #[wasm_bindgen(getter)]
     pub fn txid(&self) -> Option<Txid> {
         match &self.0 {
-            BdkWalletEvent::TxConfirmed { txid, .. }
-            | BdkWalletEvent::TxUnconfirmed { txid, .. }
-            | BdkWalletEvent::TxReplaced { txid, .. }
-            | BdkWalletEvent::TxDropped { txid, .. } => Some((*txid).into()),
+            Event::Bdk(
+                BdkWalletEvent::TxConfirmed { txid, .. }
+                | BdkWalletEvent::TxUnconfirmed { txid, .. }
+                | BdkWalletEvent::TxReplaced { txid, .. }
+                | BdkWalletEvent::TxDropped { txid, .. },
+            )
+            | Event::NewTx { txid, .. } => Some((*txid).into()),
             _ => None,
         }
     }
 
     /// The transaction, if applicable.
     ///
-    /// Available for: `tx_confirmed`, `tx_unconfirmed`, `tx_replaced`, `tx_dropped`.
+    /// Available for: `tx_confirmed`, `tx_unconfirmed`, `tx_replaced`, `tx_dropped`, `tx_received`,
+    /// `tx_sent`.
     #[wasm_bindgen(getter)]
     pub fn tx(&self) -> Option<Transaction> {
         match &self.0 {
-            BdkWalletEvent::TxConfirmed { tx, .. }
-            | BdkWalletEvent::TxUnconfirmed { tx, .. }
-            | BdkWalletEvent::TxReplaced { tx, .. }
-            | BdkWalletEvent::TxDropped { tx, .. } => Some(tx.as_ref().clone().into()),
+            Event::Bdk(
+                BdkWalletEvent::TxConfirmed { tx, .. }
+                | BdkWalletEvent::TxUnconfirmed { tx, .. }
+                | BdkWalletEvent::TxReplaced { tx, .. }
+                | BdkWalletEvent::TxDropped { tx, .. },
+            )
+            | Event::NewTx { tx, .. } => Some(tx.as_ref().clone().into()),
+            _ => None,
+        }
+    }
+
+    /// The amount the transaction spends from the wallet, for `tx_received` and `tx_sent` events.
+    #[wasm_bindgen(getter)]
+    pub fn sent(&self) -> Option<Amount> {
+        match &self.0 {
+            Event::NewTx { sent, .. } => Some((*sent).into()),
+            _ => None,
+        }
+    }
+
+    /// The amount the transaction pays to the wallet, for `tx_received` and `tx_sent` events.
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> Option<Amount> {
+        match &self.0 {
+            Event::NewTx { received, .. } => Some((*received).into()),
             _ => None,
         }
     }
@@ -72,7 +156,7 @@ impl WalletEvent {
     #[wasm_bindgen(getter)]
     pub fn old_tip(&self) -> Option<BlockId> {
         match &self.0 {
-            BdkWalletEvent::ChainTipChanged { old_tip, .. } => Some((*old_tip).into()),
+            Event::Bdk(BdkWalletEvent::ChainTipChanged { old_tip, .. }) => Some((*old_tip).into()),
             _ => None,
         }
     }
@@ -81,7 +165,7 @@ impl WalletEvent {
     #[wasm_bindgen(getter)]
     pub fn new_tip(&self) -> Option<BlockId> {
         match &self.0 {
-            BdkWalletEvent::ChainTipChanged { new_tip, .. } => Some((*new_tip).into()),
+            Event::Bdk(BdkWalletEvent::ChainTipChanged { new_tip, .. }) => Some((*new_tip).into()),
             _ => None,
         }
     }
@@ -90,7 +174,7 @@ impl WalletEvent {
     #[wasm_bindgen(getter)]
     pub fn block_time(&self) -> Option<ConfirmationBlockTime> {
         match &self.0 {
-            BdkWalletEvent::TxConfirmed { block_time, .. } => Some(block_time.into()),
+            Event::Bdk(BdkWalletEvent::TxConfirmed { block_time, .. }) => Some(block_time.into()),
             _ => None,
         }
     }
@@ -101,14 +185,34 @@ impl WalletEvent {
     #[wasm_bindgen(getter)]
     pub fn old_block_time(&self) -> Option<ConfirmationBlockTime> {
         match &self.0 {
-            BdkWalletEvent::TxConfirmed {
-                old_block_time: Some(bt),
-                ..
-            }
-            | BdkWalletEvent::TxUnconfirmed {
-                old_block_time: Some(bt),
-                ..
-            } => Some(ConfirmationBlockTime::from(bt)),
+            Event::Bdk(
+                BdkWalletEvent::TxConfirmed {
+                    old_block_time: Some(bt),
+                    ..
+                }
+                | BdkWalletEvent::TxUnconfirmed {
+                    old_block_time: Some(bt),
+                    ..
+                },
+            ) => Some(ConfirmationBlockTime::from(bt)),
+            _ => None,
+        }
+    }
+
+    /// The balance before the change, for `balance_changed` events.
+    #[wasm_bindgen(getter)]
+    pub fn old_balance(&self) -> Option<Balance> {
+        match &self.0 {
+            Event::BalanceChanged { old_balance, .. } => Some(old_balance.clone().into()),
+            _ => None,
+        }
+    }
+
+    /// The balance after the change, for `balance_changed` events.
+    #[wasm_bindgen(getter)]
+    pub fn new_balance(&self) -> Option<Balance> {
+        match &self.0 {
+            Event::BalanceChanged { new_balance, .. } => Some(new_balance.clone().into()),
             _ => None,
         }
     }
@@ -116,6 +220,6 @@ impl WalletEvent {
 
 impl From<BdkWalletEvent> for WalletEvent {
     fn from(inner: BdkWalletEvent) -> Self {
-        WalletEvent(inner)
+        WalletEvent(Event::Bdk(inner))
     }
 }
@@ -1,22 +1,9 @@
-use bdk_wallet::bitcoin::FeeRate as BdkFeeRate;
-use std::collections::HashMap;
+use bdk_wallet::bitcoin::{FeeRate as BdkFeeRate, Weight};
+use wasm_bindgen::{prelude::wasm_bindgen, JsError};
 
-use wasm_bindgen::prelude::wasm_bindgen;
+use crate::result::JsResult;
 
-/// Map where the key is the confirmation target (in number of blocks) and the value is the estimated feerate (in sat/vB).
-#[wasm_bindgen]
-pub struct FeeEstimates(HashMap<u16, f64>);
-
-impl_inner_wrapper!(FeeEstimates, HashMap<u16, f64>, into_inner);
-
-#[wasm_bindgen]
-impl FeeEstimates {
-    /// Returns the feerate (in sat/vB) or undefined.
-    /// Available confirmation targets are 1-25, 144, 504 and 1008 blocks.
-    pub fn get(&self, k: u16) -> Option<f64> {
-        self.0.get(&k).copied()
-    }
-}
+use super::Amount;
 
 /// Represents fee rate.
 ///
@@ -35,11 +22,36 @@ impl FeeRate {
         FeeRate(BdkFeeRate::from_sat_per_vb_unchecked(sat_vb))
     }
 
+    /// Constructs a fee rate from sat/kwu, the most precise unit.
+    pub fn from_sat_per_kwu(sat_kwu: u64) -> Self {
+        FeeRate(BdkFeeRate::from_sat_per_kwu(sat_kwu))
+    }
+
+    /// Constructs a fee rate from a fractional sat/vB rate, e.g. `1.5`.
+    ///
+    /// The rate is rounded up to the next sat/kwu (0.004 sat/vB), so it is never below the
+    /// requested one.
+    pub fn from_sat_per_vb(sat_vb: f64) -> JsResult<FeeRate> {
+        Self::from_sat_per_vb_f64(sat_vb).ok_or_else(|| JsError::new(&format!("Invalid fee rate: {sat_vb} sat/vB")))
+    }
+
+    /// Constructs a fee rate from BTC/kvB, the unit used by Bitcoin Core (e.g. `estimatesmartfee`).
+    pub fn from_btc_per_kvb(btc_kvb: f64) -> JsResult<FeeRate> {
+        // 1 BTC/kvB = 100_000_000 sat / 1000 vB
+        Self::from_sat_per_vb_f64(btc_kvb * 100_000.0)
+            .ok_or_else(|| JsError::new(&format!("Invalid fee rate: {btc_kvb} BTC/kvB")))
+    }
+
     /// Returns raw fee rate.
     pub fn to_sat_per_kwu(&self) -> u64 {
         self.0.to_sat_per_kwu()
     }
 
+    /// Converts to sat/vB, keeping the fractional part.
+    pub fn to_sat_per_vb(&self) -> f64 {
+        self.0.to_sat_per_kwu() as f64 / 250.0
+    }
+
     /// Converts to sat/vB rounding up.
     pub fn to_sat_per_vb_ceil(&self) -> u64 {
         self.0.to_sat_per_vb_ceil()
@@ -49,4 +61,36 @@ impl FeeRate {
     pub fn to_sat_per_vb_floor(&self) -> u64 {
         self.0.to_sat_per_vb_floor()
     }
+
+    /// Converts to BTC/kvB.
+    pub fn to_btc_per_kvb(&self) -> f64 {
+        self.0.to_sat_per_kwu() as f64 * 4.0 / 100_000_000.0
+    }
+
+    /// Calculates the fee of a transaction of `vsize` virtual bytes, rounded up to the next
+    /// satoshi. Returns undefined on overflow.
+    pub fn fee_for_vsize(&self, vsize: u64) -> Option<Amount> {
+        self.0.fee_vb(vsize).map(Into::into)
+    }
+
+    /// Calculates the fee of a transaction of `weight` weight units, rounded up to the next
+    /// satoshi. Returns undefined on overflow.
+    pub fn fee_for_weight(&self, weight: u64) -> Option<Amount> {
+        self.0.fee_wu(Weight::from_wu(weight)).map(Into::into)
+    }
+}
+
+impl FeeRate {
+    /// Convert a fractional sat/vB rate, as returned by fee estimators, rounding up to the next
+    /// sat/kwu so the rate is never below the requested one. Returns `None` for negative,
+    /// non-finite or too large rates.
+    pub(crate) fn from_sat_per_vb_f64(sat_vb: f64) -> Option<FeeRate> {
+        if !sat_vb.is_finite() || sat_vb < 0.0 {
+            return None;
+        }
+        // 1 vB = 4 wu, so 1 sat/vB = 250 sat/kwu. The epsilon absorbs the floating point error
+        // of the unit conversions (e.g. 1.1 * 250 = 275.00000000000006) before rounding up.
+        let sat_kwu = (sat_vb * 250.0 - 1e-6).max(0.0).ceil();
+        (sat_kwu < u64::MAX as f64).then(|| FeeRate(BdkFeeRate::from_sat_per_kwu(sat_kwu as u64)))
+    }
 }
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bdk_wallet::{bitcoin::FeeRate as BdkFeeRate, serde_json::from_str};
+use serde::Deserialize;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::result::JsResult;
+
+use super::FeeRate;
+
+/// Confirmation targets, in blocks, of the [`FeeTier`]s computed from [`FeeEstimates`].
+const FASTEST_TARGET: u16 = 1;
+const HALF_HOUR_TARGET: u16 = 3;
+const HOUR_TARGET: u16 = 6;
+const ECONOMY_TARGET: u16 = 144;
+const MINIMUM_TARGET: u16 = 1008;
+
+/// Map where the key is the confirmation target (in number of blocks) and the value is the estimated feerate (in sat/vB).
+#[wasm_bindgen]
+pub struct FeeEstimates(HashMap<u16, f64>);
+
+impl_inner_wrapper!(FeeEstimates, HashMap<u16, f64>, into_inner);
+
+#[wasm_bindgen]
+impl FeeEstimates {
+    /// Parse the output of Esplora's `/fee-estimates` endpoint, e.g. `{"1": 87.882, "2": 87.882}`.
+    pub fn from_json(json: &str) -> JsResult<FeeEstimates> {
+        Ok(FeeEstimates(from_str(json)?))
+    }
+
+    /// Returns the feerate (in sat/vB) or undefined.
+    /// Available confirmation targets are 1-25, 144, 504 and 1008 blocks.
+    pub fn get(&self, k: u16) -> Option<f64> {
+        self.0.get(&k).copied()
+    }
+
+    /// Returns the feerate (in sat/vB) to confirm within `target` blocks, or undefined if there
+    /// are no estimates.
+    ///
+    /// Targets without an estimate are linearly interpolated between the closest available ones.
+    /// Targets below the lowest or above the highest available one get its estimate.
+    pub fn interpolate(&self, target: u16) -> Option<f64> {
+        let estimates: BTreeMap<u16, f64> = self
+            .0
+            .iter()
+            .filter(|(_, rate)| rate.is_finite() && **rate >= 0.0)
+            .map(|(target, rate)| (*target, *rate))
+            .collect();
+
+        let below = estimates.range(..=target).next_back();
+        let above = estimates.range(target..).next();
+        match (below, above) {
+            (Some((&low, &low_rate)), Some((&high, &high_rate))) if low != high => {
+                let ratio = f64::from(target - low) / f64::from(high - low);
+                Some(low_rate + (high_rate - low_rate) * ratio)
+            }
+            (Some((_, &rate)), _) | (None, Some((_, &rate))) => Some(rate),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the [`FeeRate`] to confirm within `target` blocks, keeping the sub-sat/vB
+    /// precision of the estimate, or undefined if there are no estimates.
+    pub fn fee_rate(&self, target: u16) -> Option<FeeRate> {
+        self.interpolate(target).and_then(FeeRate::from_sat_per_vb_f64)
+    }
+
+    /// Returns the [`RecommendedFees`] for the usual confirmation targets, or undefined if there
+    /// are no estimates.
+    pub fn recommended(&self) -> Option<RecommendedFees> {
+        RecommendedFees::new(
+            self.interpolate(FASTEST_TARGET)?,
+            self.interpolate(HALF_HOUR_TARGET)?,
+            self.interpolate(HOUR_TARGET)?,
+            self.interpolate(ECONOMY_TARGET)?,
+            self.interpolate(MINIMUM_TARGET)?,
+        )
+        .ok()
+    }
+}
+
+/// Named fee rate tiers, as used by mempool.space.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FeeTier {
+    /// Next block.
+    Fastest = "fastest",
+    /// Within 3 blocks.
+    HalfHour = "halfHour",
+    /// Within 6 blocks.
+    Hour = "hour",
+    /// Within a day.
+    Economy = "economy",
+    /// Lowest rate still relayed and eventually confirmed.
+    Minimum = "minimum",
+}
+
+/// Fee rates for each [`FeeTier`], either computed from [`FeeEstimates`] or parsed from
+/// mempool.space's `/v1/fees/recommended` endpoint.
+///
+/// Slower tiers never have a higher rate than faster ones.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct RecommendedFees {
+    fastest: FeeRate,
+    half_hour: FeeRate,
+    hour: FeeRate,
+    economy: FeeRate,
+    minimum: FeeRate,
+}
+
+/// Response of mempool.space's `/v1/fees/recommended` (or `/v1/fees/precise`) endpoint, in sat/vB.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MempoolRecommendedFees {
+    fastest_fee: f64,
+    half_hour_fee: f64,
+    hour_fee: f64,
+    economy_fee: f64,
+    minimum_fee: f64,
+}
+
+#[wasm_bindgen]
+impl RecommendedFees {
+    /// Parse the output of mempool.space's `/v1/fees/recommended` endpoint, e.g.
+    /// `{"fastestFee": 12, "halfHourFee": 10, "hourFee": 8, "economyFee": 4, "minimumFee": 2}`.
+    ///
+    /// Fractional rates, as returned by `/v1/fees/precise`, are kept.
+    pub fn from_json(json: &str) -> JsResult<RecommendedFees> {
+        let fees: MempoolRecommendedFees = from_str(json)?;
+        RecommendedFees::new(
+            fees.fastest_fee,
+            fees.half_hour_fee,
+            fees.hour_fee,
+            fees.economy_fee,
+            fees.minimum_fee,
+        )
+    }
+
+    /// Returns the fee rate of `tier`.
+    pub fn get(&self, tier: FeeTier) -> FeeRate {
+        match tier {
+            FeeTier::Fastest => self.fastest,
+            FeeTier::HalfHour => self.half_hour,
+            FeeTier::Hour => self.hour,
+            FeeTier::Economy => self.economy,
+            FeeTier::Minimum => self.minimum,
+            _ => self.fastest,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn fastest(&self) -> FeeRate {
+        self.fastest
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn half_hour(&self) -> FeeRate {
+        self.half_hour
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn hour(&self) -> FeeRate {
+        self.hour
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn economy(&self) -> FeeRate {
+        self.economy
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn minimum(&self) -> FeeRate {
+        self.minimum
+    }
+}
+
+impl RecommendedFees {
+    /// Build the tiers from sat/vB rates, from the fastest to the slowest.
+    fn new(fastest: f64, half_hour: f64, hour: f64, economy: f64, minimum: f64) -> JsResult<RecommendedFees> {
+        let rate = |sat_vb: f64| FeeRate::from_sat_per_vb(sat_vb).map(BdkFeeRate::from);
+
+        // Estimates for different targets come from separate samples, so they are not always
+        // monotonic: raise each tier to at least the rate of the next slower one.
+        let minimum = rate(minimum)?;
+        let economy = rate(economy)?.max(minimum);
+        let hour = rate(hour)?.max(economy);
+        let half_hour = rate(half_hour)?.max(hour);
+        let fastest = rate(fastest)?.max(half_hour);
+
+        Ok(RecommendedFees {
+            fastest: fastest.into(),
+            half_hour: half_hour.into(),
+            hour: hour.into(),
+            economy: economy.into(),
+            minimum: minimum.into(),
+        })
+    }
+}
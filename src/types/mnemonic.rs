@@ -0,0 +1,159 @@
+use bdk_wallet::keys::bip39::{Error as Bip39Error, Language as Bip39Language, Mnemonic as Bip39Mnemonic};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use super::{BdkError, BdkErrorCode};
+
+/// A BIP39 mnemonic code.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Mnemonic(Bip39Mnemonic);
+
+impl_inner_wrapper!(Mnemonic, Bip39Mnemonic, into_inner);
+
+#[wasm_bindgen]
+impl Mnemonic {
+    /// Generate a new mnemonic of `word_count` words (12, 15, 18, 21 or 24) from random entropy.
+    ///
+    /// Words are taken from the English wordlist unless another `language` is given.
+    pub fn generate(word_count: usize, language: Option<Language>) -> Result<Mnemonic, BdkError> {
+        if !(12..=24).contains(&word_count) || word_count % 3 != 0 {
+            return Err(Bip39Error::BadWordCount(word_count).into());
+        }
+
+        let mut entropy = [0u8; 32];
+        let entropy = &mut entropy[..word_count / 3 * 4];
+        getrandom::getrandom(entropy).map_err(|e| BdkError::new(BdkErrorCode::Unexpected, e.to_string(), ()))?;
+
+        Mnemonic::from_entropy(entropy, language)
+    }
+
+    /// Create a mnemonic encoding `entropy`, of 16 to 32 bytes by multiples of 4.
+    pub fn from_entropy(entropy: &[u8], language: Option<Language>) -> Result<Mnemonic, BdkError> {
+        let language = language.unwrap_or(Language::English);
+        Ok(Mnemonic(Bip39Mnemonic::from_entropy_in(language.into(), entropy)?))
+    }
+
+    /// Parse a mnemonic and validate its checksum.
+    ///
+    /// The language is detected from the words unless it is given.
+    pub fn from_string(mnemonic: &str, language: Option<Language>) -> Result<Mnemonic, BdkError> {
+        let mnemonic = match language {
+            Some(language) => Bip39Mnemonic::parse_in(language.into(), mnemonic)?,
+            None => Bip39Mnemonic::parse(mnemonic)?,
+        };
+        Ok(Mnemonic(mnemonic))
+    }
+
+    /// The 64-byte BIP39 seed of the mnemonic, protected by an optional `passphrase`.
+    pub fn to_seed(&self, passphrase: Option<String>) -> Vec<u8> {
+        self.0.to_seed(passphrase.unwrap_or_default()).to_vec()
+    }
+
+    /// The language of the words of the mnemonic
+    #[wasm_bindgen(getter)]
+    pub fn language(&self) -> Language {
+        self.0.language().into()
+    }
+
+    /// Number of words of the mnemonic
+    #[wasm_bindgen(getter)]
+    pub fn word_count(&self) -> usize {
+        self.0.word_count()
+    }
+
+    /// The words of the mnemonic
+    #[wasm_bindgen(getter)]
+    pub fn words(&self) -> Vec<String> {
+        self.0.words().map(String::from).collect()
+    }
+
+    /// The entropy encoded by the mnemonic
+    #[wasm_bindgen(getter)]
+    pub fn entropy(&self) -> Vec<u8> {
+        self.0.to_entropy()
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.0.to_string()
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn js_clone(&self) -> Mnemonic {
+        self.clone()
+    }
+}
+
+/// Language of a BIP39 wordlist.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// The English wordlist.
+    English = "english",
+    /// The Simplified Chinese wordlist.
+    SimplifiedChinese = "simplified_chinese",
+    /// The Traditional Chinese wordlist.
+    TraditionalChinese = "traditional_chinese",
+    /// The Czech wordlist.
+    Czech = "czech",
+    /// The French wordlist.
+    French = "french",
+    /// The Italian wordlist.
+    Italian = "italian",
+    /// The Japanese wordlist.
+    Japanese = "japanese",
+    /// The Korean wordlist.
+    Korean = "korean",
+    /// The Portuguese wordlist.
+    Portuguese = "portuguese",
+    /// The Spanish wordlist.
+    Spanish = "spanish",
+}
+
+impl From<Bip39Language> for Language {
+    fn from(language: Bip39Language) -> Self {
+        match language {
+            Bip39Language::English => Language::English,
+            Bip39Language::SimplifiedChinese => Language::SimplifiedChinese,
+            Bip39Language::TraditionalChinese => Language::TraditionalChinese,
+            Bip39Language::Czech => Language::Czech,
+            Bip39Language::French => Language::French,
+            Bip39Language::Italian => Language::Italian,
+            Bip39Language::Japanese => Language::Japanese,
+            Bip39Language::Korean => Language::Korean,
+            Bip39Language::Portuguese => Language::Portuguese,
+            Bip39Language::Spanish => Language::Spanish,
+        }
+    }
+}
+
+impl From<Language> for Bip39Language {
+    fn from(language: Language) -> Self {
+        match language {
+            Language::SimplifiedChinese => Bip39Language::SimplifiedChinese,
+            Language::TraditionalChinese => Bip39Language::TraditionalChinese,
+            Language::Czech => Bip39Language::Czech,
+            Language::French => Bip39Language::French,
+            Language::Italian => Bip39Language::Italian,
+            Language::Japanese => Bip39Language::Japanese,
+            Language::Korean => Bip39Language::Korean,
+            Language::Portuguese => Bip39Language::Portuguese,
+            Language::Spanish => Bip39Language::Spanish,
+            _ => Bip39Language::English,
+        }
+    }
+}
+
+impl From<Bip39Error> for BdkError {
+    fn from(e: Bip39Error) -> Self {
+        use Bip39Error::*;
+        match &e {
+            BadWordCount(count) => BdkError::new(BdkErrorCode::BadWordCount, e.to_string(), count),
+            UnknownWord(index) => BdkError::new(BdkErrorCode::UnknownWord, e.to_string(), index),
+            BadEntropyBitCount(bits) => BdkError::new(BdkErrorCode::BadEntropyBitCount, e.to_string(), bits),
+            InvalidChecksum => BdkError::new(BdkErrorCode::InvalidChecksum, e.to_string(), ()),
+            AmbiguousLanguages(_) => BdkError::new(BdkErrorCode::AmbiguousLanguages, e.to_string(), ()),
+        }
+    }
+}
@@ -8,8 +8,10 @@ mod checkpoint;
 mod error;
 mod event;
 mod fee;
+mod fee_estimation;
 mod input;
 mod keychain;
+mod mnemonic;
 mod network;
 mod output;
 mod psbt;
@@ -28,8 +30,10 @@ pub use checkpoint::*;
 pub use error::*;
 pub use event::*;
 pub use fee::*;
+pub use fee_estimation::*;
 pub use input::*;
 pub use keychain::*;
+pub use mnemonic::*;
 pub use network::*;
 pub use output::*;
 pub use psbt::*;
@@ -1,18 +1,25 @@
 use bdk_wallet::serde_json::to_string;
 use std::ops::DerefMut;
-use std::str::FromStr;
 
 use bdk_wallet::{
-    bitcoin::{Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf},
+    bitcoin::{
+        base64::{prelude::BASE64_STANDARD, Engine},
+        bip32::KeySource,
+        hex::DisplayHex,
+        psbt::{Input as BdkInput, Output as BdkOutput},
+        taproot::TapLeafHash,
+        Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf, Sequence, TxIn as BdkTxIn, TxOut as BdkTxOut,
+    },
     psbt::PsbtUtils,
 };
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::result::JsResult;
-use crate::types::ScriptBuf;
+use crate::types::{BdkError, BdkErrorCode, ScriptBuf};
+use crate::utils::psbt_v2;
 
-use super::{Address, Amount, FeeRate, Transaction};
+use super::{Address, Amount, FeeRate, OutPoint, Transaction, TxOut};
 
 /// A Partially Signed Transaction.
 #[wasm_bindgen]
@@ -73,17 +80,133 @@ impl Psbt {
         fee_rate.map(Into::into)
     }
 
-    /// The version number of this PSBT. If omitted, the version number is 0.
+    /// The version number of this PSBT: 0 ([BIP 174]) or 2 ([BIP 370]). If omitted, the version number is 0.
+    ///
+    /// [BIP 174]: https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki
+    /// [BIP 370]: https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
     #[wasm_bindgen(getter)]
     pub fn version(&self) -> u32 {
         self.0.version
     }
 
+    /// Converts this PSBT to a version 0 PSBT, dropping the fields only allowed in version 2.
+    pub fn to_v0(&self) -> Psbt {
+        let mut psbt = self.0.clone();
+        psbt.version = 0;
+        psbt_v2::strip_v2_fields(&mut psbt);
+        Psbt(psbt)
+    }
+
+    /// Converts this PSBT to a version 2 PSBT.
+    ///
+    /// Inputs and outputs are not modifiable until allowed with [`set_tx_modifiable`].
+    pub fn to_v2(&self) -> Psbt {
+        let mut psbt = self.0.clone();
+        psbt.version = 2;
+        psbt_v2::update_tx_modifiable(&mut psbt);
+        Psbt(psbt)
+    }
+
+    /// Whether inputs can be added to this version 2 PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn inputs_modifiable(&self) -> bool {
+        self.0.version == 2 && psbt_v2::tx_modifiable(&self.0) & psbt_v2::INPUTS_MODIFIABLE != 0
+    }
+
+    /// Whether outputs can be added to this version 2 PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn outputs_modifiable(&self) -> bool {
+        self.0.version == 2 && psbt_v2::tx_modifiable(&self.0) & psbt_v2::OUTPUTS_MODIFIABLE != 0
+    }
+
+    /// Whether an input of this version 2 PSBT has a `SIGHASH_SINGLE` signature, whose paired
+    /// output at the same index cannot change.
+    #[wasm_bindgen(getter)]
+    pub fn has_sighash_single(&self) -> bool {
+        self.0.version == 2 && psbt_v2::tx_modifiable(&self.0) & psbt_v2::HAS_SIGHASH_SINGLE != 0
+    }
+
+    /// Sets whether inputs and outputs can be added to this version 2 PSBT.
+    ///
+    /// Constructors allow it while the transaction is being put together and clear it once done.
+    /// What the existing signatures commit to stays not modifiable.
+    pub fn set_tx_modifiable(&mut self, inputs: bool, outputs: bool) -> Result<(), BdkError> {
+        self.require_v2()?;
+
+        let mut flags = psbt_v2::tx_modifiable(&self.0) & !(psbt_v2::INPUTS_MODIFIABLE | psbt_v2::OUTPUTS_MODIFIABLE);
+        if inputs {
+            flags |= psbt_v2::INPUTS_MODIFIABLE;
+        }
+        if outputs {
+            flags |= psbt_v2::OUTPUTS_MODIFIABLE;
+        }
+        psbt_v2::set_tx_modifiable(&mut self.0, flags);
+        psbt_v2::update_tx_modifiable(&mut self.0);
+        Ok(())
+    }
+
+    /// Adds an input spending `outpoint` to this version 2 PSBT, as the constructor role.
+    ///
+    /// The spent output can be given as `witness_utxo` for segwit inputs, and as the full
+    /// `non_witness_utxo` transaction for legacy ones. Without `sequence`, the input has the
+    /// final sequence `0xFFFFFFFF`.
+    ///
+    /// Fails if a signature of the PSBT commits to all of its inputs, as per BIP 370.
+    pub fn add_input(
+        &mut self,
+        outpoint: OutPoint,
+        sequence: Option<u32>,
+        witness_utxo: Option<TxOut>,
+        non_witness_utxo: Option<Transaction>,
+    ) -> Result<(), BdkError> {
+        if !self.inputs_modifiable() {
+            return Err(BdkError::new(
+                BdkErrorCode::PsbtNotModifiable,
+                "Inputs of this PSBT are not modifiable",
+                (),
+            ));
+        }
+        psbt_v2::check_input_addition(&self.0)?;
+
+        self.0.unsigned_tx.input.push(BdkTxIn {
+            previous_output: outpoint.into(),
+            sequence: sequence.map(Sequence).unwrap_or(Sequence::MAX),
+            ..Default::default()
+        });
+        self.0.inputs.push(BdkInput {
+            witness_utxo: witness_utxo.map(Into::into),
+            non_witness_utxo: non_witness_utxo.map(Into::into),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Adds an output paying `recipient` to this version 2 PSBT, as the constructor role.
+    ///
+    /// Fails if a signature of the PSBT commits to all of its outputs, or if the new output
+    /// would be paired with a `SIGHASH_SINGLE` signed input, as per BIP 370.
+    pub fn add_output(&mut self, recipient: Recipient) -> Result<(), BdkError> {
+        if !self.outputs_modifiable() {
+            return Err(BdkError::new(
+                BdkErrorCode::PsbtNotModifiable,
+                "Outputs of this PSBT are not modifiable",
+                (),
+            ));
+        }
+        psbt_v2::check_output_addition(&self.0)?;
+
+        let (script_pubkey, value) = recipient.into();
+        self.0.unsigned_tx.output.push(BdkTxOut { value, script_pubkey });
+        self.0.outputs.push(BdkOutput::default());
+        Ok(())
+    }
+
     /// Combines this [`Psbt`] with `other` PSBT as described by BIP 174. In-place.
     ///
     /// In accordance with BIP 174 this function is commutative i.e., `A.combine(B) == B.combine(A)`
     pub fn combine(&mut self, other: Psbt) -> JsResult<()> {
         self.0.combine(other.into())?;
+        psbt_v2::update_tx_modifiable(&mut self.0);
         Ok(())
     }
 
@@ -93,16 +216,33 @@ impl Psbt {
         self.0.unsigned_tx.clone().into()
     }
 
-    /// Serialize the PSBT to a string in base64 format
+    /// Serialize the PSBT to a string in base64 format, in the layout of its version.
     #[allow(clippy::inherent_to_string)]
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
-        self.0.to_string()
+        match self.0.version {
+            2 => BASE64_STANDARD.encode(psbt_v2::encode(&self.0)),
+            _ => self.0.to_string(),
+        }
     }
 
-    /// Create a PSBT from a base64 string
-    pub fn from_string(val: &str) -> JsResult<Psbt> {
-        Ok(Psbt(BdkPsbt::from_str(val)?))
+    /// Create a PSBT from a base64 string, either a version 0 or a version 2 PSBT.
+    pub fn from_string(val: &str) -> Result<Psbt, BdkError> {
+        let bytes = BASE64_STANDARD
+            .decode(val)
+            .map_err(|e| BdkError::new(BdkErrorCode::Psbt, e.to_string(), ()))?;
+
+        match psbt_v2::version(&bytes)? {
+            0 => BdkPsbt::deserialize(&bytes)
+                .map(Psbt)
+                .map_err(|e| BdkError::new(BdkErrorCode::Psbt, e.to_string(), ())),
+            2 => psbt_v2::decode(&bytes).map(Psbt),
+            version => Err(BdkError::new(
+                BdkErrorCode::UnsupportedPsbtVersion,
+                format!("Unsupported PSBT version {version}"),
+                version,
+            )),
+        }
     }
 
     /// Serialize `Psbt` to JSON.
@@ -110,12 +250,312 @@ impl Psbt {
         to_string(&self.0).expect("Serialization should not fail")
     }
 
+    /// The PSBT data of each input, in the order of the unsigned transaction's inputs.
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<PsbtInput> {
+        self.0.inputs.iter().cloned().map(PsbtInput).collect()
+    }
+
+    /// The PSBT data of each output, in the order of the unsigned transaction's outputs.
+    #[wasm_bindgen(getter)]
+    pub fn outputs(&self) -> Vec<PsbtOutput> {
+        self.0.outputs.iter().cloned().map(PsbtOutput).collect()
+    }
+
     #[wasm_bindgen(js_name = clone)]
     pub fn js_clone(&self) -> Psbt {
         self.clone()
     }
 }
 
+impl Psbt {
+    fn require_v2(&self) -> Result<(), BdkError> {
+        if self.0.version != 2 {
+            return Err(BdkError::new(
+                BdkErrorCode::PsbtNotModifiable,
+                "Only version 2 PSBTs have modifiable flags",
+                (),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The PSBT data of a transaction input.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtInput(BdkInput);
+
+impl_inner_wrapper!(PsbtInput, BdkInput, into_inner);
+
+#[wasm_bindgen]
+impl PsbtInput {
+    /// The output spent by this input, for segwit inputs.
+    #[wasm_bindgen(getter)]
+    pub fn witness_utxo(&self) -> Option<TxOut> {
+        self.0.witness_utxo.as_ref().map(Into::into)
+    }
+
+    /// The full transaction containing the output spent by this input.
+    #[wasm_bindgen(getter)]
+    pub fn non_witness_utxo(&self) -> Option<Transaction> {
+        self.0.non_witness_utxo.clone().map(Into::into)
+    }
+
+    /// The ECDSA signatures collected so far.
+    #[wasm_bindgen(getter)]
+    pub fn partial_sigs(&self) -> Vec<PartialSignature> {
+        self.0
+            .partial_sigs
+            .iter()
+            .map(|(public_key, signature)| PartialSignature {
+                public_key: public_key.to_string(),
+                signature: signature.to_vec().to_lower_hex_string(),
+                sighash_type: signature.sighash_type as u32,
+            })
+            .collect()
+    }
+
+    /// The sighash type signers are asked to use, as its consensus value (e.g. `1` for `SIGHASH_ALL`).
+    #[wasm_bindgen(getter)]
+    pub fn sighash_type(&self) -> Option<u32> {
+        self.0.sighash_type.map(|sighash_type| sighash_type.to_u32())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn redeem_script(&self) -> Option<ScriptBuf> {
+        self.0.redeem_script.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn witness_script(&self) -> Option<ScriptBuf> {
+        self.0.witness_script.clone().map(Into::into)
+    }
+
+    /// The origins of the public keys needed to sign this input.
+    #[wasm_bindgen(getter)]
+    pub fn bip32_derivation(&self) -> Vec<KeyOrigin> {
+        self.0
+            .bip32_derivation
+            .iter()
+            .map(|(public_key, source)| KeyOrigin::new(public_key.to_string(), source, &[]))
+            .collect()
+    }
+
+    /// The origins of the x-only public keys needed to sign this taproot input.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_origins(&self) -> Vec<KeyOrigin> {
+        self.0
+            .tap_key_origins
+            .iter()
+            .map(|(public_key, (leaf_hashes, source))| KeyOrigin::new(public_key.to_string(), source, leaf_hashes))
+            .collect()
+    }
+
+    /// The taproot internal key, as an x-only public key in hex.
+    #[wasm_bindgen(getter)]
+    pub fn tap_internal_key(&self) -> Option<String> {
+        self.0.tap_internal_key.map(|key| key.to_string())
+    }
+
+    /// The taproot key spend signature in hex, including the sighash byte if not `SIGHASH_DEFAULT`.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_sig(&self) -> Option<String> {
+        self.0
+            .tap_key_sig
+            .map(|signature| signature.to_vec().to_lower_hex_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn final_script_sig(&self) -> Option<ScriptBuf> {
+        self.0.final_script_sig.clone().map(Into::into)
+    }
+
+    /// The items of the final witness, in hex.
+    #[wasm_bindgen(getter)]
+    pub fn final_script_witness(&self) -> Option<Vec<String>> {
+        self.0
+            .final_script_witness
+            .as_ref()
+            .map(|witness| witness.iter().map(|item| item.to_lower_hex_string()).collect())
+    }
+
+    /// Whether the input has a final scriptSig or witness, i.e. it is ready to be extracted.
+    #[wasm_bindgen(getter)]
+    pub fn is_finalized(&self) -> bool {
+        self.0.final_script_sig.is_some() || self.0.final_script_witness.is_some()
+    }
+}
+
+/// The PSBT data of a transaction output.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtOutput(BdkOutput);
+
+impl_inner_wrapper!(PsbtOutput, BdkOutput, into_inner);
+
+#[wasm_bindgen]
+impl PsbtOutput {
+    #[wasm_bindgen(getter)]
+    pub fn redeem_script(&self) -> Option<ScriptBuf> {
+        self.0.redeem_script.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn witness_script(&self) -> Option<ScriptBuf> {
+        self.0.witness_script.clone().map(Into::into)
+    }
+
+    /// The origins of the public keys of this output, e.g. to recognize change.
+    #[wasm_bindgen(getter)]
+    pub fn bip32_derivation(&self) -> Vec<KeyOrigin> {
+        self.0
+            .bip32_derivation
+            .iter()
+            .map(|(public_key, source)| KeyOrigin::new(public_key.to_string(), source, &[]))
+            .collect()
+    }
+
+    /// The origins of the x-only public keys of this taproot output.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_origins(&self) -> Vec<KeyOrigin> {
+        self.0
+            .tap_key_origins
+            .iter()
+            .map(|(public_key, (leaf_hashes, source))| KeyOrigin::new(public_key.to_string(), source, leaf_hashes))
+            .collect()
+    }
+
+    /// The taproot internal key, as an x-only public key in hex.
+    #[wasm_bindgen(getter)]
+    pub fn tap_internal_key(&self) -> Option<String> {
+        self.0.tap_internal_key.map(|key| key.to_string())
+    }
+}
+
+/// The origin of a public key in a PSBT: the fingerprint of its master key and its derivation path.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct KeyOrigin {
+    public_key: String,
+    fingerprint: String,
+    path: String,
+    leaf_hashes: Vec<String>,
+}
+
+impl KeyOrigin {
+    fn new(public_key: String, (fingerprint, path): &KeySource, leaf_hashes: &[TapLeafHash]) -> Self {
+        KeyOrigin {
+            public_key,
+            fingerprint: fingerprint.to_string(),
+            path: path.to_string(),
+            leaf_hashes: leaf_hashes.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl KeyOrigin {
+    /// The public key in hex, x-only for taproot keys.
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    /// The fingerprint of the master key, in hex.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    /// The derivation path from the master key, e.g. `84'/1'/0'/0/3`.
+    #[wasm_bindgen(getter)]
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    /// The hashes of the tap leaves the key is used in, empty for key spends and non-taproot keys.
+    #[wasm_bindgen(getter)]
+    pub fn leaf_hashes(&self) -> Vec<String> {
+        self.leaf_hashes.clone()
+    }
+}
+
+/// An ECDSA signature of a PSBT input.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PartialSignature {
+    public_key: String,
+    signature: String,
+    sighash_type: u32,
+}
+
+#[wasm_bindgen]
+impl PartialSignature {
+    /// The public key in hex.
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    /// The DER signature in hex, followed by the sighash byte.
+    #[wasm_bindgen(getter)]
+    pub fn signature(&self) -> String {
+        self.signature.clone()
+    }
+
+    /// The sighash type of the signature, as its consensus value.
+    #[wasm_bindgen(getter)]
+    pub fn sighash_type(&self) -> u32 {
+        self.sighash_type
+    }
+}
+
+/// The status of a PSBT input after signing or finalizing it with `Wallet::sign_owned_inputs`
+/// or `Wallet::finalize_psbt_inputs`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtInputStatus {
+    pub(crate) index: usize,
+    pub(crate) is_mine: bool,
+    pub(crate) signed: bool,
+    pub(crate) finalized: bool,
+    pub(crate) error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl PsbtInputStatus {
+    /// The index of the input in the PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Whether the input spends an output of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn is_mine(&self) -> bool {
+        self.is_mine
+    }
+
+    /// Whether the wallet added signatures to the input.
+    #[wasm_bindgen(getter)]
+    pub fn signed(&self) -> bool {
+        self.signed
+    }
+
+    /// Whether the input has its final scriptSig or witness.
+    #[wasm_bindgen(getter)]
+    pub fn finalized(&self) -> bool {
+        self.finalized
+    }
+
+    /// Why the input could not be finalized, e.g. missing signatures.
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
 /// A Transaction recipient
 #[wasm_bindgen]
 #[derive(Clone)]
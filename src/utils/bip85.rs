@@ -0,0 +1,56 @@
+use std::str::FromStr;
+
+use bdk_wallet::bitcoin::{bip32::Xpriv, hex::DisplayHex};
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+use crate::types::{Language, Mnemonic};
+
+use super::result::JsResult;
+
+/// Derive the BIP85 child mnemonic of `word_count` words (12, 18 or 24) at `index` from the
+/// root extended private key `xpriv`, e.g. from `seed_to_xpriv`.
+#[wasm_bindgen]
+pub fn bip85_mnemonic(xpriv: &str, word_count: u32, index: u32, language: Option<Language>) -> JsResult<Mnemonic> {
+    let xprv = Xpriv::from_str(xpriv)?;
+    let language = language.unwrap_or(Language::English);
+    let mnemonic = crate::bitcoin::derive_mnemonic(&xprv, language.into(), word_count, index)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+
+    Ok(mnemonic.into())
+}
+
+/// Derive the BIP85 child private key at `index`, WIF encoded.
+#[wasm_bindgen]
+pub fn bip85_wif(xpriv: &str, index: u32) -> JsResult<String> {
+    let xprv = Xpriv::from_str(xpriv)?;
+    let private_key = crate::bitcoin::derive_wif(&xprv, index).map_err(|e| JsError::new(&e.to_string()))?;
+
+    Ok(private_key.to_wif())
+}
+
+/// Derive the BIP85 child extended private key at `index`.
+#[wasm_bindgen]
+pub fn bip85_xpriv(xpriv: &str, index: u32) -> JsResult<String> {
+    let xprv = Xpriv::from_str(xpriv)?;
+    let child = crate::bitcoin::derive_xpriv(&xprv, index).map_err(|e| JsError::new(&e.to_string()))?;
+
+    Ok(child.to_string())
+}
+
+/// Derive `num_bytes` (16 to 64) bytes of BIP85 child entropy at `index`, hex encoded.
+#[wasm_bindgen]
+pub fn bip85_hex(xpriv: &str, num_bytes: u32, index: u32) -> JsResult<String> {
+    let xprv = Xpriv::from_str(xpriv)?;
+    let entropy = crate::bitcoin::derive_hex(&xprv, num_bytes, index).map_err(|e| JsError::new(&e.to_string()))?;
+
+    Ok(entropy.to_lower_hex_string())
+}
+
+/// Derive a BIP85 base64 password of `length` (20 to 86) characters at `index`.
+#[wasm_bindgen]
+pub fn bip85_password(xpriv: &str, length: u32, index: u32) -> JsResult<String> {
+    let xprv = Xpriv::from_str(xpriv)?;
+    let password = crate::bitcoin::derive_password(&xprv, length, index).map_err(|e| JsError::new(&e.to_string()))?;
+
+    Ok(password)
+}
@@ -1,13 +1,13 @@
 use std::str::FromStr;
 
 use bdk_wallet::{
-    bitcoin::bip32::{Fingerprint, Xpriv, Xpub},
+    bitcoin::bip32::{DerivationPath, Fingerprint, Xpriv, Xpub},
     keys::ExtendedKey,
 };
 use serde_wasm_bindgen::from_value;
 use wasm_bindgen::prelude::{wasm_bindgen, JsError, JsValue};
 
-use crate::types::{AddressType, Network, SLIP10Node};
+use crate::types::{AddressType, Mnemonic, Network, SLIP10Node};
 
 use super::result::JsResult;
 
@@ -39,9 +39,102 @@ impl DescriptorPair {
     }
 }
 
+/// Options of the descriptors built from a key
 #[wasm_bindgen]
-pub fn seed_to_descriptor(seed: &[u8], network: Network, address_type: AddressType) -> JsResult<DescriptorPair> {
-    let (external, internal) = crate::bitcoin::seed_to_descriptor(seed, network.into(), address_type.into())
+#[derive(Clone, Default)]
+pub struct DescriptorOptions {
+    legacy_p2sh: bool,
+    account: u32,
+    derivation_path: Option<String>,
+}
+
+#[wasm_bindgen]
+impl DescriptorOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        DescriptorOptions::default()
+    }
+
+    /// Build legacy P2SH descriptors, `sh(pk(...))`, for the `p2sh` address type instead of
+    /// BIP49 P2SH-wrapped P2WPKH ones.
+    #[wasm_bindgen(getter)]
+    pub fn legacy_p2sh(&self) -> bool {
+        self.legacy_p2sh
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_legacy_p2sh(&mut self, value: bool) {
+        self.legacy_p2sh = value;
+    }
+
+    /// Index of the BIP32 account, 0 by default.
+    #[wasm_bindgen(getter)]
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_account(&mut self, value: u32) {
+        self.account = value;
+    }
+
+    /// Derivation path of the account (e.g. `m/84'/0'/7'`), replacing the standard path of the
+    /// address type and account.
+    ///
+    /// It is required by the P2WSH and legacy P2SH address types, which have no standard path.
+    #[wasm_bindgen(getter)]
+    pub fn derivation_path(&self) -> Option<String> {
+        self.derivation_path.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_derivation_path(&mut self, value: Option<String>) {
+        self.derivation_path = value;
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// A `DescriptorOptions` read through its getters, which leaves it usable by the caller for
+    /// other calls, unlike passing it by value.
+    #[wasm_bindgen(typescript_type = "DescriptorOptions")]
+    pub type JsDescriptorOptions;
+
+    #[wasm_bindgen(method, getter)]
+    fn legacy_p2sh(this: &JsDescriptorOptions) -> bool;
+
+    #[wasm_bindgen(method, getter)]
+    fn account(this: &JsDescriptorOptions) -> u32;
+
+    #[wasm_bindgen(method, getter)]
+    fn derivation_path(this: &JsDescriptorOptions) -> Option<String>;
+}
+
+fn descriptor_options(options: Option<JsDescriptorOptions>) -> JsResult<crate::bitcoin::DescriptorOptions> {
+    let Some(options) = options else {
+        return Ok(crate::bitcoin::DescriptorOptions::default());
+    };
+
+    Ok(crate::bitcoin::DescriptorOptions {
+        legacy_p2sh: options.legacy_p2sh(),
+        account: options.account(),
+        derivation_path: options
+            .derivation_path()
+            .as_deref()
+            .map(DerivationPath::from_str)
+            .transpose()?,
+    })
+}
+
+#[wasm_bindgen]
+pub fn seed_to_descriptor(
+    seed: &[u8],
+    network: Network,
+    address_type: AddressType,
+    options: Option<JsDescriptorOptions>,
+) -> JsResult<DescriptorPair> {
+    let options = descriptor_options(options)?;
+    let (external, internal) = crate::bitcoin::seed_to_descriptor(seed, network.into(), address_type.into(), &options)
         .map_err(|e| JsError::new(&e.to_string()))?;
 
     Ok(DescriptorPair::new(
@@ -50,18 +143,32 @@ pub fn seed_to_descriptor(seed: &[u8], network: Network, address_type: AddressTy
     ))
 }
 
+/// Build the descriptors of a BIP39 mnemonic, protected by an optional `passphrase`.
+#[wasm_bindgen]
+pub fn mnemonic_to_descriptor(
+    mnemonic: &Mnemonic,
+    passphrase: Option<String>,
+    network: Network,
+    address_type: AddressType,
+    options: Option<JsDescriptorOptions>,
+) -> JsResult<DescriptorPair> {
+    seed_to_descriptor(&mnemonic.to_seed(passphrase), network, address_type, options)
+}
+
 #[wasm_bindgen]
 pub fn xpriv_to_descriptor(
     extended_privkey: &str,
     fingerprint: &str,
     network: Network,
     address_type: AddressType,
+    options: Option<JsDescriptorOptions>,
 ) -> JsResult<DescriptorPair> {
+    let options = descriptor_options(options)?;
     let xprv = Xpriv::from_str(extended_privkey)?;
     let fingerprint = Fingerprint::from_hex(fingerprint)?;
 
     let (external, internal) =
-        crate::bitcoin::xpriv_to_descriptor(xprv, fingerprint, network.into(), address_type.into())
+        crate::bitcoin::xpriv_to_descriptor(xprv, fingerprint, network.into(), address_type.into(), &options)
             .map_err(|e| JsError::new(&e.to_string()))?;
 
     Ok(DescriptorPair::new(
@@ -76,12 +183,14 @@ pub fn xpub_to_descriptor(
     fingerprint: &str,
     network: Network,
     address_type: AddressType,
+    options: Option<JsDescriptorOptions>,
 ) -> JsResult<DescriptorPair> {
+    let options = descriptor_options(options)?;
     let xpub = Xpub::from_str(extended_pubkey)?;
     let fingerprint = Fingerprint::from_hex(fingerprint)?;
 
     let (external, internal) =
-        crate::bitcoin::xpub_to_descriptor(xpub, fingerprint, network.into(), address_type.into())
+        crate::bitcoin::xpub_to_descriptor(xpub, fingerprint, network.into(), address_type.into(), &options)
             .map_err(|e| JsError::new(&e.to_string()))?;
 
     Ok(DescriptorPair::new(external.0.to_string(), internal.0.to_string()))
@@ -0,0 +1,16 @@
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{js_sys::Promise, Request, Response};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The global `fetch`, available in browsers, web workers and Node 18+.
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_with_request(request: &Request) -> Promise;
+}
+
+/// Send `request` with the global `fetch`, which unlike `Window::fetch` also works in web workers
+/// and Node.
+pub(crate) async fn fetch(request: &Request) -> Result<Response, JsValue> {
+    Ok(JsFuture::from(fetch_with_request(request)).await?.unchecked_into())
+}
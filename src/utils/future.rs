@@ -0,0 +1,22 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Wraps a future so it can be handed to BDK APIs that require `Send` futures.
+///
+/// JS-backed futures (`JsFuture`, closures holding `JsValue`s) are never `Send`.
+pub(crate) struct SendFuture<F>(pub F);
+
+impl<F: Future> Future for SendFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: we never move the inner future out of the pinned wrapper.
+        unsafe { self.map_unchecked_mut(|s| &mut s.0) }.poll(cx)
+    }
+}
+
+// SAFETY: Wasm is single-threaded; the value is never accessed concurrently.
+unsafe impl<F> Send for SendFuture<F> {}
@@ -1,9 +1,17 @@
+mod bip85;
 mod descriptor;
+#[cfg(any(feature = "rpc", feature = "cbf"))]
+pub(crate) mod fetch;
+pub(crate) mod future;
+pub(crate) mod psbt_v2;
+#[cfg(any(feature = "esplora", feature = "electrum"))]
+pub(crate) mod timer;
 
 #[cfg(feature = "debug")]
 mod panic_hook;
 pub mod result;
 
+pub use bip85::*;
 pub use descriptor::*;
 
 #[cfg(feature = "debug")]
@@ -0,0 +1,455 @@
+//! Encoding of version 2 PSBTs ([BIP 370]).
+//!
+//! `rust-bitcoin` only reads and writes version 0 PSBTs, which carry a global unsigned transaction.
+//! Version 2 PSBTs carry the transaction fields in the input and output maps instead. We keep them
+//! as a `bitcoin::Psbt` with `version = 2` and convert between both layouts at the key-value map
+//! level, so every field `rust-bitcoin` understands is decoded by `rust-bitcoin` itself.
+//!
+//! The fallback locktime becomes the locktime of the unsigned transaction when no input requires
+//! one. The version 2 fields without a version 0 counterpart (modifiable flags and required
+//! locktimes of inputs) are kept in the `unknown` maps of the PSBT.
+//!
+//! [BIP 370]: https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
+
+use bdk_wallet::bitcoin::{
+    absolute::LockTime,
+    consensus::encode::{deserialize, serialize, Decodable, VarInt},
+    psbt::{raw::Key, Input},
+    transaction::Version,
+    Amount, OutPoint, Psbt, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+use crate::types::{BdkError, BdkErrorCode};
+
+const MAGIC: &[u8] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const PSBT_GLOBAL_VERSION: u8 = 0xFB;
+
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0E;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0F;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+
+/// Bit of `PSBT_GLOBAL_TX_MODIFIABLE` allowing inputs to be added or removed.
+pub(crate) const INPUTS_MODIFIABLE: u8 = 0x01;
+/// Bit of `PSBT_GLOBAL_TX_MODIFIABLE` allowing outputs to be added or removed.
+pub(crate) const OUTPUTS_MODIFIABLE: u8 = 0x02;
+/// Bit of `PSBT_GLOBAL_TX_MODIFIABLE` set when an input has a `SIGHASH_SINGLE` signature.
+pub(crate) const HAS_SIGHASH_SINGLE: u8 = 0x04;
+
+const SIGHASH_ALL: u32 = 0x01;
+const SIGHASH_NONE: u32 = 0x02;
+const SIGHASH_SINGLE: u32 = 0x03;
+const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// A key-value pair of a PSBT map.
+struct Pair {
+    type_value: u8,
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl Pair {
+    fn new(type_value: u8, value: Vec<u8>) -> Self {
+        Pair {
+            type_value,
+            key: Vec::new(),
+            value,
+        }
+    }
+}
+
+type Map = Vec<Pair>;
+
+/// Reads the PSBT version from the global map of an encoded PSBT.
+pub(crate) fn version(bytes: &[u8]) -> Result<u32, BdkError> {
+    let mut data = strip_magic(bytes)?;
+    let global = read_map(&mut data)?;
+    get(&global, PSBT_GLOBAL_VERSION)
+        .map(|value| decode_value::<u32>(value, "version"))
+        .unwrap_or(Ok(0))
+}
+
+/// Decodes a version 2 PSBT.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Psbt, BdkError> {
+    let mut data = strip_magic(bytes)?;
+    let global = read_map(&mut data)?;
+
+    let tx_version = required::<i32>(&global, PSBT_GLOBAL_TX_VERSION, "transaction version")?;
+    let input_count = required::<VarInt>(&global, PSBT_GLOBAL_INPUT_COUNT, "input count")?.0;
+    let output_count = required::<VarInt>(&global, PSBT_GLOBAL_OUTPUT_COUNT, "output count")?.0;
+    let fallback_locktime = get(&global, PSBT_GLOBAL_FALLBACK_LOCKTIME)
+        .map(|value| decode_value::<u32>(value, "fallback locktime"))
+        .transpose()?;
+
+    let inputs = (0..input_count)
+        .map(|_| read_map(&mut data))
+        .collect::<Result<Vec<_>, _>>()?;
+    let outputs = (0..output_count)
+        .map(|_| read_map(&mut data))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let unsigned_tx = Transaction {
+        version: Version(tx_version),
+        lock_time: lock_time(fallback_locktime, &inputs)?,
+        input: inputs.iter().map(tx_in).collect::<Result<_, _>>()?,
+        output: outputs.iter().map(tx_out).collect::<Result<_, _>>()?,
+    };
+
+    // Rebuild the same PSBT in the version 0 layout for `rust-bitcoin` to decode.
+    let mut v0 = MAGIC.to_vec();
+    let mut global_v0 = vec![Pair::new(PSBT_GLOBAL_UNSIGNED_TX, serialize(&unsigned_tx))];
+    global_v0.extend(global.into_iter().filter(|pair| {
+        ![
+            PSBT_GLOBAL_TX_VERSION,
+            PSBT_GLOBAL_FALLBACK_LOCKTIME,
+            PSBT_GLOBAL_INPUT_COUNT,
+            PSBT_GLOBAL_OUTPUT_COUNT,
+            PSBT_GLOBAL_VERSION,
+        ]
+        .contains(&pair.type_value)
+    }));
+    write_map(&mut v0, global_v0);
+    for input in inputs {
+        write_map(
+            &mut v0,
+            input
+                .into_iter()
+                .filter(|pair| {
+                    ![PSBT_IN_PREVIOUS_TXID, PSBT_IN_OUTPUT_INDEX, PSBT_IN_SEQUENCE].contains(&pair.type_value)
+                })
+                .collect(),
+        );
+    }
+    for output in outputs {
+        write_map(
+            &mut v0,
+            output
+                .into_iter()
+                .filter(|pair| ![PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT].contains(&pair.type_value))
+                .collect(),
+        );
+    }
+
+    let mut psbt = Psbt::deserialize(&v0).map_err(|e| encoding_error(e.to_string()))?;
+    psbt.version = 2;
+    Ok(psbt)
+}
+
+/// Encodes a PSBT in the version 2 layout.
+pub(crate) fn encode(psbt: &Psbt) -> Vec<u8> {
+    let mut v0 = psbt.clone();
+    v0.version = 0;
+    let bytes = v0.serialize();
+
+    let tx = &psbt.unsigned_tx;
+    let mut data = &bytes[MAGIC.len()..];
+    let global = read_map(&mut data).expect("rust-bitcoin encodes valid maps");
+    let mut read_maps = |count: usize| -> Vec<Map> {
+        (0..count)
+            .map(|_| read_map(&mut data).expect("rust-bitcoin encodes valid maps"))
+            .collect()
+    };
+    let inputs = read_maps(tx.input.len());
+    let outputs = read_maps(tx.output.len());
+
+    let has_required_locktime = psbt.inputs.iter().any(|input| {
+        input.unknown.keys().any(|key| {
+            key.type_value == PSBT_IN_REQUIRED_TIME_LOCKTIME || key.type_value == PSBT_IN_REQUIRED_HEIGHT_LOCKTIME
+        })
+    });
+
+    let mut global_v2: Map = global
+        .into_iter()
+        .filter(|pair| pair.type_value != PSBT_GLOBAL_UNSIGNED_TX)
+        .collect();
+    global_v2.push(Pair::new(PSBT_GLOBAL_TX_VERSION, serialize(&tx.version)));
+    if !has_required_locktime && tx.lock_time != LockTime::ZERO {
+        global_v2.push(Pair::new(PSBT_GLOBAL_FALLBACK_LOCKTIME, serialize(&tx.lock_time)));
+    }
+    global_v2.push(Pair::new(
+        PSBT_GLOBAL_INPUT_COUNT,
+        serialize(&VarInt(tx.input.len() as u64)),
+    ));
+    global_v2.push(Pair::new(
+        PSBT_GLOBAL_OUTPUT_COUNT,
+        serialize(&VarInt(tx.output.len() as u64)),
+    ));
+    global_v2.push(Pair::new(PSBT_GLOBAL_VERSION, serialize(&2u32)));
+
+    let mut v2 = MAGIC.to_vec();
+    write_map(&mut v2, global_v2);
+    for (mut input, txin) in inputs.into_iter().zip(&tx.input) {
+        input.push(Pair::new(PSBT_IN_PREVIOUS_TXID, serialize(&txin.previous_output.txid)));
+        input.push(Pair::new(PSBT_IN_OUTPUT_INDEX, serialize(&txin.previous_output.vout)));
+        input.push(Pair::new(PSBT_IN_SEQUENCE, serialize(&txin.sequence)));
+        write_map(&mut v2, input);
+    }
+    for (mut output, txout) in outputs.into_iter().zip(&tx.output) {
+        output.push(Pair::new(PSBT_OUT_AMOUNT, serialize(&txout.value.to_sat())));
+        output.push(Pair::new(PSBT_OUT_SCRIPT, txout.script_pubkey.to_bytes()));
+        write_map(&mut v2, output);
+    }
+    v2
+}
+
+/// Removes the fields only allowed in version 2 PSBTs, once the PSBT is turned into a version 0 one.
+pub(crate) fn strip_v2_fields(psbt: &mut Psbt) {
+    psbt.unknown
+        .retain(|key, _| key.type_value != PSBT_GLOBAL_TX_MODIFIABLE);
+    for input in &mut psbt.inputs {
+        input.unknown.retain(|key, _| {
+            ![PSBT_IN_REQUIRED_TIME_LOCKTIME, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME].contains(&key.type_value)
+        });
+    }
+}
+
+/// The `PSBT_GLOBAL_TX_MODIFIABLE` flags of a PSBT, `0` when absent.
+pub(crate) fn tx_modifiable(psbt: &Psbt) -> u8 {
+    psbt.unknown
+        .get(&tx_modifiable_key())
+        .and_then(|value| value.first().copied())
+        .unwrap_or(0)
+}
+
+pub(crate) fn set_tx_modifiable(psbt: &mut Psbt, flags: u8) {
+    psbt.unknown.insert(tx_modifiable_key(), vec![flags]);
+}
+
+/// Clears the modifiable flags of a version 2 PSBT that its signatures forbid and sets its
+/// `HAS_SIGHASH_SINGLE` flag, as signers do.
+pub(crate) fn update_tx_modifiable(psbt: &mut Psbt) {
+    if psbt.version != 2 {
+        return;
+    }
+
+    let current = tx_modifiable(psbt);
+    let mut flags = current;
+    for sighash in psbt.inputs.iter().flat_map(sighash_types) {
+        if sighash & SIGHASH_ANYONECANPAY == 0 {
+            flags &= !INPUTS_MODIFIABLE;
+        }
+        match sighash & !SIGHASH_ANYONECANPAY {
+            SIGHASH_NONE => {}
+            SIGHASH_SINGLE => flags |= HAS_SIGHASH_SINGLE,
+            _ => flags &= !OUTPUTS_MODIFIABLE,
+        }
+    }
+    if flags != current {
+        set_tx_modifiable(psbt, flags);
+    }
+}
+
+/// Fails if adding an input would invalidate a signature of the PSBT, i.e. one that is not
+/// `SIGHASH_ANYONECANPAY`.
+pub(crate) fn check_input_addition(psbt: &Psbt) -> Result<(), BdkError> {
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        if sighash_types(input).any(|sighash| sighash & SIGHASH_ANYONECANPAY == 0) {
+            return Err(not_modifiable(format!(
+                "A signature of input {index} commits to all the inputs of this PSBT"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails if adding an output would invalidate a signature of the PSBT: one committing to all
+/// the outputs, or a `SIGHASH_SINGLE` one of the input paired with the new output.
+pub(crate) fn check_output_addition(psbt: &Psbt) -> Result<(), BdkError> {
+    let new_index = psbt.outputs.len();
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        for sighash in sighash_types(input) {
+            match sighash & !SIGHASH_ANYONECANPAY {
+                SIGHASH_NONE => {}
+                SIGHASH_SINGLE if index != new_index => {}
+                SIGHASH_SINGLE => {
+                    return Err(not_modifiable(format!(
+                        "The SIGHASH_SINGLE signature of input {index} commits to the output added at its index"
+                    )))
+                }
+                _ => {
+                    return Err(not_modifiable(format!(
+                        "A signature of input {index} commits to all the outputs of this PSBT"
+                    )))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The sighash types of the signatures of an input. Finalized inputs, whose signatures are no
+/// longer told apart, count as signed with `SIGHASH_ALL`.
+fn sighash_types(input: &Input) -> Box<dyn Iterator<Item = u32> + '_> {
+    if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+        return Box::new(std::iter::once(SIGHASH_ALL));
+    }
+
+    Box::new(
+        input
+            .partial_sigs
+            .values()
+            .map(|sig| sig.sighash_type.to_u32())
+            .chain(input.tap_key_sig.iter().map(|sig| sig.sighash_type as u32))
+            .chain(input.tap_script_sigs.values().map(|sig| sig.sighash_type as u32)),
+    )
+}
+
+fn not_modifiable(message: String) -> BdkError {
+    BdkError::new(BdkErrorCode::PsbtNotModifiable, message, ())
+}
+
+fn tx_modifiable_key() -> Key {
+    Key {
+        type_value: PSBT_GLOBAL_TX_MODIFIABLE,
+        key: Vec::new(),
+    }
+}
+
+/// Computes the locktime of the transaction from the required locktimes of its inputs, as per BIP 370.
+fn lock_time(fallback: Option<u32>, inputs: &[Map]) -> Result<LockTime, BdkError> {
+    let mut height = None;
+    let mut time = None;
+    let mut height_possible = true;
+    let mut time_possible = true;
+
+    for input in inputs {
+        let required_height = get(input, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME)
+            .map(|value| decode_value::<u32>(value, "required height locktime"))
+            .transpose()?;
+        let required_time = get(input, PSBT_IN_REQUIRED_TIME_LOCKTIME)
+            .map(|value| decode_value::<u32>(value, "required time locktime"))
+            .transpose()?;
+        if required_height.is_none() && required_time.is_none() {
+            continue;
+        }
+
+        match required_height {
+            Some(value) => height = height.max(Some(value)),
+            None => height_possible = false,
+        }
+        match required_time {
+            Some(value) => time = time.max(Some(value)),
+            None => time_possible = false,
+        }
+    }
+
+    match (height, time) {
+        (None, None) => Ok(LockTime::from_consensus(fallback.unwrap_or(0))),
+        (Some(height), _) if height_possible => {
+            LockTime::from_height(height).map_err(|e| encoding_error(format!("invalid required height locktime: {e}")))
+        }
+        (_, Some(time)) if time_possible => {
+            LockTime::from_time(time).map_err(|e| encoding_error(format!("invalid required time locktime: {e}")))
+        }
+        _ => Err(encoding_error("inputs require both height and time locktimes")),
+    }
+}
+
+fn tx_in(input: &Map) -> Result<TxIn, BdkError> {
+    let txid = required::<Txid>(input, PSBT_IN_PREVIOUS_TXID, "input previous txid")?;
+    let vout = required::<u32>(input, PSBT_IN_OUTPUT_INDEX, "input output index")?;
+    let sequence = get(input, PSBT_IN_SEQUENCE)
+        .map(|value| decode_value::<Sequence>(value, "input sequence"))
+        .transpose()?
+        .unwrap_or(Sequence::MAX);
+
+    Ok(TxIn {
+        previous_output: OutPoint::new(txid, vout),
+        script_sig: ScriptBuf::new(),
+        sequence,
+        witness: Witness::new(),
+    })
+}
+
+fn tx_out(output: &Map) -> Result<TxOut, BdkError> {
+    let value = required::<u64>(output, PSBT_OUT_AMOUNT, "output amount")?;
+    let script_pubkey = get(output, PSBT_OUT_SCRIPT)
+        .ok_or_else(|| encoding_error("missing output script"))?
+        .clone();
+
+    Ok(TxOut {
+        value: Amount::from_sat(value),
+        script_pubkey: ScriptBuf::from_bytes(script_pubkey),
+    })
+}
+
+fn strip_magic(bytes: &[u8]) -> Result<&[u8], BdkError> {
+    bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| encoding_error("invalid PSBT magic bytes"))
+}
+
+/// Reads a map up to its separator. Keys are `<keylen> <keytype> <keydata>`, values `<valuelen> <value>`.
+fn read_map(data: &mut &[u8]) -> Result<Map, BdkError> {
+    let mut map = Map::new();
+    loop {
+        let key = read_bytes(data)?;
+        if key.is_empty() {
+            return Ok(map);
+        }
+
+        let mut key = key.as_slice();
+        let type_value = VarInt::consensus_decode(&mut key).map_err(|e| encoding_error(e.to_string()))?;
+        let type_value = u8::try_from(type_value.0).map_err(|_| encoding_error("unsupported key type"))?;
+        let value = read_bytes(data)?;
+        map.push(Pair {
+            type_value,
+            key: key.to_vec(),
+            value,
+        });
+    }
+}
+
+fn read_bytes(data: &mut &[u8]) -> Result<Vec<u8>, BdkError> {
+    let len = VarInt::consensus_decode(data)
+        .map_err(|e| encoding_error(e.to_string()))?
+        .0 as usize;
+    if data.len() < len {
+        return Err(encoding_error("unexpected end of PSBT data"));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Writes a map sorted by key, followed by its separator.
+fn write_map(out: &mut Vec<u8>, mut map: Map) {
+    map.sort_by(|a, b| (a.type_value, &a.key).cmp(&(b.type_value, &b.key)));
+    for pair in map {
+        let mut key = serialize(&VarInt(pair.type_value as u64));
+        key.extend(pair.key);
+        out.extend(serialize(&key));
+        out.extend(serialize(&pair.value));
+    }
+    out.push(0x00);
+}
+
+fn get(map: &Map, type_value: u8) -> Option<&Vec<u8>> {
+    map.iter()
+        .find(|pair| pair.type_value == type_value && pair.key.is_empty())
+        .map(|pair| &pair.value)
+}
+
+fn required<T: Decodable>(map: &Map, type_value: u8, name: &str) -> Result<T, BdkError> {
+    let value = get(map, type_value).ok_or_else(|| encoding_error(format!("missing {name}")))?;
+    decode_value(value, name)
+}
+
+fn decode_value<T: Decodable>(value: &[u8], name: &str) -> Result<T, BdkError> {
+    deserialize(value).map_err(|e| encoding_error(format!("invalid {name}: {e}")))
+}
+
+fn encoding_error(message: impl Into<String>) -> BdkError {
+    BdkError::new(BdkErrorCode::Psbt, message, ())
+}
@@ -0,0 +1,69 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use wasm_bindgen::{
+    prelude::{wasm_bindgen, Closure},
+    JsCast, JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::js_sys::{Function, Promise};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The global `setTimeout`, available in browsers, web workers and Node. Node returns a
+    /// `Timeout` object instead of a number, so the handle is kept opaque.
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(callback: &Function, ms: i32) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(handle: &JsValue);
+}
+
+pub(crate) struct WebSleep {
+    future: JsFuture,
+    handle: JsValue,
+    // Kept alive until the timer is cleared, so it is never called after being dropped.
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl Future for WebSleep {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // delegate to the inner JsFuture
+        Pin::new(&mut self.get_mut().future).poll(cx).map(|_| ())
+    }
+}
+
+impl Drop for WebSleep {
+    fn drop(&mut self) {
+        // A pending timer would keep Node's event loop alive after a timeout won the race.
+        clear_timeout(&self.handle);
+    }
+}
+
+// SAFETY: Wasm is single-threaded; the value is never accessed concurrently.
+unsafe impl Send for WebSleep {}
+
+/// Complete after `dur`, with the global `setTimeout`. Dropping the future clears the timer.
+pub(crate) fn sleep(dur: Duration) -> WebSleep {
+    let ms = dur.as_millis().min(i32::MAX as u128) as i32;
+    let mut timer = None;
+    let promise = Promise::new(&mut |resolve, _reject| {
+        let callback = Closure::once(move || {
+            let _ = resolve.call0(&JsValue::NULL);
+        });
+        let handle = set_timeout(callback.as_ref().unchecked_ref(), ms);
+        timer = Some((handle, callback));
+    });
+    // The executor of `Promise::new` runs synchronously.
+    let (handle, callback) = timer.expect("promise executor has run");
+    WebSleep {
+        future: JsFuture::from(promise),
+        handle,
+        _callback: callback,
+    }
+}
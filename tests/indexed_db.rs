@@ -0,0 +1,63 @@
+//! Test suite for the IndexedDB wallet store.
+
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use bitcoindevkit::{
+    bitcoin::{IndexedDbStore, Wallet},
+    types::{BdkErrorCode, KeychainKind, Network},
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const DB_NAME: &str = "bdk-wasm-test-wallet";
+const EXTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPe2qpAuh1K1Hig72LCoP4JgNxZM2ZRWHZYnpuw5oHoGBsQm7Qb8mLgPpRJVn3hceWgGQRNbPD6x1pp2Qme2YFRAPeYh7vmvE/84'/1'/0'/0/*)#a6kgzlgq";
+const INTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPe2qpAuh1K1Hig72LCoP4JgNxZM2ZRWHZYnpuw5oHoGBsQm7Qb8mLgPpRJVn3hceWgGQRNbPD6x1pp2Qme2YFRAPeYh7vmvE/84'/1'/0'/1/*)#vwnfl2cc";
+
+#[wasm_bindgen_test]
+async fn test_indexed_db_store() {
+    IndexedDbStore::delete(DB_NAME.into())
+        .await
+        .map_err(|e| e.message())
+        .expect("delete");
+
+    let store = IndexedDbStore::new(DB_NAME.into());
+    let empty = Wallet::load_with_store(store, None, None)
+        .await
+        .map_err(|e| e.message())
+        .expect("load empty store");
+    assert!(empty.is_none());
+
+    let wallet = Wallet::create_with_store(
+        IndexedDbStore::new(DB_NAME.into()),
+        Network::Testnet,
+        EXTERNAL_DESC.into(),
+        INTERNAL_DESC.into(),
+    )
+    .await
+    .map_err(|e| e.message())
+    .expect("create");
+
+    wallet.reveal_addresses_to(KeychainKind::External, 4);
+    wallet.reveal_next_address(KeychainKind::Internal);
+    wallet.persist().await.map_err(|e| e.message()).expect("persist");
+
+    let loaded = Wallet::load_with_store(IndexedDbStore::new(DB_NAME.into()), None, None)
+        .await
+        .map_err(|e| e.message())
+        .expect("load")
+        .expect("wallet data");
+    assert_eq!(loaded.derivation_index(KeychainKind::External), Some(4));
+    assert_eq!(loaded.derivation_index(KeychainKind::Internal), Some(0));
+
+    let result = Wallet::create_with_store(
+        IndexedDbStore::new(DB_NAME.into()),
+        Network::Testnet,
+        EXTERNAL_DESC.into(),
+        INTERNAL_DESC.into(),
+    )
+    .await;
+    assert!(matches!(result, Err(e) if matches!(e.code(), BdkErrorCode::DataAlreadyExists)));
+}
@@ -0,0 +1,197 @@
+import { execSync } from "child_process";
+import { createHash } from "crypto";
+import { createServer } from "http";
+import { AddressInfo } from "net";
+import {
+  Amount,
+  BdkError,
+  BdkErrorCode,
+  BitcoindRpcClient,
+  Recipient,
+  SignOptions,
+  FeeRate,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+
+// Requires a regtest Bitcoin Core node. Set BITCOIND_RPC_URL, BITCOIND_RPC_USER and
+// BITCOIND_RPC_PASSWORD to run the sync tests, and BITCOIND_CLI to the command used to
+// mine blocks (defaults to the Esplora regtest container).
+const rpcUrl = process.env.BITCOIND_RPC_URL;
+const rpcUser = process.env.BITCOIND_RPC_USER;
+const rpcPassword = process.env.BITCOIND_RPC_PASSWORD;
+const cli = process.env.BITCOIND_CLI || "docker exec esplora-regtest cli -regtest";
+
+const describeRpc = rpcUrl ? describe : describe.skip;
+
+function bitcoinCli(command: string): string {
+  return execSync(`${cli} ${command}`, { encoding: "utf-8" }).trim();
+}
+
+describe("Bitcoin Core RPC client", () => {
+  it("fails when the node is unreachable", async () => {
+    const client = new BitcoindRpcClient("http://127.0.0.1:1");
+    const wallet = Wallet.create(
+      "regtest",
+      "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/0/*)",
+      "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/1/*)"
+    );
+
+    try {
+      await client.sync_blocks(wallet);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.BitcoindRpc);
+    }
+  });
+
+  it("returns the events of the blocks applied before a failure", async () => {
+    const sha256d = (data: Buffer) =>
+      createHash("sha256")
+        .update(createHash("sha256").update(data).digest())
+        .digest();
+    // Headers of empty blocks, without proof of work, which the client does not check.
+    const header = (prev: Buffer, time: number) => {
+      const header = Buffer.alloc(80);
+      header.writeInt32LE(0x20000000, 0);
+      prev.copy(header, 4);
+      header.writeUInt32LE(time, 68);
+      header.writeUInt32LE(0x207fffff, 72);
+      return header;
+    };
+    const genesisHash =
+      "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206";
+    const first = header(
+      Buffer.from(genesisHash, "hex").reverse(),
+      1296688603
+    );
+    const second = header(sha256d(first), 1296688604);
+    const blocks = [first, second].map((header) => ({
+      hash: Buffer.from(sha256d(header)).reverse().toString("hex"),
+      hex: header.toString("hex") + "00",
+    }));
+
+    // Serves a chain of 2 blocks, failing to return the second one until `failing` is unset.
+    let failing = true;
+    const server = createServer((req, res) => {
+      let body = "";
+      req.on("data", (chunk) => (body += chunk));
+      req.on("end", () => {
+        const { id, method, params } = JSON.parse(body);
+        const reply = (result: unknown, error: unknown = null) =>
+          res.end(JSON.stringify({ id, result, error }));
+        if (method === "getblockcount") {
+          reply(2);
+        } else if (method === "getblockhash") {
+          reply(params[0] === 0 ? genesisHash : blocks[params[0] - 1].hash);
+        } else if (params[0] === blocks[1].hash && failing) {
+          reply(null, { code: -1, message: "Block not available" });
+        } else {
+          reply(blocks.find((block) => block.hash === params[0])!.hex);
+        }
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, "127.0.0.1", resolve));
+    const { port } = server.address() as AddressInfo;
+
+    try {
+      const client = new BitcoindRpcClient(`http://127.0.0.1:${port}`);
+      const wallet = Wallet.create(
+        "regtest",
+        "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/0/*)",
+        "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/1/*)"
+      );
+
+      try {
+        await client.sync_blocks(wallet);
+        fail("expected an error");
+      } catch (error) {
+        expect(error).toBeInstanceOf(BdkError);
+        expect(error.message).toContain("Block not available");
+      }
+      expect(wallet.latest_checkpoint.height).toBe(1);
+
+      failing = false;
+      const events = await client.sync_blocks(wallet);
+      expect(wallet.latest_checkpoint.height).toBe(2);
+      const tips = events
+        .filter((event) => event.kind === "chain_tip_changed")
+        .map((event) => event.new_tip!.height);
+      expect(tips).toEqual([1, 2]);
+    } finally {
+      server.close();
+    }
+  });
+});
+
+// Tests are expected to run in order
+describeRpc("Bitcoin Core RPC client (regtest)", () => {
+  const externalDescriptor =
+    "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/0/*)#jjcsy5wd";
+  const internalDescriptor =
+    "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/1/*)#rxa3ep74";
+
+  const client = new BitcoindRpcClient(rpcUrl!, rpcUser, rpcPassword);
+  let wallet: Wallet;
+
+  beforeAll(() => {
+    wallet = Wallet.create("regtest", externalDescriptor, internalDescriptor);
+    wallet.reveal_addresses_to("external", 5);
+  });
+
+  it("syncs blocks up to the node's tip", async () => {
+    const tipHeight = parseInt(bitcoinCli("getblockcount"), 10);
+
+    const events = await client.sync_blocks(wallet);
+
+    expect(events.length).toBeGreaterThan(0);
+    expect(wallet.latest_checkpoint.height).toBe(tipHeight);
+    expect(wallet.latest_checkpoint.hash).toBe(
+      bitcoinCli(`getblockhash ${tipHeight}`)
+    );
+    expect(wallet.balance.confirmed.to_sat()).toBeGreaterThan(0);
+  }, 120000);
+
+  it("applies only new blocks on the next sync", async () => {
+    const height = wallet.latest_checkpoint.height;
+    bitcoinCli(`generatetoaddress 2 ${bitcoinCli("getnewaddress")}`);
+
+    await client.sync_blocks(wallet);
+
+    expect(wallet.latest_checkpoint.height).toBe(height + 2);
+  }, 30000);
+
+  it("picks up mempool transactions and their confirmation", async () => {
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .add_recipient(
+        new Recipient(
+          wallet.peek_address("external", 3).address.script_pubkey,
+          Amount.from_sat(BigInt(10000))
+        )
+      )
+      .finish();
+    wallet.sign(psbt, new SignOptions());
+    const tx = psbt.extract_tx();
+    const txid = tx.compute_txid();
+
+    await client.broadcast(tx);
+    await client.sync(wallet);
+    expect(wallet.get_tx(txid)!.chain_position.is_confirmed).toBe(false);
+
+    // Transactions already downloaded by the client are applied to other wallets too.
+    const other = Wallet.create(
+      "regtest",
+      externalDescriptor,
+      internalDescriptor
+    );
+    other.reveal_addresses_to("external", 5);
+    await client.sync(other, wallet.latest_checkpoint.height);
+    expect(other.get_tx(txid)!.chain_position.is_confirmed).toBe(false);
+
+    bitcoinCli(`generatetoaddress 1 ${bitcoinCli("getnewaddress")}`);
+    await client.sync(wallet);
+    expect(wallet.get_tx(txid)!.chain_position.is_confirmed).toBe(true);
+  }, 30000);
+});
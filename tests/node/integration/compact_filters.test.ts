@@ -0,0 +1,172 @@
+import { execSync } from "child_process";
+import { createHash } from "crypto";
+import { createServer } from "http";
+import {
+  BdkError,
+  BdkErrorCode,
+  CompactFiltersClient,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+
+// Requires a regtest Bitcoin Core node running with `-rest -blockfilterindex`. Set CBF_REST_URL
+// (e.g. http://localhost:18443/rest) to run the sync tests, and BITCOIND_CLI to the command used
+// to query the node (defaults to the Esplora regtest container).
+const restUrl = process.env.CBF_REST_URL;
+const cli = process.env.BITCOIND_CLI || "docker exec esplora-regtest cli -regtest";
+
+const describeCbf = restUrl ? describe : describe.skip;
+
+const externalDescriptor =
+  "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/0/*)#jjcsy5wd";
+const internalDescriptor =
+  "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/1/*)#rxa3ep74";
+
+function bitcoinCli(command: string): string {
+  return execSync(`${cli} ${command}`, { encoding: "utf-8" }).trim();
+}
+
+const sha256d = (data: Buffer) =>
+  createHash("sha256")
+    .update(createHash("sha256").update(data).digest())
+    .digest();
+// Block hashes are displayed in the reverse order of their bytes.
+const displayHash = (header: Buffer) =>
+  Buffer.from(sha256d(header)).reverse().toString("hex");
+
+function header(
+  version: number,
+  prev: Buffer,
+  merkleRoot: Buffer,
+  time: number,
+  bits: number,
+  nonce: number
+): Buffer {
+  const header = Buffer.alloc(80);
+  header.writeInt32LE(version, 0);
+  prev.copy(header, 4);
+  merkleRoot.copy(header, 36);
+  header.writeUInt32LE(time, 68);
+  header.writeUInt32LE(bits, 72);
+  header.writeUInt32LE(nonce, 76);
+  return header;
+}
+
+// Mine a header on top of `prev` at the (regtest) difficulty of `bits`.
+function mine(prev: Buffer, time: number, bits: number): Buffer {
+  for (let nonce = 0; ; nonce++) {
+    const mined = header(
+      0x20000000,
+      sha256d(prev),
+      Buffer.alloc(32),
+      time,
+      bits,
+      nonce
+    );
+    if (sha256d(mined)[31] < 0x7f) {
+      return mined;
+    }
+  }
+}
+
+describe("Compact filters client", () => {
+  it("fails when the node is unreachable", async () => {
+    const client = new CompactFiltersClient("http://127.0.0.1:1/rest");
+    const wallet = Wallet.create("regtest", externalDescriptor, internalDescriptor);
+
+    try {
+      await client.sync(wallet);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.CompactFilters);
+    }
+  });
+
+  it("rejects headers changing the difficulty outside of retargets", async () => {
+    const genesis = header(
+      1,
+      Buffer.alloc(32),
+      Buffer.from(
+        "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+        "hex"
+      ).reverse(),
+      1296688602,
+      0x207fffff,
+      2
+    );
+    const first = mine(genesis, 1296688603, 0x207fffff);
+    const second = mine(first, 1296688604, 0x207ffffe);
+
+    // Serves a chain of valid proof of work whose second block lowers the target.
+    const routes: Record<string, string | Buffer> = {
+      "/rest/chaininfo.json": JSON.stringify({ blocks: 2 }),
+      "/rest/blockhashbyheight/0.json": JSON.stringify({
+        blockhash: displayHash(genesis),
+      }),
+      [`/rest/headers/${displayHash(genesis)}.bin?count=3`]: Buffer.concat([
+        genesis,
+        first,
+        second,
+      ]),
+    };
+    const server = createServer((request, response) => {
+      const body = routes[request.url!];
+      response.writeHead(body === undefined ? 404 : 200);
+      response.end(body);
+    });
+    await new Promise<void>((resolve) =>
+      server.listen(0, "127.0.0.1", resolve)
+    );
+    const { port } = server.address() as { port: number };
+
+    const client = new CompactFiltersClient(`http://127.0.0.1:${port}/rest`);
+    const wallet = Wallet.create("regtest", externalDescriptor, internalDescriptor);
+    try {
+      await client.sync(wallet);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.CompactFilters);
+      expect(error.message).toContain("difficulty of the header at height 2");
+    } finally {
+      server.close();
+    }
+    expect(wallet.latest_checkpoint.height).toBe(0);
+  });
+});
+
+// Tests are expected to run in order
+describeCbf("Compact filters client (regtest)", () => {
+  const client = new CompactFiltersClient(restUrl!);
+  let wallet: Wallet;
+
+  beforeAll(() => {
+    wallet = Wallet.create("regtest", externalDescriptor, internalDescriptor);
+  });
+
+  it("applies the matching blocks up to the node's tip", async () => {
+    const tipHeight = parseInt(bitcoinCli("getblockcount"), 10);
+
+    await client.sync(wallet);
+
+    expect(wallet.latest_checkpoint.height).toBe(tipHeight);
+    expect(wallet.latest_checkpoint.hash).toBe(
+      bitcoinCli(`getblockhash ${tipHeight}`)
+    );
+    expect(wallet.balance.confirmed.to_sat()).toBeGreaterThan(0);
+
+    // Only the blocks with wallet transactions and the tip were applied.
+    expect(wallet.checkpoints().length).toBeLessThan(tipHeight);
+  }, 120000);
+
+  it("only moves the tip when new blocks do not match", async () => {
+    const balance = wallet.balance.total.to_sat();
+    const height = wallet.latest_checkpoint.height;
+    bitcoinCli(`generatetoaddress 3 ${bitcoinCli("getnewaddress")}`);
+
+    await client.sync(wallet);
+
+    expect(wallet.latest_checkpoint.height).toBe(height + 3);
+    expect(wallet.balance.total.to_sat()).toBe(balance);
+  }, 30000);
+});
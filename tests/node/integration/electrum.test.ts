@@ -0,0 +1,232 @@
+import { createHash } from "crypto";
+import { createServer } from "http";
+import type { Socket } from "net";
+import {
+  BdkError,
+  BdkErrorCode,
+  ElectrumClient,
+  Network,
+  Txid,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+
+// Requires an Electrum server reachable over WebSocket, e.g. Fulcrum or electrs behind websockify.
+// Set ELECTRUM_URL (and NETWORK) to run the scanning tests.
+const network: Network = (process.env.NETWORK as Network) || "signet";
+const electrumUrl = process.env.ELECTRUM_URL;
+
+const describeElectrum = electrumUrl ? describe : describe.skip;
+
+type Respond = (request: { id: number; method: string }) => string | undefined;
+
+// A minimal WebSocket server answering each Electrum request with `respond`, if it returns a line.
+async function fakeServer(
+  respond: Respond
+): Promise<{ url: string; close: () => void }> {
+  const sockets: Socket[] = [];
+  const server = createServer();
+  server.on("upgrade", (request, socket: Socket) => {
+    sockets.push(socket);
+    const accept = createHash("sha1")
+      .update(
+        `${request.headers["sec-websocket-key"]}258EAFA5-E914-47DA-95CA-C5AB0DC85B11`
+      )
+      .digest("base64");
+    socket.write(
+      "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n" +
+        `Connection: Upgrade\r\nSec-WebSocket-Accept: ${accept}\r\n\r\n`
+    );
+
+    let buffer = Buffer.alloc(0);
+    socket.on("data", (chunk: Buffer) => {
+      buffer = Buffer.concat([buffer, chunk]);
+      while (buffer.length >= 2) {
+        // Client frames are masked, with a 7 or 16 bit length for our requests.
+        let length = buffer[1] & 0x7f;
+        let offset = 2;
+        if (length === 126) {
+          if (buffer.length < 4) return;
+          length = buffer.readUInt16BE(2);
+          offset = 4;
+        }
+        if (buffer.length < offset + 4 + length) return;
+
+        const mask = buffer.subarray(offset, offset + 4);
+        const payload = Buffer.from(
+          buffer
+            .subarray(offset + 4, offset + 4 + length)
+            .map((byte, i) => byte ^ mask[i % 4])
+        );
+        const text = (buffer[0] & 0x0f) === 1;
+        buffer = buffer.subarray(offset + 4 + length);
+        if (!text) continue;
+
+        const reply = respond(JSON.parse(payload.toString()));
+        if (reply !== undefined) {
+          const data = Buffer.from(reply);
+          const header =
+            data.length < 126
+              ? Buffer.from([0x81, data.length])
+              : Buffer.from([0x81, 126, data.length >> 8, data.length & 0xff]);
+          socket.write(Buffer.concat([header, data]));
+        }
+      }
+    });
+  });
+
+  await new Promise<void>((resolve) =>
+    server.listen(0, "127.0.0.1", resolve)
+  );
+  const { port } = server.address() as { port: number };
+  return {
+    url: `ws://127.0.0.1:${port}`,
+    close: () => {
+      sockets.forEach((socket) => socket.destroy());
+      server.close();
+    },
+  };
+}
+
+// Answers the version handshake, and every other request with `respond`.
+const handshake =
+  (respond: Respond): Respond =>
+  (request) =>
+    request.method === "server.version"
+      ? `${JSON.stringify({
+          jsonrpc: "2.0",
+          id: request.id,
+          result: ["fake", "1.4"],
+        })}\n`
+      : respond(request);
+
+const txid = Txid.from_string(
+  "0000000000000000000000000000000000000000000000000000000000000001"
+);
+
+describe("Electrum client", () => {
+  it("fails to connect to an unreachable server", async () => {
+    try {
+      await ElectrumClient.connect("ws://127.0.0.1:1");
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.ElectrumConnection);
+    }
+  });
+
+  it("fails the requests in flight on a malformed message", async () => {
+    const server = await fakeServer(handshake(() => "not json\n"));
+    const client = await ElectrumClient.connect(server.url);
+    try {
+      await client.get_tx(txid);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.ElectrumServer);
+    } finally {
+      client.close();
+      server.close();
+    }
+  });
+
+  it("times out requests the server does not answer", async () => {
+    const server = await fakeServer(handshake(() => undefined));
+    const client = await ElectrumClient.connect(server.url, 200);
+    try {
+      await client.get_tx(txid);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.ElectrumServer);
+      expect(error.message).toContain("timed out after 200 ms");
+    } finally {
+      client.close();
+      server.close();
+    }
+  });
+});
+
+// Tests are expected to run in order
+describeElectrum(`Electrum client (${network})`, () => {
+  const stopGap = 5;
+  const parallelRequests = 5;
+  const externalDescriptor =
+    "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/0/*)#jjcsy5wd";
+  const internalDescriptor =
+    "wpkh(tprv8ZgxMBicQKsPd5puBG1xsJ5V53vVPfCy2gnZfsqzmDSDjaQx8LEW4REFvrj6PQMuer7NqZeBiy9iP9ucqJZiveeEGqQ5CvcfV6SPcy8LQR7/84'/1'/0'/1/*)#rxa3ep74";
+
+  let client: ElectrumClient;
+  let wallet: Wallet;
+
+  beforeAll(async () => {
+    client = await ElectrumClient.connect(electrumUrl!);
+    wallet = Wallet.create(network, externalDescriptor, internalDescriptor);
+  });
+
+  afterAll(() => client.close());
+
+  it("performs full scan on a wallet", async () => {
+    const update = await client.full_scan(
+      wallet.start_full_scan(),
+      stopGap,
+      parallelRequests
+    );
+    wallet.apply_update(update);
+
+    expect(wallet.balance.trusted_spendable.to_sat()).toBeGreaterThan(0);
+    expect(wallet.latest_checkpoint.height).toBeGreaterThan(0);
+
+    const confirmed = wallet
+      .transactions()
+      .filter((tx) => tx.chain_position.is_confirmed);
+    expect(confirmed.length).toBeGreaterThan(0);
+  }, 30000);
+
+  it("syncs revealed scripts", async () => {
+    const balance = wallet.balance.total.to_sat();
+    const update = await client.sync(
+      wallet.start_sync_with_revealed_spks(),
+      parallelRequests
+    );
+    wallet.apply_update(update);
+
+    expect(wallet.balance.total.to_sat()).toBe(balance);
+  }, 30000);
+
+  it("fetches a transaction by txid", async () => {
+    const walletTx = wallet.transactions()[0];
+    const tx = await client.get_tx(walletTx.txid);
+
+    expect(tx).toBeDefined();
+    expect(tx!.compute_txid().toString()).toBe(walletTx.txid.toString());
+
+    const unknown = await client.get_tx(txid);
+    expect(unknown).toBeUndefined();
+  });
+
+  it("rejects broadcasting an invalid transaction", async () => {
+    const tx = wallet.transactions()[0].tx;
+
+    try {
+      // Already confirmed, so the server refuses it.
+      await client.broadcast(tx);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.ElectrumServer);
+    }
+  });
+
+  it("fails requests after the connection is closed", async () => {
+    const closing = await ElectrumClient.connect(electrumUrl!);
+    closing.close();
+
+    try {
+      await closing.sync(wallet.start_sync_with_revealed_spks(), 1);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.ElectrumConnection);
+    }
+  });
+});
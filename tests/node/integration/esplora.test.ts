@@ -1,4 +1,6 @@
 import { execSync } from "child_process";
+import { createServer, IncomingHttpHeaders, Server } from "http";
+import { AddressInfo } from "net";
 import {
   Amount,
   BdkError,
@@ -6,12 +8,17 @@ import {
   Block,
   BlockId,
   EsploraClient,
+  EsploraClientBuilder,
   EvictedTx,
   FeeRate,
   Network,
+  OutPoint,
   Recipient,
   SignOptions,
+  SyncProgress,
+  SyncRequestBuilder,
   Psbt,
+  Txid,
   TxOrdering,
   UnconfirmedTx,
   Wallet,
@@ -127,7 +134,11 @@ describe(`Esplora client (${network})`, () => {
   });
 
   it("performs full scan on a wallet", async () => {
-    const request = wallet.start_full_scan();
+    const scanned: Record<string, number> = { external: 0, internal: 0 };
+    const request = wallet.start_full_scan((keychain, index, script) => {
+      expect(script.as_bytes().length).toBe(22); // P2WPKH
+      scanned[keychain] = Math.max(scanned[keychain], index + 1);
+    });
     const update = await esploraClient.full_scan(
       request,
       stopGap,
@@ -137,6 +148,24 @@ describe(`Esplora client (${network})`, () => {
 
     expect(wallet.balance.trusted_spendable.to_sat()).toBeGreaterThan(0);
     expect(wallet.latest_checkpoint.height).toBeGreaterThan(0);
+    expect(scanned.external).toBeGreaterThan(stopGap);
+    expect(scanned.internal).toBeGreaterThanOrEqual(stopGap);
+  }, 30000);
+
+  it("reports the progress of a sync", async () => {
+    const progress: SyncProgress[] = [];
+    const request = wallet.start_sync_with_revealed_spks((_item, p) =>
+      progress.push(p)
+    );
+    const update = await esploraClient.sync(request, parallelRequests);
+    wallet.apply_update(update);
+
+    expect(progress.length).toBeGreaterThan(0);
+    const last = progress[progress.length - 1];
+    expect(last.remaining).toBe(0);
+    expect(last.consumed).toBe(last.total);
+    expect(last.total).toBe(progress.length);
+    expect(last.spks_consumed).toBeGreaterThan(0);
   }, 30000);
 
   it("lists scanned outputs and resolves known UTXOs", () => {
@@ -211,6 +240,125 @@ describe(`Esplora client (${network})`, () => {
     expect(walletTx.chain_position.is_confirmed).toBe(false);
   }, 30000);
 
+  it("exposes the PSBT inputs and outputs", () => {
+    const recipientAddress = wallet.peek_address("external", 6);
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(feeRate)
+      .add_recipient(
+        new Recipient(
+          recipientAddress.address.script_pubkey,
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+
+    const inputs = psbt.inputs;
+    expect(inputs).toHaveLength(psbt.unsigned_tx.input.length);
+    for (const input of inputs) {
+      expect(input.witness_utxo!.value.to_sat()).toBeGreaterThan(BigInt(0));
+      expect(input.non_witness_utxo).toBeDefined();
+      expect(input.partial_sigs).toHaveLength(0);
+      expect(input.is_finalized).toBe(false);
+      expect(input.final_script_witness).toBeUndefined();
+
+      const [origin] = input.bip32_derivation;
+      expect(origin.fingerprint).toMatch(/^[0-9a-f]{8}$/);
+      expect(origin.path).toMatch(/^84'\/1'\/0'\/[01]\/\d+$/);
+      expect(origin.public_key).toHaveLength(66);
+      expect(origin.leaf_hashes).toHaveLength(0);
+      expect(input.tap_key_origins).toHaveLength(0);
+    }
+
+    // The change output is ours, so it has its derivation.
+    const outputs = psbt.outputs;
+    expect(outputs).toHaveLength(psbt.unsigned_tx.output.length);
+    expect(
+      outputs.some((output) =>
+        output.bip32_derivation.some((origin) =>
+          origin.path.startsWith("84'/1'/0'/1/")
+        )
+      )
+    ).toBe(true);
+
+    // Without finalizing, the signatures are kept as partial signatures.
+    const signOptions = new SignOptions();
+    signOptions.try_finalize = false;
+    expect(wallet.sign(psbt, signOptions)).toBe(false);
+    const [signature] = psbt.inputs[0].partial_sigs;
+    expect(signature.public_key).toBe(
+      psbt.inputs[0].bip32_derivation[0].public_key
+    );
+    expect(signature.sighash_type).toBe(1);
+
+    // Finalizing replaces them with the final witness.
+    expect(wallet.finalize_psbt(psbt, new SignOptions())).toBe(true);
+    for (const input of psbt.inputs) {
+      expect(input.is_finalized).toBe(true);
+      expect(input.partial_sigs).toHaveLength(0);
+      expect(input.final_script_witness).toHaveLength(2);
+    }
+
+    wallet.cancel_tx(psbt.unsigned_tx);
+  });
+
+  it("syncs only unconfirmed transactions and UTXOs", async () => {
+    const unconfirmed = wallet
+      .transactions()
+      .filter((tx) => !tx.chain_position.is_confirmed)
+      .map((tx) => tx.txid.toString());
+    expect(unconfirmed.length).toBeGreaterThan(0);
+
+    const items: string[] = [];
+    let last: SyncProgress | undefined;
+    const request = wallet.start_sync_unconfirmed_and_utxos((item, p) => {
+      items.push(item);
+      last = p;
+    });
+    const update = await esploraClient.sync(request, parallelRequests);
+    wallet.apply_update(update);
+
+    expect(last!.spks_consumed).toBe(0);
+    expect(last!.txids_consumed).toBe(unconfirmed.length);
+    expect(last!.outpoints_consumed).toBeGreaterThan(0);
+    unconfirmed.forEach((txid) =>
+      expect(items.some((item) => item.includes(txid))).toBe(true)
+    );
+  }, 30000);
+
+  it("syncs a custom set of scripts, transactions and outputs", async () => {
+    const address = wallet.peek_address("external", 5);
+    const txid = wallet.transactions()[0].txid;
+    const utxo = wallet.list_unspent()[0];
+
+    const request = new SyncRequestBuilder()
+      .chain_tip(wallet.latest_checkpoint)
+      .spk_with_index("external", 5, address.address.script_pubkey)
+      .txids([txid])
+      .outpoints([utxo.outpoint])
+      .build();
+    const update = await esploraClient.sync(request, parallelRequests);
+    wallet.apply_update(update);
+
+    expect(wallet.get_tx(txid)).toBeDefined();
+  }, 30000);
+
+  it("syncs scripts without keychain nor index", async () => {
+    const address = wallet.peek_address("external", 0).address;
+    const items: string[] = [];
+
+    const request = new SyncRequestBuilder()
+      .chain_tip(wallet.latest_checkpoint)
+      .spks([address.script_pubkey])
+      .inspect((item: string) => items.push(item))
+      .build();
+    const update = await esploraClient.sync(request, parallelRequests);
+    wallet.apply_update(update);
+
+    // Reported without a keychain nor index
+    expect(items).toEqual([`script '${address.script_pubkey.to_string()}'`]);
+  }, 30000);
+
   it("returns tx_details for a known transaction", () => {
     // After the "sends a transaction" test, the wallet has at least one tx
     const txs = wallet.transactions();
@@ -341,6 +489,193 @@ describe(`Esplora client (${network})`, () => {
       expect(psbt.fee().to_sat()).toBeGreaterThan(BigInt(0));
     });
 
+    it("builds a version 2 PSBT and signs it", () => {
+      const recipientAddress = wallet.peek_address("external", 9);
+      const sendAmount = Amount.from_sat(BigInt(800));
+
+      const psbt = wallet
+        .build_tx()
+        .fee_rate(minFeeRate())
+        .psbt_version(2)
+        .add_recipient(
+          new Recipient(recipientAddress.address.script_pubkey, sendAmount)
+        )
+        .finish();
+
+      expect(psbt.version).toBe(2);
+      expect(psbt.inputs_modifiable).toBe(true);
+      expect(psbt.outputs_modifiable).toBe(true);
+
+      const parsed = Psbt.from_string(psbt.toString());
+      expect(parsed.version).toBe(2);
+      expect(parsed.inputs[0].witness_utxo).toBeDefined();
+
+      expect(wallet.sign(parsed, new SignOptions())).toBe(true);
+      expect(parsed.to_v0().extract_tx().compute_txid().toString()).toBe(
+        psbt.unsigned_tx.compute_txid().toString()
+      );
+      wallet.cancel_tx(psbt.unsigned_tx);
+    });
+
+    it("signs only the owned inputs of a collaborative PSBT", () => {
+      const recipientAddress = wallet.peek_address("external", 9);
+      const psbt = wallet
+        .build_tx()
+        .fee_rate(minFeeRate())
+        .psbt_version(2)
+        .add_recipient(
+          new Recipient(
+            recipientAddress.address.script_pubkey,
+            Amount.from_sat(BigInt(800))
+          )
+        )
+        .finish();
+      const ownedCount = psbt.inputs.length;
+
+      // Another participant adds an input, without its UTXO data.
+      psbt.add_input(
+        new OutPoint(
+          Txid.from_string(
+            "0000000000000000000000000000000000000000000000000000000000000001"
+          ),
+          0
+        )
+      );
+      expect(() => wallet.sign(psbt, new SignOptions())).toThrow();
+
+      const statuses = wallet.sign_owned_inputs(psbt, new SignOptions());
+      expect(statuses).toHaveLength(ownedCount + 1);
+      for (const status of statuses.slice(0, ownedCount)) {
+        expect(status.is_mine).toBe(true);
+        expect(status.signed).toBe(true);
+        expect(status.finalized).toBe(true);
+      }
+      const foreign = statuses[ownedCount];
+      expect(foreign.index).toBe(ownedCount);
+      expect(foreign.is_mine).toBe(false);
+      expect(foreign.signed).toBe(false);
+      expect(foreign.finalized).toBe(false);
+      expect(foreign.error).toBeDefined();
+
+      // Finalizing again reports the same, without signing.
+      const finalized = wallet.finalize_psbt_inputs(psbt, new SignOptions());
+      expect(finalized.map((status) => status.finalized)).toEqual(
+        statuses.map((status) => status.finalized)
+      );
+      wallet.cancel_tx(psbt.unsigned_tx);
+    });
+
+    it("analyzes a PSBT before signing it", () => {
+      // Pay another wallet, so the recipient is not ours
+      const otherWallet = Wallet.create(
+        network,
+        "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p",
+        "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/1/*)#dd6w3a4e"
+      );
+      const recipientAddress = otherWallet.peek_address("external", 0);
+      const psbt = wallet
+        .build_tx()
+        .fee_rate(minFeeRate())
+        .add_recipient(
+          new Recipient(
+            recipientAddress.address.script_pubkey,
+            Amount.from_sat(BigInt(800))
+          )
+        )
+        .finish();
+
+      const analysis = wallet.analyze_psbt(psbt);
+      expect(analysis.inputs.every((input) => input.is_mine)).toBe(true);
+      expect(analysis.outputs.map((output) => output.kind).sort()).toEqual([
+        "change",
+        "send",
+      ]);
+      expect(analysis.fee!.to_sat()).toBe(psbt.fee().to_sat());
+      expect(analysis.sent.to_sat()).toBe(
+        analysis.received.to_sat() + BigInt(800) + psbt.fee().to_sat()
+      );
+      expect(analysis.warnings.map((warning) => warning.code)).toEqual([
+        "external_output",
+      ]);
+      expect(analysis.has_warning("absurd_fee_rate")).toBe(false);
+
+      // The fee rate is absurd for a lower maximum fee rate.
+      const strict = wallet.analyze_psbt(psbt, new FeeRate(BigInt(0)));
+      expect(strict.has_warning("absurd_fee_rate")).toBe(true);
+      wallet.cancel_tx(psbt.unsigned_tx);
+    });
+
+    it("signs with an external signer registered by fingerprint", async () => {
+      // A watch-only copy of the wallet, signing through a "hardware wallet" holding the keys.
+      const watchOnly = Wallet.create(
+        network,
+        wallet.public_descriptor("external"),
+        wallet.public_descriptor("internal")
+      );
+      const request = watchOnly.start_full_scan();
+      const update = await esploraClient.full_scan(
+        request,
+        stopGap,
+        parallelRequests
+      );
+      watchOnly.apply_update(update);
+
+      const recipientAddress = watchOnly.peek_address("external", 9);
+      const psbt = watchOnly
+        .build_tx()
+        .fee_rate(minFeeRate())
+        .add_recipient(
+          new Recipient(
+            recipientAddress.address.script_pubkey,
+            Amount.from_sat(BigInt(800))
+          )
+        )
+        .finish();
+      const [origin] = psbt.inputs[0].bip32_derivation;
+      expect(watchOnly.sign(psbt, new SignOptions())).toBe(false);
+
+      // Invalid signatures returned by `signPsbt` are rejected.
+      watchOnly.add_external_signer(origin.fingerprint, {
+        signPsbt: async (unsigned: Psbt) => {
+          const options = new SignOptions();
+          options.try_finalize = false;
+          wallet.sign(unsigned, options);
+          const [partialSig] = unsigned.inputs[0].partial_sigs;
+          const bytes = Buffer.from(unsigned.toString(), "base64");
+          const offset = bytes.indexOf(Buffer.from(partialSig.signature, "hex"));
+          bytes[offset + 10] ^= 1;
+          return bytes.toString("base64");
+        },
+      });
+      await expect(
+        watchOnly.sign_async(Psbt.from_string(psbt.toString()), new SignOptions())
+      ).rejects.toMatchObject({ code: BdkErrorCode.Signer });
+
+      const signed: Psbt[] = [];
+      watchOnly.add_external_signer(origin.fingerprint, {
+        signPsbt: async (unsigned: Psbt) => {
+          const options = new SignOptions();
+          options.try_finalize = false;
+          wallet.sign(unsigned, options);
+          signed.push(unsigned);
+          return unsigned.toString();
+        },
+      });
+
+      expect(await watchOnly.sign_async(psbt, new SignOptions())).toBe(true);
+      expect(signed).toHaveLength(1);
+      for (const input of psbt.inputs) {
+        expect(input.is_finalized).toBe(true);
+      }
+      expect(psbt.extract_tx().compute_txid().toString()).toBe(
+        psbt.unsigned_tx.compute_txid().toString()
+      );
+
+      // Nothing is left to sign, so the external signer is not called again.
+      expect(await watchOnly.sign_async(psbt, new SignOptions())).toBe(true);
+      expect(signed).toHaveLength(1);
+    });
+
     it("builds a tx with include_output_redeem_witness_script", () => {
       const recipientAddress = wallet.peek_address("external", 10);
       const sendAmount = Amount.from_sat(BigInt(800));
@@ -909,3 +1244,125 @@ describeRegtest("Block application APIs (regtest)", () => {
     ).toBe(false);
   });
 });
+
+describe("Esplora client builder", () => {
+  const txid = Txid.from_string(
+    "0000000000000000000000000000000000000000000000000000000000000001"
+  );
+
+  let server: Server;
+  let url: string;
+  let received: IncomingHttpHeaders | undefined;
+  let respond = true;
+  let unavailable = 0;
+  let requestTimes: number[] = [];
+
+  beforeAll(async () => {
+    server = createServer((req, res) => {
+      received = req.headers;
+      requestTimes.push(Date.now());
+      if (unavailable > 0) {
+        unavailable--;
+        res.writeHead(503).end("Service unavailable");
+      } else if (respond) {
+        res.writeHead(404).end("Transaction not found");
+      }
+    });
+    await new Promise<void>((resolve) => server.listen(0, "127.0.0.1", resolve));
+    url = `http://127.0.0.1:${(server.address() as AddressInfo).port}`;
+  });
+
+  afterAll(() => {
+    server.closeAllConnections();
+    server.close();
+  });
+
+  it("sends the configured headers", async () => {
+    respond = true;
+    const client = new EsploraClientBuilder(url)
+      .bearer_token("secret")
+      .header("X-Api-Key", "key")
+      .user_agent("bdk-wasm-test")
+      .build();
+
+    expect(await client.get_tx(txid)).toBeUndefined();
+    expect(received!.authorization).toBe("Bearer secret");
+    expect(received!["x-api-key"]).toBe("key");
+    expect(received!["user-agent"]).toBe("bdk-wasm-test");
+  });
+
+  async function expectBdkError(
+    promise: Promise<unknown>,
+    code: BdkErrorCode
+  ): Promise<BdkError> {
+    try {
+      await promise;
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(code);
+      return error;
+    }
+    throw new Error("expected an error");
+  }
+
+  it("fails calls that exceed the timeout", async () => {
+    respond = false;
+    const client = new EsploraClientBuilder(url).call_timeout(200).build();
+
+    const error = await expectBdkError(
+      client.get_tx(txid),
+      BdkErrorCode.Esplora
+    );
+    expect(error.message).toContain("timed out");
+  });
+
+  it("rejects aborted calls", async () => {
+    respond = false;
+    const client = new EsploraClientBuilder(url).build();
+
+    const controller = new AbortController();
+    const pending = client.get_tx(txid, controller.signal);
+    setTimeout(() => controller.abort(), 100);
+    await expectBdkError(pending, BdkErrorCode.Aborted);
+
+    // Already aborted signals fail without sending any request.
+    await expectBdkError(
+      client.get_tx(txid, controller.signal),
+      BdkErrorCode.Aborted
+    );
+  });
+
+  it("retries failed requests with the configured backoff", async () => {
+    respond = true;
+    unavailable = 3;
+    requestTimes = [];
+    const client = new EsploraClientBuilder(url)
+      .max_retries(3)
+      .retry_backoff(100, 3, 400)
+      .build();
+
+    expect(await client.get_tx(txid)).toBeUndefined();
+    expect(requestTimes).toHaveLength(4);
+    const delays = requestTimes
+      .slice(1)
+      .map((time, i) => time - requestTimes[i]);
+    // 100 ms, then 300 ms, then 900 ms capped at 400 ms.
+    expect(delays[0]).toBeGreaterThanOrEqual(90);
+    expect(delays[0]).toBeLessThan(250);
+    expect(delays[1]).toBeGreaterThanOrEqual(290);
+    expect(delays[2]).toBeGreaterThanOrEqual(390);
+    expect(delays[2]).toBeLessThan(800);
+  });
+
+  it("rejects an invalid retry backoff", () => {
+    expect(() =>
+      new EsploraClientBuilder(url).retry_backoff(100, 0.5, 1000).build()
+    ).toThrow("backoff factor");
+  });
+
+  it("rejects invalid headers", () => {
+    expect(() =>
+      new EsploraClientBuilder(url).header("Invalid Header", "value").build()
+    ).toThrow();
+  });
+});
@@ -1,6 +1,7 @@
 import { execSync } from "child_process";
 import {
   Amount,
+  ChangeSet,
   EsploraClient,
   FeeRate,
   Network,
@@ -9,6 +10,8 @@ import {
   Wallet,
   WalletEvent,
   WalletEventKind,
+  WalletPersister,
+  WalletSyncer,
 } from "../../../pkg/bitcoindevkit";
 
 const network: Network = (process.env.NETWORK as Network) || "regtest";
@@ -31,6 +34,9 @@ const EventKind = {
   TxUnconfirmed: "tx_unconfirmed" as WalletEventKind,
   TxReplaced: "tx_replaced" as WalletEventKind,
   TxDropped: "tx_dropped" as WalletEventKind,
+  TxReceived: "tx_received" as WalletEventKind,
+  TxSent: "tx_sent" as WalletEventKind,
+  BalanceChanged: "balance_changed" as WalletEventKind,
 };
 
 /**
@@ -146,6 +152,25 @@ describeRegtest("Wallet events (regtest)", () => {
       expect(event.block_time!.block_id.height).toBeGreaterThan(0);
     }
 
+    // The funding transaction is new and only pays to the wallet
+    const receivedEvents = events.filter(
+      (e) => e.kind === EventKind.TxReceived
+    );
+    expect(receivedEvents.length).toBeGreaterThanOrEqual(1);
+    for (const event of receivedEvents) {
+      expect(event.txid).toBeDefined();
+      expect(event.sent!.to_sat()).toBe(BigInt(0));
+      expect(event.received!.to_sat()).toBeGreaterThan(0);
+    }
+
+    // The balance went up from zero, and is reported last
+    const balanceEvent = events[events.length - 1];
+    expect(balanceEvent.kind).toBe(EventKind.BalanceChanged);
+    expect(balanceEvent.old_balance!.total.to_sat()).toBe(BigInt(0));
+    expect(balanceEvent.new_balance!.total.to_sat()).toBe(
+      wallet.balance.total.to_sat()
+    );
+
     // Wallet should have balance after applying events
     expect(wallet.balance.trusted_spendable.to_sat()).toBeGreaterThan(0);
   }, 30000);
@@ -215,6 +240,16 @@ describeRegtest("Wallet events (regtest)", () => {
     expect(ourTxEvent!.block_time).toBeDefined();
     expect(ourTxEvent!.block_time!.block_id.height).toBeGreaterThan(tipBefore);
     expect(ourTxEvent!.tx).toBeDefined();
+
+    // The transaction was broadcast without being applied, so it is new to the wallet
+    const sentEvent = events.find(
+      (e) =>
+        e.kind === EventKind.TxSent && e.txid?.toString() === txid.toString()
+    );
+    expect(sentEvent).toBeDefined();
+    expect(sentEvent!.sent!.to_sat()).toBeGreaterThan(
+      sentEvent!.received!.to_sat()
+    );
   }, 30000);
 
   it("event kind returns valid string enum values", async () => {
@@ -234,6 +269,9 @@ describeRegtest("Wallet events (regtest)", () => {
       "tx_unconfirmed",
       "tx_replaced",
       "tx_dropped",
+      "tx_received",
+      "tx_sent",
+      "balance_changed",
     ]);
 
     for (const event of events) {
@@ -258,8 +296,74 @@ describeRegtest("Wallet events (regtest)", () => {
         e.kind === EventKind.TxConfirmed ||
         e.kind === EventKind.TxUnconfirmed ||
         e.kind === EventKind.TxReplaced ||
-        e.kind === EventKind.TxDropped
+        e.kind === EventKind.TxDropped ||
+        e.kind === EventKind.TxReceived ||
+        e.kind === EventKind.TxSent ||
+        e.kind === EventKind.BalanceChanged
     );
     expect(txEvents.length).toBe(0);
   }, 30000);
+
+  it("dispatches events from the background syncer", async () => {
+    const syncer = new WalletSyncer(wallet, esploraClient, 500, 1);
+    const errors: unknown[] = [];
+    syncer.on_error((error: unknown) => errors.push(error));
+    const tipChanged = new Promise<WalletEvent>((resolve) =>
+      syncer.on(EventKind.ChainTipChanged, resolve)
+    );
+
+    const tip = wallet.latest_checkpoint.height;
+    mineBlocks(1);
+    await waitForEsploraHeight(tip + 1);
+
+    syncer.start();
+    expect(syncer.is_running).toBe(true);
+    try {
+      const event = await tipChanged;
+      expect(event.new_tip!.height).toBe(tip + 1);
+      // The syncer updates the wallet shared with JS
+      expect(wallet.latest_checkpoint.height).toBe(tip + 1);
+    } finally {
+      syncer.stop();
+    }
+    expect(syncer.is_running).toBe(false);
+    expect(errors).toHaveLength(0);
+  }, 30000);
+
+  it("completes a round stopped while persisting its update", async () => {
+    const blobs: string[] = [];
+    let persisting: () => void = () => {};
+    const persistStarted = new Promise<void>((r) => (persisting = r));
+    let release: () => void = () => {};
+    const persister: WalletPersister = {
+      initialize: async () => undefined,
+      persist: async (changeset: ChangeSet) => {
+        if (blobs.length > 0) {
+          persisting();
+          await new Promise<void>((r) => (release = r));
+        }
+        blobs.push(changeset.to_json());
+      },
+    };
+    const persisted = await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDescriptor,
+      internalDescriptor
+    );
+
+    const syncer = new WalletSyncer(persisted, esploraClient, 500, 1);
+    const tipChanged = new Promise<WalletEvent>((resolve) =>
+      syncer.on(EventKind.ChainTipChanged, resolve)
+    );
+    syncer.start();
+    await persistStarted;
+    syncer.stop();
+    release();
+
+    // The update was applied, so its events and changes are not lost
+    const event = await tipChanged;
+    expect(event.new_tip!.height).toBe(persisted.latest_checkpoint.height);
+    expect(blobs).toHaveLength(2);
+  }, 30000);
 });
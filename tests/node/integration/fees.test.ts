@@ -0,0 +1,149 @@
+import {
+  FeeEstimates,
+  FeeRate,
+  RecommendedFees,
+} from "../../../pkg/bitcoindevkit";
+
+describe("Fee estimation", () => {
+  const estimates = FeeEstimates.from_json(
+    JSON.stringify({ "2": 20.5, "6": 10, "12": 4, "144": 1.5, "1008": 1 })
+  );
+
+  it("parses Esplora fee estimates", () => {
+    expect(estimates.get(6)).toBe(10);
+    expect(estimates.get(7)).toBeUndefined();
+  });
+
+  it("interpolates missing confirmation targets", () => {
+    expect(estimates.interpolate(6)).toBe(10);
+    expect(estimates.interpolate(9)).toBe(7);
+    // Outside of the available targets
+    expect(estimates.interpolate(1)).toBe(20.5);
+    expect(estimates.interpolate(2000)).toBe(1);
+
+    expect(FeeEstimates.from_json("{}").interpolate(1)).toBeUndefined();
+  });
+
+  it("keeps sub-sat/vB precision", () => {
+    // 1 sat/vB = 250 sat/kwu
+    expect(estimates.fee_rate(144)!.to_sat_per_kwu()).toBe(BigInt(375));
+    expect(estimates.fee_rate(144)!.to_sat_per_vb_ceil()).toBe(BigInt(2));
+    // 17.875 sat/vB, rounded up to the next sat/kwu
+    expect(estimates.fee_rate(3)!.to_sat_per_kwu()).toBe(BigInt(4469));
+  });
+
+  it("computes recommended fee tiers from estimates", () => {
+    const fees = estimates.recommended()!;
+
+    expect(fees.fastest.to_sat_per_kwu()).toBe(BigInt(5125));
+    expect(fees.get("halfHour").to_sat_per_kwu()).toBe(BigInt(4469));
+    expect(fees.hour.to_sat_per_kwu()).toBe(BigInt(2500));
+    expect(fees.economy.to_sat_per_kwu()).toBe(BigInt(375));
+    expect(fees.get("minimum").to_sat_per_kwu()).toBe(BigInt(250));
+
+    expect(FeeEstimates.from_json("{}").recommended()).toBeUndefined();
+  });
+
+  it("parses mempool.space recommended fees", () => {
+    const fees = RecommendedFees.from_json(
+      JSON.stringify({
+        fastestFee: 12,
+        halfHourFee: 10,
+        hourFee: 8,
+        economyFee: 4,
+        minimumFee: 2,
+      })
+    );
+
+    expect(fees.get("fastest").to_sat_per_vb_floor()).toBe(BigInt(12));
+    expect(fees.half_hour.to_sat_per_vb_floor()).toBe(BigInt(10));
+    expect(fees.get("hour").to_sat_per_vb_floor()).toBe(BigInt(8));
+    expect(fees.get("economy").to_sat_per_vb_floor()).toBe(BigInt(4));
+    expect(fees.minimum.to_sat_per_vb_floor()).toBe(BigInt(2));
+  });
+
+  it("keeps the tiers ordered", () => {
+    const fees = RecommendedFees.from_json(
+      JSON.stringify({
+        fastestFee: 5.5,
+        halfHourFee: 6,
+        hourFee: 3,
+        economyFee: 1.2,
+        minimumFee: 1,
+      })
+    );
+
+    expect(fees.fastest.to_sat_per_kwu()).toBe(BigInt(1500));
+    expect(fees.half_hour.to_sat_per_kwu()).toBe(BigInt(1500));
+    expect(fees.economy.to_sat_per_kwu()).toBe(BigInt(300));
+  });
+
+  it("rejects invalid recommended fees", () => {
+    expect(() => RecommendedFees.from_json("{}")).toThrow();
+    expect(() =>
+      RecommendedFees.from_json(
+        JSON.stringify({
+          fastestFee: -1,
+          halfHourFee: 1,
+          hourFee: 1,
+          economyFee: 1,
+          minimumFee: 1,
+        })
+      )
+    ).toThrow("Invalid fee rate");
+  });
+});
+
+describe("FeeRate", () => {
+  it("is constructed from sat/kwu, fractional sat/vB and BTC/kvB", () => {
+    expect(FeeRate.from_sat_per_kwu(BigInt(253)).to_sat_per_kwu()).toBe(
+      BigInt(253)
+    );
+    expect(FeeRate.from_sat_per_vb(1.5).to_sat_per_kwu()).toBe(BigInt(375));
+    expect(FeeRate.from_sat_per_vb(0.1).to_sat_per_kwu()).toBe(BigInt(25));
+    expect(FeeRate.from_sat_per_vb(1.1).to_sat_per_kwu()).toBe(BigInt(275));
+    // Bitcoin Core's unit: 0.00001 BTC/kvB = 1 sat/vB
+    expect(FeeRate.from_btc_per_kvb(0.00001).to_sat_per_kwu()).toBe(
+      BigInt(250)
+    );
+    expect(FeeRate.from_btc_per_kvb(0.00012345).to_sat_per_vb()).toBe(12.348);
+  });
+
+  it("never rounds below the requested rate", () => {
+    // Just above a sat/kvB boundary, rounded up to the next sat/kwu.
+    expect(FeeRate.from_sat_per_vb(1.0004).to_sat_per_kwu()).toBe(BigInt(251));
+    expect(FeeRate.from_sat_per_vb(0.0004).to_sat_per_kwu()).toBe(BigInt(1));
+    expect(FeeRate.from_sat_per_vb(2.0001).to_sat_per_kwu()).toBe(BigInt(501));
+    expect(FeeRate.from_btc_per_kvb(0.0000100004).to_sat_per_kwu()).toBe(
+      BigInt(251)
+    );
+    expect(FeeRate.from_sat_per_vb(0).to_sat_per_kwu()).toBe(BigInt(0));
+  });
+
+  it("converts back to fractional units", () => {
+    const rate = FeeRate.from_sat_per_vb(2.5);
+
+    expect(rate.to_sat_per_vb()).toBe(2.5);
+    expect(rate.to_sat_per_vb_floor()).toBe(BigInt(2));
+    expect(rate.to_sat_per_vb_ceil()).toBe(BigInt(3));
+    expect(rate.to_btc_per_kvb()).toBe(0.000025);
+  });
+
+  it("rejects invalid rates", () => {
+    expect(() => FeeRate.from_sat_per_vb(-1)).toThrow("Invalid fee rate");
+    expect(() => FeeRate.from_sat_per_vb(NaN)).toThrow("Invalid fee rate");
+    expect(() => FeeRate.from_btc_per_kvb(Infinity)).toThrow(
+      "Invalid fee rate"
+    );
+  });
+
+  it("computes fees for a size, rounding up", () => {
+    const rate = FeeRate.from_sat_per_vb(1.5);
+
+    expect(rate.fee_for_vsize(BigInt(141))!.to_sat()).toBe(BigInt(212));
+    expect(rate.fee_for_weight(BigInt(561))!.to_sat()).toBe(BigInt(211));
+    expect(new FeeRate(BigInt(2)).fee_for_vsize(BigInt(100))!.to_sat()).toBe(
+      BigInt(200)
+    );
+  });
+});
@@ -0,0 +1,99 @@
+import {
+  Address,
+  BdkError,
+  BdkErrorCode,
+  Wallet,
+  verify_message,
+} from "../../../pkg/bitcoindevkit";
+
+describe("Message signing", () => {
+  // Private key of the BIP 322 test vectors.
+  const privateKey = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+  const segwitAddress = () =>
+    Address.from_string("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "bitcoin");
+  const taprootAddress = () =>
+    Address.from_string(
+      "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3",
+      "bitcoin"
+    );
+
+  it("verifies the BIP 322 test vectors", () => {
+    expect(
+      verify_message(
+        segwitAddress(),
+        "",
+        "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="
+      )
+    ).toBe(true);
+    expect(
+      verify_message(
+        segwitAddress(),
+        "Hello World",
+        "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="
+      )
+    ).toBe(true);
+    expect(
+      verify_message(
+        taprootAddress(),
+        "Hello World",
+        "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ=="
+      )
+    ).toBe(true);
+  });
+
+  it("rejects signatures of another message", () => {
+    expect(
+      verify_message(
+        segwitAddress(),
+        "Hello World",
+        "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="
+      )
+    ).toBe(false);
+    expect(verify_message(segwitAddress(), "Hello World", "not a signature")).toBe(
+      false
+    );
+  });
+
+  it("signs with P2WPKH addresses in the simple and full formats", () => {
+    const wallet = Wallet.create_single("bitcoin", `wpkh(${privateKey})`);
+
+    const simple = wallet.sign_message(segwitAddress(), "Hello World");
+    expect(verify_message(segwitAddress(), "Hello World", simple)).toBe(true);
+    expect(verify_message(segwitAddress(), "Hello", simple)).toBe(false);
+
+    const full = wallet.sign_message(segwitAddress(), "Hello World", "full");
+    expect(full).not.toBe(simple);
+    expect(verify_message(segwitAddress(), "Hello World", full)).toBe(true);
+  });
+
+  it("signs with P2TR addresses", () => {
+    const wallet = Wallet.create_single("bitcoin", `tr(${privateKey})`);
+
+    const signature = wallet.sign_message(taprootAddress(), "Hello World");
+    expect(verify_message(taprootAddress(), "Hello World", signature)).toBe(true);
+  });
+
+  it("signs with P2PKH addresses in the legacy format", () => {
+    const wallet = Wallet.create_single("bitcoin", `pkh(${privateKey})`);
+    const address = wallet.peek_address("external", 0).address;
+
+    const signature = wallet.sign_message(address, "Hello World");
+    expect(verify_message(address, "Hello World", signature)).toBe(true);
+    expect(verify_message(address, "Hello", signature)).toBe(false);
+    expect(() => wallet.sign_message(address, "Hello World", "simple")).toThrow(
+      BdkError
+    );
+  });
+
+  it("refuses to sign with an address of another wallet", () => {
+    const wallet = Wallet.create_single("bitcoin", `tr(${privateKey})`);
+
+    try {
+      wallet.sign_message(segwitAddress(), "Hello World");
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect((error as BdkError).code).toBe(BdkErrorCode.UnknownAddress);
+    }
+  });
+});
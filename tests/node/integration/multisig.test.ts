@@ -0,0 +1,133 @@
+import {
+  BdkError,
+  BdkErrorCode,
+  DescriptorPair,
+  MultisigDescriptorBuilder,
+  MultisigScript,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+
+describe("MultisigDescriptorBuilder", () => {
+  const originPath = "m/48'/1'/0'/2'";
+  // Account keys of three cosigners, at the BIP48 P2WSH path of their master key.
+  const cosigners = [
+    {
+      fingerprint: "4ba43603",
+      xpriv:
+        "tprv8hFctGb1osu2xgPgzZjcWA7mP1YGWJNCqrjPSLttbyUFVhjjP4Q84LDPUHb6iQQHE1tnCKT1bDhnni2m77be58zhqTdcXjj5w3QXMHC1ffo",
+      xpub: "tpubDDwf2gdFxFahr9RUtDQCuZmsx34CfdZ7RALAirwC2FGeLBzW1TDiEpqFeRdxLdZD7rfsbZHYwSaT6CLM3TAcYRw6xfRv4U6KCQt4Zuhvjkz",
+    },
+    {
+      fingerprint: "8dfc9b34",
+      xpub: "tpubDEXiq2SVhhqALktxfVFgj3C9M3T2G7xL11iezYg2LJAf245YkNyqp2K9TrvHABDCp2232k34UegU4aKEtUZNigit8EEqoLNe2JKMzMiLwYq",
+    },
+    {
+      fingerprint: "56c4fac3",
+      xpub: "tpubDEg3kqr2jo5ergkJbFqRHvCpiob7wR7Hi44J7y987G1JZfbzBND77XKTyPZzGvh3uyDf8kexMJnFD9W8FuraJ4wLMsx6YuZVXRSRRcx6QdD",
+    },
+  ];
+  const key = (index: number, keychain: number) =>
+    `[${cosigners[index].fingerprint}/48'/1'/0'/2']${cosigners[index].xpub}/${keychain}/*`;
+
+  const builder = (threshold: number, script: MultisigScript) =>
+    cosigners.reduce(
+      (builder, cosigner) =>
+        builder.add_cosigner(cosigner.xpub, cosigner.fingerprint, originPath),
+      new MultisigDescriptorBuilder(threshold, script, "testnet")
+    );
+
+  it("builds 2-of-3 descriptors for each script", () => {
+    const expected: [MultisigScript, string, string][] = [
+      [
+        "sh",
+        `sh(sortedmulti(2,${key(0, 0)},${key(1, 0)},${key(2, 0)}))#m2eywhaz`,
+        "2NB9gPR867nVKnoQhPdX5bxDv1u4KiUNLs5",
+      ],
+      [
+        "sh_wsh",
+        `sh(wsh(sortedmulti(2,${key(0, 0)},${key(1, 0)},${key(2, 0)})))#sanj93g2`,
+        "2MvFe56sSJ1MxM1cSELwts5mN2qdJnHth2U",
+      ],
+      [
+        "wsh",
+        `wsh(sortedmulti(2,${key(0, 0)},${key(1, 0)},${key(2, 0)}))#lls4p9kd`,
+        "tb1qkpad42v3wkkt9hfww7vs7udr72pm32vyz5kgphkjm5m3z57pgz0srtay9a",
+      ],
+      [
+        "tr",
+        `tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,multi_a(2,${key(0, 0)},${key(1, 0)},${key(2, 0)}))#5jejjlg2`,
+        "tb1pnsg0llhldsz9r67ayvaxsw4n62vmfu8pthr5qnh0sq93wullrfespwp2dh",
+      ],
+    ];
+
+    for (const [script, external, address] of expected) {
+      const descriptors = builder(2, script).build();
+      expect(descriptors.external).toBe(external);
+      expect(descriptors.internal).toContain(`${key(0, 1)},${key(1, 1)}`);
+
+      const wallet = Wallet.create(
+        "testnet",
+        descriptors.external,
+        descriptors.internal
+      );
+      expect(wallet.peek_address("external", 0).address.toString()).toBe(
+        address
+      );
+    }
+  });
+
+  it("keeps the private keys of cosigner descriptors", () => {
+    const [local, ...others] = cosigners;
+    const localKey = `[${local.fingerprint}/48'/1'/0'/2']${local.xpriv}`;
+    const descriptors = others
+      .reduce(
+        (builder, cosigner) =>
+          builder.add_cosigner(cosigner.xpub, cosigner.fingerprint, originPath),
+        new MultisigDescriptorBuilder(2, "wsh", "testnet")
+          .sorted(false)
+          .add_cosigner_descriptors(
+            new DescriptorPair(
+              `wsh(pk(${localKey}/0/*))`,
+              `wsh(pk(${localKey}/1/*))`
+            )
+          )
+      )
+      .build();
+
+    expect(descriptors.external).toBe(
+      `wsh(multi(2,${localKey}/0/*,${key(1, 0)},${key(2, 0)}))#22jnrku6`
+    );
+    expect(descriptors.internal).toBe(
+      `wsh(multi(2,${localKey}/1/*,${key(1, 1)},${key(2, 1)}))#0lefasvj`
+    );
+  });
+
+  it("rejects invalid thresholds", () => {
+    for (const threshold of [0, 4]) {
+      try {
+        builder(threshold, "wsh").build();
+        fail("expected an error");
+      } catch (error) {
+        expect(error).toBeInstanceOf(BdkError);
+        expect((error as BdkError).code).toBe(BdkErrorCode.InvalidThreshold);
+      }
+    }
+  });
+
+  it("rejects keys of another network", () => {
+    const mainnetBuilder = new MultisigDescriptorBuilder(
+      1,
+      "wsh",
+      "bitcoin"
+    ).add_cosigner(cosigners[0].xpub, cosigners[0].fingerprint, originPath);
+
+    try {
+      mainnetBuilder.build();
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect((error as BdkError).code).toBe(BdkErrorCode.NetworkMismatch);
+      expect((error as BdkError).data).toBe(0);
+    }
+  });
+});
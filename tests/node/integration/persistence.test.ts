@@ -0,0 +1,320 @@
+import {
+  BdkError,
+  BdkErrorCode,
+  ChangeSet,
+  Wallet,
+  WalletPersister,
+} from "../../../pkg/bitcoindevkit";
+import type { Network } from "../../../pkg/bitcoindevkit";
+
+// Keeps every persisted changeset as a JSON blob, as a key-value store would.
+class MemoryPersister implements WalletPersister {
+  blobs: string[] = [];
+
+  async initialize(): Promise<ChangeSet | undefined> {
+    if (this.blobs.length === 0) {
+      return undefined;
+    }
+
+    const changeset = ChangeSet.from_json(this.blobs[0]);
+    for (const blob of this.blobs.slice(1)) {
+      changeset.merge(ChangeSet.from_json(blob));
+    }
+    return changeset;
+  }
+
+  async persist(changeset: ChangeSet): Promise<void> {
+    this.blobs.push(changeset.to_json());
+  }
+}
+
+describe("Wallet persistence", () => {
+  const network: Network = "testnet";
+  const externalDesc =
+    "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p";
+  const internalDesc =
+    "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/1/*)#dd6w3a4e";
+
+  it("returns undefined when loading from an empty persister", async () => {
+    const loaded = await Wallet.load_with_persister(new MemoryPersister());
+
+    expect(loaded).toBeUndefined();
+  });
+
+  it("persists the initial changeset and every mutation", async () => {
+    const persister = new MemoryPersister();
+    const wallet = await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDesc,
+      internalDesc
+    );
+    expect(persister.blobs.length).toBe(1);
+
+    wallet.reveal_addresses_to("external", 3);
+    wallet.reveal_next_address("internal");
+    await wallet.persist();
+
+    expect(persister.blobs.length).toBeGreaterThan(1);
+    expect(await wallet.persist()).toBe(false);
+
+    const loaded = await Wallet.load_with_persister(
+      persister,
+      externalDesc,
+      internalDesc
+    );
+    expect(loaded.derivation_index("external")).toBe(3);
+    expect(loaded.derivation_index("internal")).toBe(0);
+    expect(loaded.peek_address("external", 0).address.toString()).toBe(
+      "tb1qjtgffm20l9vu6a7gacxvpu2ej4kdcsgc26xfdz"
+    );
+  });
+
+  it("refuses to create a wallet over existing data", async () => {
+    const persister = new MemoryPersister();
+    await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDesc,
+      internalDesc
+    );
+
+    try {
+      await Wallet.create_with_persister(
+        persister,
+        network,
+        externalDesc,
+        internalDesc
+      );
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.DataAlreadyExists);
+    }
+  });
+
+  it("keeps changes that failed to persist", async () => {
+    const persister = new MemoryPersister();
+    const wallet = await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDesc,
+      internalDesc
+    );
+    await wallet.persist();
+
+    const persist = persister.persist;
+    persister.persist = async () => {
+      throw new Error("storage unavailable");
+    };
+    wallet.reveal_next_address("external");
+
+    await expect(wallet.persist()).rejects.toBeInstanceOf(BdkError);
+
+    persister.persist = persist;
+    expect(await wallet.persist()).toBe(true);
+
+    const loaded = await Wallet.load_with_persister(persister);
+    expect(loaded.derivation_index("external")).toBe(0);
+  });
+
+  it("rejects persisting a wallet without persister", async () => {
+    const wallet = Wallet.create(network, externalDesc, internalDesc);
+
+    await expect(wallet.persist()).rejects.toBeInstanceOf(BdkError);
+  });
+
+  it("leaves the changeset returned by initialize usable", async () => {
+    const persister = new MemoryPersister();
+    await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDesc,
+      internalDesc
+    );
+    const cached = await persister.initialize();
+    const cachedPersister: WalletPersister = {
+      initialize: async () => cached,
+      persist: async () => {},
+    };
+
+    await Wallet.load_with_persister(cachedPersister);
+    expect(cached!.to_json()).toBe(persister.blobs[0]);
+    const loaded = await Wallet.load_with_persister(cachedPersister);
+    expect(loaded.peek_address("external", 0).address.toString()).toBe(
+      "tb1qjtgffm20l9vu6a7gacxvpu2ej4kdcsgc26xfdz"
+    );
+  });
+
+  it("refuses to take the staged changes of a wallet with persister", async () => {
+    const persister = new MemoryPersister();
+    const wallet = await Wallet.create_with_persister(
+      persister,
+      network,
+      externalDesc,
+      internalDesc
+    );
+    wallet.reveal_next_address("external");
+
+    try {
+      wallet.take_staged();
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.Persist);
+    }
+
+    // The changes are still persisted
+    await wallet.persist();
+    const loaded = await Wallet.load_with_persister(persister);
+    expect(loaded.derivation_index("external")).toBe(0);
+  });
+
+  describe("ChangeSet binary encoding", () => {
+    const changeset = () => {
+      const wallet = Wallet.create(network, externalDesc, internalDesc);
+      wallet.reveal_addresses_to("external", 5);
+      return wallet.take_staged();
+    };
+
+    it("round-trips through to_bytes and from_bytes", () => {
+      const original = changeset();
+      const bytes = original.to_bytes();
+
+      expect(Array.from(bytes.slice(0, 5))).toEqual([0x42, 0x44, 0x4b, 0x43, 1]);
+      expect(bytes.length).toBeLessThan(
+        new TextEncoder().encode(original.to_json()).length
+      );
+
+      const decoded = ChangeSet.from_bytes(bytes);
+      expect(decoded.to_json()).toBe(original.to_json());
+
+      const loaded = Wallet.load(decoded, externalDesc, internalDesc);
+      expect(loaded.derivation_index("external")).toBe(5);
+    });
+
+    it("persists and reloads a wallet through binary blobs", async () => {
+      const blobs: Uint8Array[] = [];
+      const persister: WalletPersister = {
+        initialize: async () => {
+          if (blobs.length === 0) {
+            return undefined;
+          }
+
+          const merged = ChangeSet.from_bytes(blobs[0]);
+          for (const blob of blobs.slice(1)) {
+            merged.merge(ChangeSet.from_bytes(blob));
+          }
+          return merged;
+        },
+        persist: async (changeset: ChangeSet) => {
+          blobs.push(changeset.to_bytes());
+        },
+      };
+
+      const wallet = await Wallet.create_with_persister(
+        persister,
+        network,
+        externalDesc,
+        internalDesc
+      );
+      wallet.reveal_addresses_to("external", 3);
+      await wallet.persist();
+      expect(blobs.length).toBe(2);
+
+      const loaded = await Wallet.load_with_persister(
+        persister,
+        externalDesc,
+        internalDesc
+      );
+      expect(loaded.derivation_index("external")).toBe(3);
+    });
+
+    it("migrates JSON blobs written by previous releases", () => {
+      const json = changeset().to_json();
+      const decoded = ChangeSet.from_bytes(new TextEncoder().encode(json));
+
+      expect(decoded.to_json()).toBe(json);
+    });
+
+    it("rejects unknown format versions and malformed blobs", () => {
+      // Version 0 never existed and 0xff is not known yet.
+      for (const version of [0, 0xff]) {
+        const bytes = changeset().to_bytes();
+        bytes[4] = version;
+
+        try {
+          ChangeSet.from_bytes(bytes);
+          fail("expected an error");
+        } catch (error) {
+          expect(error).toBeInstanceOf(BdkError);
+          expect(error.code).toBe(BdkErrorCode.UnsupportedChangeSetVersion);
+          expect(error.data).toBe(version);
+        }
+      }
+
+      try {
+        ChangeSet.from_bytes(new Uint8Array([1, 2, 3]));
+        fail("expected an error");
+      } catch (error) {
+        expect(error).toBeInstanceOf(BdkError);
+        expect(error.code).toBe(BdkErrorCode.ChangeSetEncoding);
+      }
+    });
+  });
+
+  describe("ChangeSet encryption", () => {
+    const passphrase = "correct horse battery staple";
+
+    it("round-trips through encrypt and decrypt", () => {
+      const wallet = Wallet.create(network, externalDesc, internalDesc);
+      wallet.reveal_addresses_to("external", 3);
+      const original = wallet.take_staged();
+
+      const blob = original.encrypt(passphrase);
+      expect(Array.from(blob.slice(0, 5))).toEqual([0x42, 0x44, 0x4b, 0x45, 1]);
+      expect(original.encrypt(passphrase)).not.toEqual(blob);
+
+      const decrypted = ChangeSet.decrypt(blob, passphrase);
+      expect(decrypted.to_json()).toBe(original.to_json());
+    });
+
+    it("rejects a wrong passphrase or tampered data", () => {
+      const wallet = Wallet.create(network, externalDesc, internalDesc);
+      const blob = wallet.take_staged().encrypt(passphrase);
+      const tampered = blob.slice();
+      tampered[tampered.length - 1] ^= 1;
+
+      for (const [data, secret] of [
+        [blob, "wrong passphrase"],
+        [tampered, passphrase],
+      ] as const) {
+        try {
+          ChangeSet.decrypt(data, secret);
+          fail("expected an error");
+        } catch (error) {
+          expect(error).toBeInstanceOf(BdkError);
+          expect(error.code).toBe(BdkErrorCode.Decryption);
+        }
+      }
+    });
+
+    it("rejects tampered KDF costs before deriving the key", () => {
+      const wallet = Wallet.create(network, externalDesc, internalDesc);
+      const blob = wallet.take_staged().encrypt(passphrase);
+
+      // The memory, iterations and parallelism costs follow the magic and version.
+      for (const offset of [5, 9, 13]) {
+        const tampered = blob.slice();
+        tampered[offset + 3] = 0xff;
+        try {
+          ChangeSet.decrypt(tampered, passphrase);
+          fail("expected an error");
+        } catch (error) {
+          expect(error).toBeInstanceOf(BdkError);
+          expect(error.code).toBe(BdkErrorCode.Decryption);
+        }
+      }
+    });
+  });
+});
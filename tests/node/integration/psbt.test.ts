@@ -0,0 +1,279 @@
+import {
+  Address,
+  Amount,
+  BdkError,
+  BdkErrorCode,
+  OutPoint,
+  Psbt,
+  Recipient,
+  SignOptions,
+  Txid,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+
+describe("PSBT version 2", () => {
+  // Valid PSBTv2 from the BIP 370 test vectors: 1 input and 2 outputs.
+  const psbtV2Base64 =
+    "cHNidP8BAgQCAAAAAQQBAQEFAQIB+wQCAAAAAAEOIAsK2SFBnByHGXNdctxzn56p4GONH+TB7vD5lECEgV/IAQ8EAAAAAAABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==";
+  const recipientAddress = Address.from_string(
+    "tb1qd28npep0s8frcm3y7dxqajkcy2m40eysplyr9v",
+    "testnet"
+  );
+
+  it("parses a version 2 PSBT", () => {
+    const psbt = Psbt.from_string(psbtV2Base64);
+
+    expect(psbt.version).toBe(2);
+    const tx = psbt.unsigned_tx;
+    expect(tx.input).toHaveLength(1);
+    expect(tx.tx_in(0).previous_output.toString()).toBe(
+      "c85f81844094f9f0eec1e41f8d63e0a99e9f73dc725d7319871c9c4121d90a0b:0"
+    );
+    expect(tx.output.map((txout) => txout.value.to_sat())).toEqual([
+      BigInt(241160),
+      BigInt(199998859),
+    ]);
+    expect(psbt.inputs_modifiable).toBe(false);
+    expect(psbt.outputs_modifiable).toBe(false);
+  });
+
+  it("converts between version 0 and version 2", () => {
+    const v2 = Psbt.from_string(psbtV2Base64);
+
+    const v0 = v2.to_v0();
+    expect(v0.version).toBe(0);
+    expect(Psbt.from_string(v0.toString()).version).toBe(0);
+    expect(v0.unsigned_tx.compute_txid().toString()).toBe(
+      v2.unsigned_tx.compute_txid().toString()
+    );
+
+    const backToV2 = v0.to_v2();
+    expect(backToV2.version).toBe(2);
+    expect(Psbt.from_string(backToV2.toString()).toString()).toBe(
+      backToV2.toString()
+    );
+  });
+
+  it("adds inputs and outputs when modifiable", () => {
+    const psbt = Psbt.from_string(psbtV2Base64);
+    // wasm-bindgen takes ownership of the outpoint, so each call gets a fresh one.
+    const outpoint = () =>
+      new OutPoint(
+        Txid.from_string(
+          "0000000000000000000000000000000000000000000000000000000000000001"
+        ),
+        1
+      );
+
+    expect(() => psbt.add_input(outpoint())).toThrow(BdkError);
+    psbt.set_tx_modifiable(true, true);
+    psbt.add_input(outpoint(), 0xfffffffd);
+    psbt.add_output(
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+    );
+
+    // The constructed inputs and outputs survive a round trip.
+    const parsed = Psbt.from_string(psbt.toString());
+    expect(parsed.inputs_modifiable).toBe(true);
+    expect(parsed.inputs).toHaveLength(2);
+    expect(parsed.unsigned_tx.tx_in(1).sequence).toBe(0xfffffffd);
+    expect(parsed.outputs).toHaveLength(3);
+    expect(parsed.unsigned_tx.tx_out(2).value.to_sat()).toBe(BigInt(1000));
+
+    // Version 0 PSBTs have no modifiable flags.
+    expect(parsed.to_v0().inputs_modifiable).toBe(false);
+  });
+
+  describe("with signatures", () => {
+    // The BIP 370 test vector with modifiable inputs and outputs and a key path signature of
+    // input 0, whose sighash type is the last byte of the key.
+    const signedPsbts = {
+      all:
+        "cHNidP8BAgQCAAAAAQQBAQEFAQIBBgEDAfsEAgAAAAABDiALCtkhQZwchxlzXXLcc5+eqeBjjR/kwe7w+ZRAhIFfyAEPBAAAAAABE0EHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHAQABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==",
+      allAnyoneCanPay:
+        "cHNidP8BAgQCAAAAAQQBAQEFAQIBBgEDAfsEAgAAAAABDiALCtkhQZwchxlzXXLcc5+eqeBjjR/kwe7w+ZRAhIFfyAEPBAAAAAABE0EHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHgQABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==",
+      noneAnyoneCanPay:
+        "cHNidP8BAgQCAAAAAQQBAQEFAQIBBgEDAfsEAgAAAAABDiALCtkhQZwchxlzXXLcc5+eqeBjjR/kwe7w+ZRAhIFfyAEPBAAAAAABE0EHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHggABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==",
+      singleAnyoneCanPay:
+        "cHNidP8BAgQCAAAAAQQBAQEFAQIBBgEDAfsEAgAAAAABDiALCtkhQZwchxlzXXLcc5+eqeBjjR/kwe7w+ZRAhIFfyAEPBAAAAAABE0EHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHgwABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==",
+    };
+    // The same PSBT with a `SIGHASH_SINGLE | SIGHASH_ANYONECANPAY` signature and no outputs.
+    const singleWithoutOutputs =
+      "cHNidP8BAgQCAAAAAQQBAQEFAQABBgEDAfsEAgAAAAABDiALCtkhQZwchxlzXXLcc5+eqeBjjR/kwe7w+ZRAhIFfyAEPBAAAAAABE0EHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHgwA=";
+    const outpoint = () =>
+      new OutPoint(
+        Txid.from_string(
+          "0000000000000000000000000000000000000000000000000000000000000002"
+        ),
+        0
+      );
+    const recipient = () =>
+      new Recipient(
+        recipientAddress.script_pubkey,
+        Amount.from_sat(BigInt(1000))
+      );
+
+    function expectNotModifiable(add: () => void, message: string) {
+      try {
+        add();
+        fail("expected an error");
+      } catch (error) {
+        expect(error).toBeInstanceOf(BdkError);
+        expect((error as BdkError).code).toBe(BdkErrorCode.PsbtNotModifiable);
+        expect((error as BdkError).message).toContain(message);
+      }
+    }
+
+    it("rejects inputs when a signature commits to all inputs", () => {
+      const psbt = Psbt.from_string(signedPsbts.all);
+
+      expectNotModifiable(() => psbt.add_input(outpoint()), "all the inputs");
+      expect(psbt.inputs).toHaveLength(1);
+
+      const anyoneCanPay = Psbt.from_string(signedPsbts.allAnyoneCanPay);
+      anyoneCanPay.add_input(outpoint());
+      expect(anyoneCanPay.inputs).toHaveLength(2);
+    });
+
+    it("rejects outputs when a signature commits to all outputs", () => {
+      for (const base64 of [signedPsbts.all, signedPsbts.allAnyoneCanPay]) {
+        const psbt = Psbt.from_string(base64);
+        expectNotModifiable(
+          () => psbt.add_output(recipient()),
+          "all the outputs"
+        );
+        expect(psbt.outputs).toHaveLength(2);
+      }
+
+      const none = Psbt.from_string(signedPsbts.noneAnyoneCanPay);
+      none.add_output(recipient());
+      expect(none.outputs).toHaveLength(3);
+    });
+
+    it("rejects outputs paired with a SIGHASH_SINGLE input", () => {
+      const psbt = Psbt.from_string(singleWithoutOutputs);
+      expectNotModifiable(
+        () => psbt.add_output(recipient()),
+        "SIGHASH_SINGLE"
+      );
+      expect(psbt.outputs).toHaveLength(0);
+
+      // Input 0 is already paired with output 0, so outputs can be appended.
+      const paired = Psbt.from_string(signedPsbts.singleAnyoneCanPay);
+      paired.add_output(recipient());
+      expect(paired.outputs).toHaveLength(3);
+    });
+
+    it("updates the modifiable flags from the signatures", () => {
+      const all = Psbt.from_string(signedPsbts.all);
+      all.set_tx_modifiable(true, true);
+      expect(all.inputs_modifiable).toBe(false);
+      expect(all.outputs_modifiable).toBe(false);
+
+      const allAnyoneCanPay = Psbt.from_string(signedPsbts.allAnyoneCanPay);
+      allAnyoneCanPay.set_tx_modifiable(true, true);
+      expect(allAnyoneCanPay.inputs_modifiable).toBe(true);
+      expect(allAnyoneCanPay.outputs_modifiable).toBe(false);
+
+      const single = Psbt.from_string(signedPsbts.singleAnyoneCanPay);
+      expect(single.has_sighash_single).toBe(false);
+      single.set_tx_modifiable(true, true);
+      expect(single.inputs_modifiable).toBe(true);
+      expect(single.outputs_modifiable).toBe(true);
+      expect(single.has_sighash_single).toBe(true);
+      expect(Psbt.from_string(single.toString()).has_sighash_single).toBe(true);
+    });
+  });
+
+  it("rejects adding to a version 0 PSBT", () => {
+    const psbt = Psbt.from_string(psbtV2Base64).to_v0();
+
+    try {
+      psbt.set_tx_modifiable(true, true);
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect((error as BdkError).code).toBe(BdkErrorCode.PsbtNotModifiable);
+    }
+  });
+});
+
+describe("PSBT roles for foreign PSBTs", () => {
+  const wallet = Wallet.create(
+    "testnet",
+    "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p",
+    "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/1/*)#dd6w3a4e"
+  );
+  // A PSBT with a single input of another wallet, without its UTXO data.
+  const foreignPsbt = () =>
+    Psbt.from_string(
+      "cHNidP8BAgQCAAAAAQQBAQEFAQIB+wQCAAAAAAEOIAsK2SFBnByHGXNdctxzn56p4GONH+TB7vD5lECEgV/IAQ8EAAAAAAABAwgIrgMAAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA=="
+    );
+
+  it("reports foreign inputs instead of failing", () => {
+    expect(() => wallet.sign(foreignPsbt(), new SignOptions())).toThrow();
+
+    const psbt = foreignPsbt();
+    const [status] = wallet.sign_owned_inputs(psbt, new SignOptions());
+    expect(status.index).toBe(0);
+    expect(status.is_mine).toBe(false);
+    expect(status.signed).toBe(false);
+    expect(status.finalized).toBe(false);
+    expect(status.error).toBeDefined();
+    expect(psbt.inputs[0].partial_sigs).toHaveLength(0);
+  });
+
+  it("reports the inputs that cannot be finalized", () => {
+    const [status] = wallet.finalize_psbt_inputs(
+      foreignPsbt(),
+      new SignOptions()
+    );
+    expect(status.finalized).toBe(false);
+    expect(status.error).toBeDefined();
+  });
+
+  it("warns about foreign inputs without UTXO data", () => {
+    const analysis = wallet.analyze_psbt(foreignPsbt());
+
+    expect(analysis.inputs[0].is_mine).toBe(false);
+    expect(analysis.inputs[0].value).toBeUndefined();
+    expect(analysis.fee).toBeUndefined();
+    expect(analysis.outputs.map((output) => output.kind)).toEqual([
+      "send",
+      "send",
+    ]);
+
+    const warnings = analysis.warnings;
+    expect(warnings.map((warning) => warning.code)).toEqual([
+      "missing_utxo",
+      "external_output",
+      "external_output",
+    ]);
+    expect(warnings[0].input_index).toBe(0);
+    expect(warnings[2].output_index).toBe(1);
+  });
+
+  it("rejects external signers without signing callbacks", () => {
+    try {
+      wallet.add_external_signer("d34db33f", {});
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect((error as BdkError).code).toBe(BdkErrorCode.Signer);
+    }
+  });
+
+  it("does not call external signers for keys they do not hold", async () => {
+    const signEcdsa = jest.fn();
+    wallet.add_external_signer("d34db33f", {
+      signEcdsa,
+      signSchnorr: jest.fn(),
+    });
+
+    const psbt = foreignPsbt();
+    const options = new SignOptions();
+    options.trust_witness_utxo = true;
+    await expect(wallet.sign_async(psbt, options)).resolves.toBe(false);
+    expect(signEcdsa).not.toHaveBeenCalled();
+  });
+});
@@ -1,6 +1,16 @@
 import {
   AddressType,
+  BdkError,
+  BdkErrorCode,
+  DescriptorOptions,
+  Mnemonic,
   Network,
+  bip85_hex,
+  bip85_mnemonic,
+  bip85_password,
+  bip85_wif,
+  bip85_xpriv,
+  mnemonic_to_descriptor,
   seed_to_descriptor,
   seed_to_xpriv,
   xpriv_to_descriptor,
@@ -71,4 +81,217 @@ describe("Utilities", () => {
       "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/1/*)#ltuly67e"
     );
   });
+
+  it("builds P2WSH and legacy P2SH descriptors", () => {
+    // Single key P2WSH and legacy P2SH have no standard path
+    expect(() => seed_to_descriptor(seed, network, "p2wsh")).toThrow();
+    const wshOptions = new DescriptorOptions();
+    wshOptions.derivation_path = "m/48'/1'/0'/2'";
+    expect(seed_to_descriptor(seed, network, "p2wsh", wshOptions).external).toBe(
+      "wsh(pk(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/48'/1'/0'/2'/0/*))#rvs5cfk0"
+    );
+
+    // P2SH defaults to BIP49 P2SH-wrapped P2WPKH
+    expect(seed_to_descriptor(seed, network, "p2sh").external).toBe(
+      "sh(wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/49'/1'/0'/0/*))#lypzwjnr"
+    );
+    const options = new DescriptorOptions();
+    options.legacy_p2sh = true;
+    expect(() => seed_to_descriptor(seed, network, "p2sh", options)).toThrow();
+    options.derivation_path = "m/45'/1'/0'";
+    expect(seed_to_descriptor(seed, network, "p2sh", options).external).toBe(
+      "sh(pk(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/45'/1'/0'/0/*))#e23aa2mv"
+    );
+  });
+
+  it("builds descriptors of other accounts and derivation paths", () => {
+    const accountOptions = new DescriptorOptions();
+    accountOptions.account = 1;
+    expect(
+      seed_to_descriptor(seed, network, addressType, accountOptions).internal
+    ).toBe(
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/1'/1/*)#8kdg9nny"
+    );
+
+    const xpub =
+      "tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq";
+    const pathOptions = new DescriptorOptions();
+    pathOptions.derivation_path = "m/84'/1'/0'";
+    expect(
+      xpub_to_descriptor(xpub, "27f9035f", network, addressType, pathOptions)
+        .external
+    ).toBe(
+      "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/0/*)#wle7e0wp"
+    );
+    const wshOptions = new DescriptorOptions();
+    wshOptions.derivation_path = "m/48'/1'/0'/2'";
+    expect(
+      xpub_to_descriptor(xpub, "27f9035f", network, "p2wsh", wshOptions)
+        .external
+    ).toBe(
+      "wsh(pk([27f9035f/48'/1'/0'/2']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/0/*))#m5jajdam"
+    );
+
+    // Options are borrowed and can be reused across calls
+    const reused = seed_to_descriptor(
+      seed,
+      network,
+      addressType,
+      accountOptions
+    );
+    expect(reused.internal).toBe(
+      seed_to_descriptor(seed, network, addressType, accountOptions).internal
+    );
+    expect(accountOptions.account).toBe(1);
+
+    const invalidOptions = new DescriptorOptions();
+    invalidOptions.derivation_path = "m/84'/invalid";
+    expect(() =>
+      seed_to_descriptor(seed, network, addressType, invalidOptions)
+    ).toThrow();
+  });
+});
+
+describe("Mnemonic", () => {
+  const phrase =
+    "journey embrace permit coil indoor stereo welcome maid movie easy clock spider tent slush bright luxury awake waste legal modify awkward answer acid goose";
+
+  it("generates mnemonics of the requested length", () => {
+    for (const wordCount of [12, 15, 18, 21, 24]) {
+      const mnemonic = Mnemonic.generate(wordCount);
+      expect(mnemonic.word_count).toBe(wordCount);
+      expect(mnemonic.language).toBe("english");
+      expect(Mnemonic.from_string(mnemonic.toString()).toString()).toBe(
+        mnemonic.toString()
+      );
+    }
+    expect(Mnemonic.generate(12).toString()).not.toBe(
+      Mnemonic.generate(12).toString()
+    );
+    expect(() => Mnemonic.generate(13)).toThrow(BdkError);
+  });
+
+  it("encodes entropy in every language", () => {
+    const entropy = new Uint8Array(16);
+    const english = Mnemonic.from_entropy(entropy);
+    expect(english.toString()).toBe(
+      "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+    );
+
+    const spanish = Mnemonic.from_entropy(entropy.fill(7), "spanish");
+    expect(spanish.language).toBe("spanish");
+    const parsed = Mnemonic.from_string(spanish.toString());
+    expect(parsed.language).toBe("spanish");
+    expect(parsed.entropy).toEqual(entropy);
+  });
+
+  it("validates the checksum", () => {
+    try {
+      Mnemonic.from_string(phrase.replace("goose", "abandon"));
+      fail("expected an error");
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect((error as BdkError).code).toBe(BdkErrorCode.InvalidChecksum);
+    }
+
+    try {
+      Mnemonic.from_string(phrase.replace("goose", "gooze"));
+      fail("expected an error");
+    } catch (error) {
+      expect((error as BdkError).code).toBe(BdkErrorCode.UnknownWord);
+      expect((error as BdkError).data).toBe(23);
+    }
+  });
+
+  it("derives the seed with an optional passphrase", () => {
+    const mnemonic = Mnemonic.from_string(phrase);
+    expect(Buffer.from(mnemonic.to_seed())).toEqual(mnemonicToSeedSync(phrase));
+    expect(Buffer.from(mnemonic.to_seed("TREZOR"))).toEqual(
+      mnemonicToSeedSync(phrase, "TREZOR")
+    );
+
+    // BIP39 test vector
+    const vector = Mnemonic.from_entropy(new Uint8Array(16));
+    expect(Buffer.from(vector.to_seed("TREZOR")).toString("hex")).toBe(
+      "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+    );
+  });
+
+  it("generates descriptors from a mnemonic", () => {
+    const mnemonic = Mnemonic.from_string(phrase);
+    const descriptors = mnemonic_to_descriptor(
+      mnemonic,
+      undefined,
+      "testnet",
+      "p2wpkh"
+    );
+
+    expect(descriptors.external).toBe(
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p"
+    );
+
+    const withPassphrase = mnemonic_to_descriptor(
+      mnemonic,
+      "passphrase",
+      "testnet",
+      "p2wpkh"
+    );
+    expect(withPassphrase.external).toBe(
+      seed_to_descriptor(
+        mnemonicToSeedSync(phrase, "passphrase"),
+        "testnet",
+        "p2wpkh"
+      ).external
+    );
+  });
+});
+
+describe("BIP85", () => {
+  // Master key of the BIP85 test vectors
+  const xpriv =
+    "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+  it("derives child mnemonics", () => {
+    expect(bip85_mnemonic(xpriv, 12, 0).toString()).toBe(
+      "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+    );
+    expect(bip85_mnemonic(xpriv, 18, 0).toString()).toBe(
+      "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token"
+    );
+    expect(bip85_mnemonic(xpriv, 24, 0).toString()).toBe(
+      "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano"
+    );
+    expect(bip85_mnemonic(xpriv, 12, 1).toString()).not.toBe(
+      bip85_mnemonic(xpriv, 12, 0).toString()
+    );
+    expect(bip85_mnemonic(xpriv, 12, 0, "french").language).toBe("french");
+    expect(() => bip85_mnemonic(xpriv, 15, 0)).toThrow();
+  });
+
+  it("derives child keys", () => {
+    expect(bip85_wif(xpriv, 0)).toBe(
+      "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+    );
+    expect(bip85_xpriv(xpriv, 0)).toBe(
+      "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX"
+    );
+  });
+
+  it("derives hex entropy and passwords", () => {
+    expect(bip85_hex(xpriv, 64, 0)).toBe(
+      "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c"
+    );
+    expect(bip85_password(xpriv, 21, 0)).toBe("dKLoepugzdVJvdL56ogNV");
+    expect(() => bip85_hex(xpriv, 8, 0)).toThrow();
+    expect(() => bip85_password(xpriv, 100, 0)).toThrow();
+  });
+
+  it("derives from the master key of a seed", () => {
+    const seed = Mnemonic.from_entropy(new Uint8Array(16)).to_seed();
+    const child = bip85_mnemonic(seed_to_xpriv(seed, "bitcoin"), 12, 0);
+    expect(child.word_count).toBe(12);
+    expect(Mnemonic.from_string(child.toString()).toString()).toBe(
+      child.toString()
+    );
+  });
 });